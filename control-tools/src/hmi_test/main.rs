@@ -1,6 +1,7 @@
 use heapless::Vec;
 use hoshiguma_api::{
-    API_PORT, DesiredMachinePower, HMI_IP_ADDRESS, Interlock, MachineRun, Message, Severity,
+    API_PORT, DesiredMachinePower, FumeExtractionMode, HMI_IP_ADDRESS, Interlock, MachineRun,
+    Message, Severity,
     hmi::{AccessControlRawInput, OnscreenMessage, Screen, StatusScreenInfo, from_hmi, to_hmi},
 };
 use hoshiguma_api_client::{message_handler, send_request};
@@ -50,6 +51,15 @@ async fn main() {
                     info!("Notify: access control state changed: {payload:?}");
                     Message::new(&from_hmi::response::AckAccessControlStateChanged(payload.0))
                         .unwrap()
+                } else if msg
+                    .payload::<from_hmi::request::ToggleFumeExtractionMode>()
+                    .is_ok()
+                {
+                    info!("Request: toggle fume extraction mode");
+                    Message::new(&from_hmi::response::FumeExtractionModeChanged(
+                        FumeExtractionMode::OverrideRun,
+                    ))
+                    .unwrap()
                 } else {
                     warn!("Notify: unknown message type");
                     Message::new(&from_hmi::response::ApiError).unwrap()
@@ -71,6 +81,7 @@ async fn main() {
                     machine_power: DesiredMachinePower::On,
                     interlock: Interlock::OperationPermitted,
                     running: MachineRun::Idle,
+                    fume_extraction_mode: FumeExtractionMode::Automatic,
                     messages: Vec::from([
                         OnscreenMessage {
                             text: "I'm information".try_into().unwrap(),
@@ -93,6 +104,8 @@ async fn main() {
                             severity: Severity::Fatal,
                         },
                     ]),
+                    grace_period_remaining: None,
+                    session_hold_remaining: None,
                 }),
             )
             .await
@@ -117,6 +130,7 @@ async fn main() {
                     machine_power: DesiredMachinePower::On,
                     interlock: Interlock::OperationPermitted,
                     running: MachineRun::Idle,
+                    fume_extraction_mode: FumeExtractionMode::Automatic,
                     messages: Vec::from([
                         OnscreenMessage {
                             text: "Telemetry INOP".try_into().unwrap(),
@@ -143,6 +157,8 @@ async fn main() {
                             severity: Severity::Warning,
                         },
                     ]),
+                    grace_period_remaining: None,
+                    session_hold_remaining: None,
                 }),
             )
             .await
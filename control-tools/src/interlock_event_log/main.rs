@@ -0,0 +1,61 @@
+use clap::Parser;
+use hoshiguma_api::{
+    API_PORT, ORCHESTRATOR_IP_ADDRESS,
+    orchestrator::{InterlockEvent, request},
+};
+use hoshiguma_api_client::send_request;
+use tokio::net::TcpStream;
+
+/// Dump the interlock event log held by the orchestrator, oldest event first.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Index of the first event to dump, the oldest event held if not given
+    #[arg(long, default_value_t = 0)]
+    start_index: u32,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let args = Args::parse();
+
+    let mut stream = TcpStream::connect((ORCHESTRATOR_IP_ADDRESS, API_PORT))
+        .await
+        .unwrap();
+
+    let mut start_index = args.start_index;
+
+    loop {
+        let response = send_request(&mut stream, request::GetInterlockEventLog { start_index })
+            .await
+            .unwrap();
+        let page = response.0;
+
+        if start_index < page.oldest_index {
+            println!(
+                "{} events evicted before index {}",
+                page.oldest_index - start_index,
+                page.oldest_index
+            );
+        }
+
+        for event in page.events.iter() {
+            print_event(event);
+        }
+
+        match page.events.last() {
+            Some(last) if last.index + 1 < page.next_index => start_index = last.index + 1,
+            _ => break,
+        }
+    }
+}
+
+fn print_event(event: &InterlockEvent) {
+    let time = match event.wall_time {
+        Some(wall_time) => wall_time.to_rfc3339(),
+        None => format!("uptime {:?}", event.uptime),
+    };
+
+    println!("{:>6}  {}  {:?}", event.index, time, event.kind);
+}
@@ -0,0 +1,118 @@
+use clap::Parser;
+use hoshiguma_api::{API_PORT, ORCHESTRATOR_IP_ADDRESS, orchestrator::request};
+use hoshiguma_api_client::send_request;
+use tokio::net::TcpStream;
+
+/// Show the orchestrator's current view of the machine.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Repeatedly show the state at this interval in seconds, rather than once
+    #[arg(long)]
+    interval: Option<f32>,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let args = Args::parse();
+
+    loop {
+        let mut stream = TcpStream::connect((ORCHESTRATOR_IP_ADDRESS, API_PORT))
+            .await
+            .unwrap();
+
+        let wall_time = send_request(&mut stream, request::GetWallTimeSyncStatus)
+            .await
+            .unwrap()
+            .0;
+        println!(
+            "Uptime: {:?}, wall time: {}, last sync: {:?}",
+            wall_time.uptime,
+            wall_time
+                .wall_time
+                .map_or("not synchronised".to_string(), |time| time.to_string()),
+            wall_time.last_sync
+        );
+
+        let access_control = send_request(&mut stream, request::GetAccessControl)
+            .await
+            .unwrap()
+            .0;
+        println!(
+            "Access control: raw input {:?}, state {:?}",
+            access_control.raw_input, access_control.state
+        );
+
+        let machine_power = send_request(&mut stream, request::GetMachinePower)
+            .await
+            .unwrap()
+            .0;
+        println!("Desired machine power: {machine_power:?}");
+
+        let interlock = send_request(&mut stream, request::GetInterlockState)
+            .await
+            .unwrap()
+            .0;
+        println!(
+            "Interlock: {:?}, action {:?}",
+            interlock.interlock, interlock.action
+        );
+
+        let relays = send_request(&mut stream, request::GetRelayOutputs)
+            .await
+            .unwrap()
+            .0;
+        println!("Relay outputs:");
+        println!("  machine power: {:?}", relays.machine_power);
+        println!("  machine enable: {:?}", relays.machine_enable);
+        println!("  laser enable: {:?}", relays.laser_enable);
+        println!("  air assist pump: {:?}", relays.air_assist_pump);
+        println!("  fume extraction fan: {:?}", relays.fume_extraction_fan);
+
+        let monitors = send_request(&mut stream, request::GetMonitorStates)
+            .await
+            .unwrap()
+            .0;
+        println!("Monitors:");
+        for (monitor, severity) in monitors {
+            println!("  {monitor}: {severity:?}");
+        }
+
+        let temperatures = send_request(&mut stream, request::GetLatestTemperatures)
+            .await
+            .unwrap()
+            .0;
+        println!("Temperatures:");
+        for reading in temperatures {
+            match reading.reading {
+                Ok(temperature) => println!("  {}: {temperature} C", reading.sensor),
+                Err(()) => println!("  {}: failed", reading.sensor),
+            }
+        }
+
+        let coolant_rates = send_request(&mut stream, request::GetCoolantRates)
+            .await
+            .unwrap()
+            .0;
+        println!(
+            "Coolant rates: flow {:?}, return {:?}",
+            coolant_rates.flow, coolant_rates.ret
+        );
+
+        let airflow = send_request(&mut stream, request::GetExtractionAirflow)
+            .await
+            .unwrap()
+            .0;
+        println!("Extraction airflow: {airflow:?}");
+
+        drop(stream);
+
+        match args.interval {
+            Some(interval) => {
+                tokio::time::sleep(std::time::Duration::from_secs_f32(interval)).await
+            }
+            None => break,
+        }
+    }
+}
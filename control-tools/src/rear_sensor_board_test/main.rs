@@ -15,21 +15,25 @@ async fn main() {
                 red: LightPattern::ON,
                 amber: LightPattern::OFF,
                 green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
             },
             StatusLightSettings {
                 red: LightPattern::OFF,
                 amber: LightPattern::ON,
                 green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
             },
             StatusLightSettings {
                 red: LightPattern::OFF,
                 amber: LightPattern::OFF,
                 green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
             },
             StatusLightSettings {
                 red: LightPattern::BLINK_1HZ,
                 amber: LightPattern::BLINK_2HZ,
                 green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
             },
         ];
 
@@ -0,0 +1,92 @@
+use clap::Parser;
+use hoshiguma_api::{
+    API_PORT, ORCHESTRATOR_IP_ADDRESS,
+    orchestrator::{StateMachine, StateMachineSnapshot, request},
+};
+use hoshiguma_api_client::send_request;
+use hoshiguma_state_machines as sm;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use strum::IntoEnumIterator;
+use tokio::net::TcpStream;
+
+/// Dump the current internal state of the state machines running on the orchestrator.
+#[derive(Debug, Parser)]
+struct Args {
+    /// State machines to dump, all of them if none are given
+    machines: Vec<StateMachine>,
+
+    /// Repeatedly dump the state at this interval in seconds, rather than once
+    #[arg(long)]
+    interval: Option<f32>,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let args = Args::parse();
+
+    let machines = if args.machines.is_empty() {
+        StateMachine::iter().collect()
+    } else {
+        args.machines
+    };
+
+    loop {
+        let mut stream = TcpStream::connect((ORCHESTRATOR_IP_ADDRESS, API_PORT))
+            .await
+            .unwrap();
+
+        for machine in machines.iter() {
+            let response = send_request(&mut stream, request::GetStateMachineSnapshot(*machine))
+                .await
+                .unwrap();
+            print_snapshot(&response.0);
+        }
+
+        drop(stream);
+
+        match args.interval {
+            Some(interval) => {
+                tokio::time::sleep(std::time::Duration::from_secs_f32(interval)).await
+            }
+            None => break,
+        }
+    }
+}
+
+fn print_snapshot(snapshot: &StateMachineSnapshot) {
+    println!(
+        "{} (orchestrator uptime {:?}):",
+        snapshot.machine, snapshot.uptime
+    );
+
+    let Some(data) = &snapshot.snapshot else {
+        println!("  no snapshot available");
+        return;
+    };
+
+    match snapshot.machine {
+        StateMachine::AirAssist => decode::<sm::air_assist::Snapshot>(data),
+        StateMachine::CoolantRate => decode::<sm::coolant_rate::Snapshot>(data),
+        StateMachine::Cooling => decode::<sm::cooling::Snapshot>(data),
+        StateMachine::ExtractionAirflow => decode::<sm::extraction_airflow::Snapshot>(data),
+        StateMachine::FumeExtraction => decode::<sm::fume_extraction::Snapshot>(data),
+        StateMachine::HmiStatusScreen => decode::<sm::hmi_status_screen::Snapshot>(data),
+        StateMachine::Interlock => decode::<sm::interlock::Snapshot>(data),
+        StateMachine::JobTracking => decode::<sm::job_tracking::Snapshot>(data),
+        StateMachine::MachinePower => decode::<sm::machine_power::Snapshot>(data),
+        StateMachine::RuntimeCounters => decode::<sm::runtime_counters::Snapshot>(data),
+        StateMachine::StatusLight => decode::<sm::status_light::Snapshot>(data),
+        StateMachine::TemperatureFilter => decode::<sm::temperature_filter::Snapshot>(data),
+        StateMachine::Temperatures => decode::<sm::temperatures::Snapshot>(data),
+    }
+}
+
+fn decode<T: DeserializeOwned + Debug>(data: &[u8]) {
+    match postcard::from_bytes::<T>(data) {
+        Ok(snapshot) => println!("{snapshot:#?}"),
+        Err(e) => println!("  failed to decode snapshot: {e}"),
+    }
+}
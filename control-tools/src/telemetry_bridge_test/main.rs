@@ -1,6 +1,6 @@
 use hoshiguma_api::{
     API_PORT, TELEMETRY_BRIDGE_IP_ADDRESS,
-    telemetry_bridge::{FormattedTelemetryDataPoint, request},
+    telemetry_bridge::{FormattedTelemetryDataPoint, TelemetryDataPointBatch, request},
 };
 use hoshiguma_api_client::send_request;
 use log::info;
@@ -25,6 +25,7 @@ async fn main() {
 
     let b = tokio::spawn(async {
         let mut data_point_count = 0usize;
+        let mut sequence = 0u32;
 
         loop {
             let mut stream = TcpStream::connect((TELEMETRY_BRIDGE_IP_ADDRESS, API_PORT))
@@ -43,6 +44,24 @@ async fn main() {
                 .unwrap();
                 data_point_count += 1;
             }
+
+            let mut batch = TelemetryDataPointBatch::default();
+            while batch
+                .push(FormattedTelemetryDataPoint(
+                    "some_batched_data_point,with=lots,of=extra stuff=\"added\",number=42 1234567890"
+                        .try_into()
+                        .unwrap(),
+                ))
+                .is_ok()
+            {}
+            data_point_count += batch.len();
+            sequence = sequence.wrapping_add(1);
+            send_request(
+                &mut stream,
+                request::SendTelemetryDataPointBatch { sequence, batch },
+            )
+            .await
+            .unwrap();
             drop(stream);
 
             info!("Number of telemetry points sent: {data_point_count}");
@@ -2,7 +2,7 @@ use crate::{CompressorResources, api::NUM_LISTENERS};
 use defmt::{Format, warn};
 use embassy_rp::gpio::{Level, Output};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_time::{Duration, with_timeout};
+use embassy_time::{Duration, Instant, with_timeout};
 use hoshiguma_api::cooler::CompressorState;
 use hoshiguma_common::bidir_channel::{BiDirectionalChannel, BiDirectionalChannelSides};
 
@@ -49,19 +49,42 @@ impl CompressorInterfaceChannel for TheirChannelSide {
     }
 }
 
+/// Time after boot before the compressor is permitted to start.
+const START_DELAY: Duration = Duration::from_secs(10);
+
+/// Minimum time the compressor is stopped for before being restarted.
+///
+/// The cooling logic on the orchestrator enforces a longer minimum off time, this is slightly
+/// shorter so that network latency does not cause legitimate requests to be refused.
+const MINIMUM_OFF_TIME: Duration = Duration::from_secs(150);
+
 #[embassy_executor::task]
 pub(crate) async fn task(r: CompressorResources, comm: [MyChannelSide; NUM_LISTENERS]) -> ! {
     let mut output = Output::new(r.relay, Level::Low);
+    let mut stopped_at: Option<Instant> = None;
 
     loop {
         let rx_futures: [_; NUM_LISTENERS] = comm.each_ref().map(|f| f.receive());
         let (msg, idx) = embassy_futures::select::select_array(rx_futures).await;
 
-        if let Request::Set(state) = msg {
-            output.set_level(match state {
-                CompressorState::Idle => Level::Low,
-                CompressorState::Run => Level::High,
-            });
+        // Starting the compressor is subject to anti-short-cycle limits, stopping it is always permitted
+        match msg {
+            Request::Set(CompressorState::Run) if output.is_set_low() => {
+                let now = Instant::now();
+
+                if now < Instant::MIN + START_DELAY {
+                    warn!("Compressor start refused, within start delay");
+                } else if stopped_at.is_some_and(|t| now < t + MINIMUM_OFF_TIME) {
+                    warn!("Compressor start refused, within minimum off time");
+                } else {
+                    output.set_high();
+                }
+            }
+            Request::Set(CompressorState::Idle) if output.is_set_high() => {
+                output.set_low();
+                stopped_at = Some(Instant::now());
+            }
+            _ => {}
         }
 
         let state = match output.get_output_level() {
@@ -94,6 +94,10 @@ pub(super) async fn notify_task(stack: Stack<'static>, id: usize) {
                 )
                 .await;
             }
+            Notification::ToggleFumeExtractionMode => {
+                send_notification_and_validate(stack, from_hmi::request::ToggleFumeExtractionMode)
+                    .await;
+            }
         };
     }
 }
@@ -96,6 +96,7 @@ enum Notification {
     PanelInteraction,
     AccessControlInputChanged(AccessControlRawInput),
     AccessControlStateChanged(AccessControlState),
+    ToggleFumeExtractionMode,
 }
 
 static COMM_GOOD_INDICATOR: Channel<CriticalSectionRawMutex, (), 8> = Channel::new();
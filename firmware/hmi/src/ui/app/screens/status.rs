@@ -1,12 +1,19 @@
 use super::UpdateAction;
-use crate::ui::{SoftButton, app::screens::draw_buttons};
+use crate::{
+    Notification,
+    api::NOTIFICATIONS,
+    ui::{SoftButton, app::screens::draw_buttons},
+};
+use core::{fmt::Write, time::Duration};
+use defmt::warn;
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
     watch::{Receiver, Watch},
 };
 use embedded_graphics::prelude::Point;
+use heapless::String;
 use hoshiguma_api::{
-    DesiredMachinePower, Interlock, MachineRun, Severity,
+    DesiredMachinePower, FumeExtractionMode, Interlock, MachineRun, Severity,
     hmi::{AccessControlRawInput, Screen, StatusScreenInfo},
 };
 use ratatui::{
@@ -48,6 +55,7 @@ impl super::ScreenExt for StatusScreen {
                     Constraint::Length(1),
                     Constraint::Length(3),
                     Constraint::Length(3),
+                    Constraint::Length(3),
                     Constraint::Fill(1),
                     Constraint::Length(3),
                 ]
@@ -70,7 +78,11 @@ impl super::ScreenExt for StatusScreen {
             status_layout_1[0],
         );
 
-        f.render_widget(render_machine_power(self.info.as_ref()), status_layout_1[1]);
+        let mut power_countdown = String::new();
+        f.render_widget(
+            render_machine_power(self.info.as_ref(), &mut power_countdown),
+            status_layout_1[1],
+        );
 
         let status_layout_2 = Layout::default()
             .direction(Direction::Horizontal)
@@ -79,16 +91,25 @@ impl super::ScreenExt for StatusScreen {
 
         f.render_widget(render_interlock(self.info.as_ref()), status_layout_2[0]);
 
-        f.render_widget(render_status(self.info.as_ref()), status_layout_2[1]);
+        let mut countdown = String::new();
+        f.render_widget(
+            render_status(self.info.as_ref(), &mut countdown),
+            status_layout_2[1],
+        );
 
-        f.render_widget(render_messages(self.info.as_ref()), vertical_layout[4]);
+        f.render_widget(
+            render_fume_extraction(self.info.as_ref()),
+            vertical_layout[4],
+        );
+
+        f.render_widget(render_messages(self.info.as_ref()), vertical_layout[5]);
 
         draw_buttons(
             f,
-            vertical_layout[5],
+            vertical_layout[6],
             [
                 ("PAGE", Style::default().white()),
-                ("", Style::default().gray()),
+                ("EXTR", Style::default().white()),
                 ("", Style::default().gray()),
             ],
         );
@@ -97,6 +118,15 @@ impl super::ScreenExt for StatusScreen {
     fn handle_touch(&mut self, event: (Point, Option<SoftButton>)) -> UpdateAction {
         match event.1 {
             Some(SoftButton::A) => UpdateAction::ChangeToScreen(Screen::HmiInfo),
+            Some(SoftButton::B) => {
+                if NOTIFICATIONS
+                    .try_send(Notification::ToggleFumeExtractionMode)
+                    .is_err()
+                {
+                    warn!("Notification queue full, fume extraction mode not toggled");
+                }
+                UpdateAction::Nothing
+            }
             _ => UpdateAction::Nothing,
         }
     }
@@ -131,11 +161,32 @@ fn render_access_control<'a>(info: Option<&'a StatusScreenInfo>) -> Paragraph<'a
         .block(var_block("Acc. Ctrl."))
 }
 
-fn render_machine_power<'a>(info: Option<&'a StatusScreenInfo>) -> Paragraph<'a> {
+/// Formats a countdown as `<label> m:ss`, falling back to just the label if that does not fit.
+fn format_countdown(countdown: &mut String<16>, label: &str, remaining: Duration) {
+    let secs = remaining.as_secs();
+    if countdown
+        .write_fmt(format_args!("{label} {}:{:02}", secs / 60, secs % 60))
+        .is_err()
+    {
+        countdown.clear();
+        let _ = countdown.push_str(label);
+    }
+}
+
+fn render_machine_power<'a>(
+    info: Option<&'a StatusScreenInfo>,
+    countdown: &'a mut String<16>,
+) -> Paragraph<'a> {
     let text = match info {
-        Some(info) => match info.machine_power {
-            DesiredMachinePower::Off => "Off".white(),
-            DesiredMachinePower::On => "On".yellow(),
+        Some(info) => match (info.machine_power, info.session_hold_remaining) {
+            (DesiredMachinePower::Off, _) => "Off".white(),
+            (DesiredMachinePower::On, None) => "On".yellow(),
+            // Show how long the job has left before power is removed
+            (DesiredMachinePower::On, Some(remaining)) => {
+                format_countdown(countdown, "Off", remaining);
+                let countdown: &'a String<16> = countdown;
+                countdown.as_str().black().on_yellow()
+            }
         },
         None => "NO DATA".on_magenta(),
     };
@@ -159,11 +210,20 @@ fn render_interlock<'a>(info: Option<&'a StatusScreenInfo>) -> Paragraph<'a> {
         .block(var_block("Interlock"))
 }
 
-fn render_status<'a>(info: Option<&'a StatusScreenInfo>) -> Paragraph<'a> {
+fn render_status<'a>(
+    info: Option<&'a StatusScreenInfo>,
+    countdown: &'a mut String<16>,
+) -> Paragraph<'a> {
     let text = match info {
-        Some(info) => match info.running {
-            MachineRun::Idle => "Idle".white(),
-            MachineRun::Running => "Running".yellow(),
+        Some(info) => match (info.running, info.grace_period_remaining) {
+            (MachineRun::Idle, _) => "Idle".white(),
+            (MachineRun::Running, None) => "Running".yellow(),
+            // Show how long the job has left before the machine is disabled
+            (MachineRun::Running, Some(remaining)) => {
+                format_countdown(countdown, "Stop", remaining);
+                let countdown: &'a String<16> = countdown;
+                countdown.as_str().black().on_yellow()
+            }
         },
         None => "NO DATA".on_magenta(),
     };
@@ -171,6 +231,20 @@ fn render_status<'a>(info: Option<&'a StatusScreenInfo>) -> Paragraph<'a> {
     Paragraph::new(text).centered().block(var_block("Status"))
 }
 
+fn render_fume_extraction<'a>(info: Option<&'a StatusScreenInfo>) -> Paragraph<'a> {
+    let text = match info {
+        Some(info) => match info.fume_extraction_mode {
+            FumeExtractionMode::Automatic => "Auto".white(),
+            FumeExtractionMode::OverrideRun => "Override Run".black().on_yellow(),
+        },
+        None => "NO DATA".on_magenta(),
+    };
+
+    Paragraph::new(text)
+        .centered()
+        .block(var_block("Extraction"))
+}
+
 fn render_messages<'a>(info: Option<&'a StatusScreenInfo>) -> Paragraph<'a> {
     let t = match info {
         Some(info) => {
@@ -72,3 +72,35 @@ impl Sub for CoolantRate {
         Self::new(self.into_inner() - rhs.into_inner())
     }
 }
+
+/// A volume of coolant in litres.
+#[nutype(
+    const_fn,
+    default = 0.0,
+    derive(
+        Default,
+        Debug,
+        Clone,
+        Copy,
+        PartialEq,
+        PartialOrd,
+        Serialize,
+        Deserialize,
+    ),
+    derive_unchecked(Format)
+)]
+pub struct CoolantVolume(f64);
+
+impl CoolantVolume {
+    pub fn from_pulses(pulses: u64, pulses_per_litre: f64) -> Self {
+        Self::new((pulses as f64) / pulses_per_litre)
+    }
+}
+
+impl Sub for CoolantVolume {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.into_inner() - rhs.into_inner())
+    }
+}
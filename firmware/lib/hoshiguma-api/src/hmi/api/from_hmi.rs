@@ -26,6 +26,19 @@ pub mod request {
         NotifyAccessControlStateChanged,
         super::response::AckAccessControlStateChanged
     );
+
+    crate::define_message!(ToggleFumeExtractionMode, (), b"hmi/f/q/fm");
+    crate::define_request_response!(
+        ToggleFumeExtractionMode,
+        super::response::FumeExtractionModeChanged
+    );
+    impl crate::ResponseVerification<super::response::FumeExtractionModeChanged>
+        for ToggleFumeExtractionMode
+    {
+        fn verify_response(&self, _: &super::response::FumeExtractionModeChanged) -> bool {
+            true
+        }
+    }
 }
 
 pub mod response {
@@ -36,4 +49,6 @@ pub mod response {
     crate::define_message!(AckAccessControlInputChanged, (pub super::super::super::AccessControlRawInput), b"hmi/f/p/ai");
 
     crate::define_message!(AckAccessControlStateChanged, (pub super::super::super::AccessControlState), b"hmi/f/p/as");
+
+    crate::define_message!(FumeExtractionModeChanged, (pub crate::FumeExtractionMode), b"hmi/f/p/fm");
 }
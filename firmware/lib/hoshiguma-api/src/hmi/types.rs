@@ -1,4 +1,5 @@
-use crate::{DesiredMachinePower, Interlock, MachineRun, Severity};
+use crate::{DesiredMachinePower, FumeExtractionMode, Interlock, MachineRun, Severity};
+use core::time::Duration;
 use defmt::Format;
 use heapless::{String, Vec};
 use serde::{Deserialize, Serialize};
@@ -45,7 +46,12 @@ pub struct StatusScreenInfo {
     pub machine_power: DesiredMachinePower,
     pub interlock: Interlock,
     pub running: MachineRun,
+    pub fume_extraction_mode: FumeExtractionMode,
     pub messages: Vec<OnscreenMessage, 8>,
+    /// Time remaining before a job running under `Interlock::OperationPermittedUntilIdle` is stopped.
+    pub grace_period_remaining: Option<Duration>,
+    /// Time remaining before machine power is removed, after access ended during a job.
+    pub session_hold_remaining: Option<Duration>,
 }
 
 #[derive(Debug, Format, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -11,5 +11,6 @@ pub use types::*;
 
 pub mod cooler;
 pub mod hmi;
+pub mod orchestrator;
 pub mod rear_sensor_board;
 pub mod telemetry_bridge;
@@ -0,0 +1,160 @@
+pub mod request {
+    crate::define_message!(GetInterlockEventLog, { pub start_index: u32 }, b"orc/t/q/el");
+    crate::define_request_response!(GetInterlockEventLog, super::response::InterlockEventLog);
+
+    crate::define_message!(SetFumeExtractionMode, (pub crate::FumeExtractionMode), b"orc/t/q/fm");
+    crate::define_request_response!(SetFumeExtractionMode, super::response::FumeExtractionMode);
+    crate::basic_state_response_verification!(
+        SetFumeExtractionMode,
+        super::response::FumeExtractionMode
+    );
+
+    crate::define_message!(GetRecentJobs, (), b"orc/t/q/jb");
+    crate::define_request_response!(GetRecentJobs, super::response::RecentJobs);
+
+    crate::define_message!(GetRuntimeCounters, (), b"orc/t/q/rc");
+    crate::define_request_response!(GetRuntimeCounters, super::response::RuntimeCounters);
+
+    // Resets the time since service of a component, after it has been serviced
+    crate::define_message!(ResetServiceCounter, (pub crate::Component), b"orc/t/q/rs");
+    crate::define_request_response!(ResetServiceCounter, super::response::ServiceCounterReset);
+    crate::basic_state_response_verification!(
+        ResetServiceCounter,
+        super::response::ServiceCounterReset
+    );
+
+    // Discards the extraction airflow trend, after the extraction filter has been replaced
+    crate::define_message!(ResetExtractionAirflowTrend, (), b"orc/t/q/rt");
+    crate::define_request_response!(
+        ResetExtractionAirflowTrend,
+        super::response::ExtractionAirflowTrendReset
+    );
+
+    crate::define_message!(GetStateMachineSnapshot, (pub super::super::StateMachine), b"orc/t/q/sm");
+    crate::define_request_response!(
+        GetStateMachineSnapshot,
+        super::response::StateMachineSnapshot
+    );
+
+    crate::define_message!(
+        GetInputRecording,
+        {
+            pub machine: super::super::StateMachine,
+            pub start_index: u32,
+        },
+        b"orc/t/q/ir"
+    );
+    crate::define_request_response!(GetInputRecording, super::response::InputRecording);
+
+    crate::define_message!(
+        GetInputRecordingCheckpoint,
+        (pub super::super::StateMachine),
+        b"orc/t/q/ic"
+    );
+    crate::define_request_response!(
+        GetInputRecordingCheckpoint,
+        super::response::InputRecordingCheckpoint
+    );
+
+    crate::define_message!(GetMonitorStates, (), b"orc/t/q/ms");
+    crate::define_request_response!(GetMonitorStates, super::response::MonitorStates);
+
+    crate::define_message!(GetInterlockState, (), b"orc/t/q/is");
+    crate::define_request_response!(GetInterlockState, super::response::InterlockState);
+
+    crate::define_message!(GetMachinePower, (), b"orc/t/q/mp");
+    crate::define_request_response!(GetMachinePower, super::response::MachinePower);
+
+    crate::define_message!(GetRelayOutputs, (), b"orc/t/q/ro");
+    crate::define_request_response!(GetRelayOutputs, super::response::RelayOutputs);
+
+    crate::define_message!(GetLatestTemperatures, (), b"orc/t/q/lt");
+    crate::define_request_response!(GetLatestTemperatures, super::response::LatestTemperatures);
+
+    crate::define_message!(GetCoolantRates, (), b"orc/t/q/cr");
+    crate::define_request_response!(GetCoolantRates, super::response::CoolantRates);
+
+    crate::define_message!(GetExtractionAirflow, (), b"orc/t/q/ea");
+    crate::define_request_response!(GetExtractionAirflow, super::response::ExtractionAirflow);
+
+    crate::define_message!(GetAccessControl, (), b"orc/t/q/ac");
+    crate::define_request_response!(GetAccessControl, super::response::AccessControl);
+
+    crate::define_message!(GetWallTimeSyncStatus, (), b"orc/t/q/wt");
+    crate::define_request_response!(GetWallTimeSyncStatus, super::response::WallTimeSyncStatus);
+}
+
+pub mod response {
+    crate::define_message!(ApiError, (), b"orc/t/p/ae");
+
+    crate::define_message!(InterlockEventLog, (pub super::super::InterlockEventLogPage), b"orc/t/r/el");
+
+    crate::define_message!(FumeExtractionMode, (pub crate::FumeExtractionMode), b"orc/t/r/fm");
+
+    // The most recently completed jobs, oldest first
+    crate::define_message!(
+        RecentJobs,
+        (pub heapless::Vec<super::super::JobRecord, { super::super::RECENT_JOBS_COUNT }>),
+        b"orc/t/r/jb"
+    );
+
+    crate::define_message!(RuntimeCounters, (pub crate::RuntimeCounters), b"orc/t/r/rc");
+
+    crate::define_message!(ServiceCounterReset, (pub crate::Component), b"orc/t/r/rs");
+
+    crate::define_message!(ExtractionAirflowTrendReset, (), b"orc/t/r/rt");
+
+    crate::define_message!(
+        StateMachineSnapshot,
+        (pub super::super::StateMachineSnapshot),
+        b"orc/t/r/sm"
+    );
+
+    // Recorded inputs of a state machine, `None` if the inputs to the state machine are not recorded
+    crate::define_message!(
+        InputRecording,
+        (pub Option<super::super::InputRecordingPage>),
+        b"orc/t/r/ir"
+    );
+
+    // Checkpoint at the oldest recorded input of a state machine, `None` if the inputs to the state
+    // machine are not recorded or no inputs have been recorded yet
+    crate::define_message!(
+        InputRecordingCheckpoint,
+        (pub Option<super::super::RecordingCheckpoint>),
+        b"orc/t/r/ic"
+    );
+
+    crate::define_message!(MonitorStates, (pub super::super::MonitorStates), b"orc/t/r/ms");
+
+    crate::define_message!(InterlockState, (pub super::super::InterlockState), b"orc/t/r/is");
+
+    // The desired machine power, `None` if it has not been determined since boot
+    crate::define_message!(MachinePower, (pub Option<crate::DesiredMachinePower>), b"orc/t/r/mp");
+
+    crate::define_message!(RelayOutputs, (pub super::super::RelayOutputs), b"orc/t/r/ro");
+
+    // The most recent filtered reading of each temperature sensor that has reported since boot
+    crate::define_message!(
+        LatestTemperatures,
+        (pub heapless::Vec<crate::TemperatureSensorReading, { super::super::LATEST_TEMPERATURES_CAPACITY }>),
+        b"orc/t/r/lt"
+    );
+
+    crate::define_message!(CoolantRates, (pub super::super::CoolantRates), b"orc/t/r/cr");
+
+    // The most recent extraction airflow reading, `None` if none has been received since boot
+    crate::define_message!(
+        ExtractionAirflow,
+        (pub Option<crate::AirflowSensorMeasurement>),
+        b"orc/t/r/ea"
+    );
+
+    crate::define_message!(AccessControl, (pub super::super::AccessControl), b"orc/t/r/ac");
+
+    crate::define_message!(
+        WallTimeSyncStatus,
+        (pub super::super::WallTimeSyncStatus),
+        b"orc/t/r/wt"
+    );
+}
@@ -0,0 +1,4 @@
+mod api;
+mod types;
+
+pub use self::{api::*, types::*};
@@ -0,0 +1,363 @@
+use crate::{
+    AirAssistPump, BootReason, DesiredMachinePower, FumeExtractionFan, Interlock, InterlockAction,
+    LaserEnable, MachineEnable, Monitor, Severity,
+    cooler::CoolantRate,
+    hmi::{AccessControlRawInput, AccessControlState},
+};
+use chrono::{DateTime, Utc};
+use core::time::Duration;
+use defmt::Format;
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumCount, EnumIter, EnumString};
+
+/// A single entry in the orchestrator's interlock event log.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterlockEvent {
+    /// Sequence number of the event, incremented for every event recorded since boot.
+    pub index: u32,
+    /// Uptime of the orchestrator at the time the event was recorded.
+    pub uptime: Duration,
+    /// Wall time at which the event was recorded, if the orchestrator clock was synchronised.
+    pub wall_time: Option<DateTime<Utc>>,
+    pub kind: InterlockEventKind,
+}
+
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InterlockEventKind {
+    /// The orchestrator started, this is the only way a tripped interlock is reset.
+    Boot(BootReason),
+    /// The severity reported by a monitor changed.
+    MonitorSeverity(Monitor, Severity),
+    /// The interlock latched into the tripped state.
+    Tripped,
+    Interlock(Interlock),
+    Action(InterlockAction),
+}
+
+/// Maximum number of interlock events returned in a single response.
+pub const INTERLOCK_EVENT_LOG_PAGE_SIZE: usize = 6;
+
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterlockEventLogPage {
+    /// Events with an index equal to or greater than the requested index, oldest first.
+    pub events: Vec<InterlockEvent, INTERLOCK_EVENT_LOG_PAGE_SIZE>,
+    /// Index of the oldest event still held in the log.
+    /// Any events before this have been evicted.
+    pub oldest_index: u32,
+    /// Index that the next recorded event will be given.
+    pub next_index: u32,
+}
+
+/// Record of a single job, from the machine starting to run until it stopped.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+    /// Sequence number of the job, incremented for every job since boot.
+    pub index: u32,
+    /// Wall time at which the job started, if the orchestrator clock was synchronised.
+    pub start: Option<DateTime<Utc>>,
+    /// Wall time at which the job ended, if the orchestrator clock was synchronised.
+    pub end: Option<DateTime<Utc>>,
+    pub duration: Duration,
+    /// Highest coolant reservoir temperature seen during the job.
+    pub peak_coolant_reservoir_temperature: Option<f32>,
+    /// Lowest extraction airflow differential pressure seen during the job.
+    pub minimum_extraction_airflow: Option<f32>,
+    /// Number of times the interlock action changed during the job.
+    pub interlock_events: u16,
+    /// Number of times the doors were opened during the job.
+    pub door_openings: u16,
+    pub end_reason: JobEndReason,
+}
+
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum JobEndReason {
+    /// The job ran to completion.
+    Completed,
+    /// The job was stopped by the interlock.
+    Interlock,
+    /// Machine power was removed during the job.
+    PowerRemoved,
+}
+
+/// Maximum number of job records returned in a single response.
+pub const RECENT_JOBS_COUNT: usize = 5;
+
+/// A state machine running on the orchestrator.
+#[derive(
+    Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum StateMachine {
+    AirAssist,
+    CoolantRate,
+    Cooling,
+    ExtractionAirflow,
+    FumeExtraction,
+    HmiStatusScreen,
+    Interlock,
+    JobTracking,
+    MachinePower,
+    RuntimeCounters,
+    StatusLight,
+    TemperatureFilter,
+    Temperatures,
+}
+
+/// Maximum size of a serialised state machine snapshot.
+pub const STATE_MACHINE_SNAPSHOT_CAPACITY: usize = 448;
+
+/// The internal state of a state machine, as of the last time it waited for an input.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateMachineSnapshot {
+    pub machine: StateMachine,
+    /// Uptime of the orchestrator at the time the snapshot was requested.
+    ///
+    /// Times in the snapshot are relative to boot, this allows them to be related to the present.
+    pub uptime: Duration,
+    /// The snapshot type from `hoshiguma-state-machines` for the state machine, serialised with
+    /// postcard.
+    ///
+    /// `None` if the state machine is not running or the snapshot was too large.
+    pub snapshot: Option<Vec<u8, STATE_MACHINE_SNAPSHOT_CAPACITY>>,
+}
+
+/// Maximum size of a single serialised state machine input in a recording.
+pub const RECORDED_INPUT_CAPACITY: usize = 48;
+
+/// A single input sent to a state machine, as recorded by the orchestrator.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedInput {
+    /// Sequence number of the input, incremented for every input recorded since boot.
+    pub index: u32,
+    /// Uptime of the orchestrator at the time the input was sent.
+    pub uptime: Duration,
+    /// The `InputMessage` type from `hoshiguma-state-machines` for the state machine, serialised
+    /// with postcard.
+    ///
+    /// `None` if the input was too large.
+    pub input: Option<Vec<u8, RECORDED_INPUT_CAPACITY>>,
+}
+
+/// The state of a state machine immediately before it acted on a recorded input.
+///
+/// Replaying the recorded inputs from `index` onwards, starting from this state, reproduces the
+/// outputs of the state machine.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingCheckpoint {
+    /// Index of the input that followed the snapshot.
+    pub index: u32,
+    /// Uptime of the orchestrator at the time the snapshot was taken.
+    pub uptime: Duration,
+    /// The snapshot type from `hoshiguma-state-machines` for the state machine, serialised with
+    /// postcard.
+    ///
+    /// `None` if the snapshot was too large.
+    pub snapshot: Option<Vec<u8, STATE_MACHINE_SNAPSHOT_CAPACITY>>,
+}
+
+/// Maximum number of recorded inputs returned in a single response.
+pub const INPUT_RECORDING_PAGE_SIZE: usize = 6;
+
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputRecordingPage {
+    /// Inputs with an index equal to or greater than the requested index, oldest first.
+    pub inputs: Vec<RecordedInput, INPUT_RECORDING_PAGE_SIZE>,
+    /// Index of the oldest input still held in the recording.
+    /// Any inputs before this have been evicted.
+    pub oldest_index: u32,
+    /// Index that the next recorded input will be given.
+    pub next_index: u32,
+}
+
+/// Severity of every monitor, as seen by the interlock.
+pub type MonitorStates = Vec<(Monitor, Severity), { Monitor::COUNT }>;
+
+/// State of the interlock, `None` for anything that has not been determined since boot.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterlockState {
+    pub interlock: Option<Interlock>,
+    pub action: Option<InterlockAction>,
+}
+
+/// Settings of the relays driven directly by the orchestrator, `None` for any that have not been
+/// set since boot.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayOutputs {
+    pub machine_power: Option<DesiredMachinePower>,
+    pub machine_enable: Option<MachineEnable>,
+    pub laser_enable: Option<LaserEnable>,
+    pub air_assist_pump: Option<AirAssistPump>,
+    pub fume_extraction_fan: Option<FumeExtractionFan>,
+}
+
+/// Maximum number of temperature sensors returned in a single response.
+///
+/// Enough for every 1-Wire sensor on the orchestrator, cooler and rear sensor board, plus the
+/// extraction airflow sensor.
+pub const LATEST_TEMPERATURES_CAPACITY: usize =
+    3 * crate::OnewireTemperatureSensorReadings::MAX_NUM_SENSORS + 1;
+
+/// The most recent coolant rates reported by the cooler, `None` for any that have not been
+/// received since boot.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoolantRates {
+    pub flow: Option<CoolantRate>,
+    pub ret: Option<CoolantRate>,
+}
+
+/// Access control as reported by the HMI, `None` for anything that has not been received since
+/// boot.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessControl {
+    pub raw_input: Option<AccessControlRawInput>,
+    pub state: Option<AccessControlState>,
+}
+
+/// Status of the synchronisation of the orchestrator clock with the telemetry bridge.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WallTimeSyncStatus {
+    /// Uptime of the orchestrator at the time the status was requested.
+    pub uptime: Duration,
+    /// Current wall time, if the clock has been synchronised.
+    pub wall_time: Option<DateTime<Utc>>,
+    /// Uptime of the orchestrator at the last successful synchronisation, if there has been one.
+    pub last_sync: Option<Duration>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Message, TemperatureSensor, TemperatureSensorReading,
+        orchestrator::response::{
+            InputRecording, InputRecordingCheckpoint, InterlockEventLog, LatestTemperatures,
+            MonitorStates, RecentJobs,
+        },
+    };
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn full_event_log_page_fits_in_message() {
+        let event = InterlockEvent {
+            index: u32::MAX,
+            uptime: Duration::new(u64::MAX, 999_999_999),
+            wall_time: Some(DateTime::from_timestamp_nanos(i64::MAX)),
+            kind: InterlockEventKind::MonitorSeverity(
+                Monitor::ExtractionAirflowSensorFunctional,
+                Severity::Critical,
+            ),
+        };
+
+        let mut events = Vec::new();
+        while events.push(event.clone()).is_ok() {}
+
+        let page = InterlockEventLogPage {
+            events,
+            oldest_index: u32::MAX,
+            next_index: u32::MAX,
+        };
+
+        assert!(Message::new(&InterlockEventLog(page)).is_ok());
+    }
+
+    #[test]
+    fn full_recent_jobs_fits_in_message() {
+        let job = JobRecord {
+            index: u32::MAX,
+            start: Some(DateTime::from_timestamp_nanos(i64::MAX)),
+            end: Some(DateTime::from_timestamp_nanos(i64::MAX)),
+            duration: Duration::new(u64::MAX, 999_999_999),
+            peak_coolant_reservoir_temperature: Some(f32::MAX),
+            minimum_extraction_airflow: Some(f32::MIN),
+            interlock_events: u16::MAX,
+            door_openings: u16::MAX,
+            end_reason: JobEndReason::PowerRemoved,
+        };
+
+        let mut jobs = Vec::new();
+        while jobs.push(job.clone()).is_ok() {}
+
+        assert!(Message::new(&RecentJobs(jobs)).is_ok());
+    }
+
+    #[test]
+    fn full_state_machine_snapshot_fits_in_message() {
+        let mut snapshot = Vec::new();
+        while snapshot.push(u8::MAX).is_ok() {}
+
+        let snapshot = StateMachineSnapshot {
+            machine: StateMachine::TemperatureFilter,
+            uptime: Duration::new(u64::MAX, 999_999_999),
+            snapshot: Some(snapshot),
+        };
+
+        assert!(
+            Message::new(&crate::orchestrator::response::StateMachineSnapshot(
+                snapshot
+            ))
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn full_input_recording_page_fits_in_message() {
+        let mut input = Vec::new();
+        while input.push(u8::MAX).is_ok() {}
+
+        let recorded = RecordedInput {
+            index: u32::MAX,
+            uptime: Duration::new(u64::MAX, 999_999_999),
+            input: Some(input),
+        };
+
+        let mut inputs = Vec::new();
+        while inputs.push(recorded.clone()).is_ok() {}
+
+        let page = InputRecordingPage {
+            inputs,
+            oldest_index: u32::MAX,
+            next_index: u32::MAX,
+        };
+
+        assert!(Message::new(&InputRecording(Some(page))).is_ok());
+    }
+
+    #[test]
+    fn full_recording_checkpoint_fits_in_message() {
+        let mut snapshot = Vec::new();
+        while snapshot.push(u8::MAX).is_ok() {}
+
+        let checkpoint = RecordingCheckpoint {
+            index: u32::MAX,
+            uptime: Duration::new(u64::MAX, 999_999_999),
+            snapshot: Some(snapshot),
+        };
+
+        assert!(Message::new(&InputRecordingCheckpoint(Some(checkpoint))).is_ok());
+    }
+
+    #[test]
+    fn all_monitor_states_fit_in_message() {
+        let mut states = super::MonitorStates::new();
+        for monitor in Monitor::iter() {
+            states.push((monitor, Severity::Critical)).unwrap();
+        }
+
+        assert!(Message::new(&MonitorStates(states)).is_ok());
+    }
+
+    #[test]
+    fn full_latest_temperatures_fits_in_message() {
+        let reading = TemperatureSensorReading {
+            sensor: TemperatureSensor::RearSensorBoardOnewire(u64::MAX),
+            reading: Ok(f32::MAX),
+        };
+
+        let mut readings = Vec::new();
+        while readings.push(reading).is_ok() {}
+
+        assert!(Message::new(&LatestTemperatures(readings)).is_ok());
+    }
+}
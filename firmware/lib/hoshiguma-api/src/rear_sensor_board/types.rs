@@ -7,6 +7,8 @@ pub struct StatusLightSettings {
     pub red: LightPattern,
     pub amber: LightPattern,
     pub green: LightPattern,
+    /// Pattern for the buzzer, which is optional and may not be fitted.
+    pub buzzer: LightPattern,
 }
 
 /// Represents a repeating pattern of binary light states.
@@ -74,6 +76,74 @@ impl LightPattern {
         LightState::Off,
         LightState::Off,
     ]);
+    pub const BLINK_5HZ: Self = Self([
+        LightState::On, // 0 (0s)
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+        LightState::On, // 10 (1s)
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+        LightState::On,
+        LightState::Off,
+    ]);
+    /// As `BLINK_1HZ`, but in antiphase, for alternating with a light showing `BLINK_1HZ`.
+    pub const BLINK_1HZ_INVERTED: Self = Self([
+        LightState::Off, // 0 (0s)
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::On, // 10 (1s)
+        LightState::On,
+        LightState::On,
+        LightState::On,
+        LightState::On,
+        LightState::On,
+        LightState::On,
+        LightState::On,
+        LightState::On,
+        LightState::On,
+    ]);
+    /// A single short pulse every two seconds.
+    pub const PULSE: Self = Self([
+        LightState::On, // 0 (0s)
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off, // 10 (1s)
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+        LightState::Off,
+    ]);
 }
 
 #[derive(Debug, Format, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
@@ -14,6 +14,21 @@ pub mod request {
         SendTelemetryDataPoint,
         super::response::TelemetryDataPointAck
     );
+
+    // A batch is resent with the same sequence number until it is acknowledged, so that the
+    // telemetry bridge can drop a batch it has already received
+    crate::define_message!(
+        SendTelemetryDataPointBatch,
+        {
+            pub sequence: u32,
+            pub batch: super::super::TelemetryDataPointBatch,
+        },
+        b"tlm/t/q/db"
+    );
+    crate::define_request_response!(
+        SendTelemetryDataPointBatch,
+        super::response::TelemetryDataPointBatchAck
+    );
 }
 
 pub mod response {
@@ -26,4 +41,7 @@ pub mod response {
     crate::define_message!(Time, (pub Option<DateTime<Utc>>), b"tlm/t/r/tm");
 
     crate::define_message!(TelemetryDataPointAck, (), b"tlm/t/r/dp");
+
+    // The sequence number of the acknowledged batch
+    crate::define_message!(TelemetryDataPointBatchAck, (pub u32), b"tlm/t/r/db");
 }
@@ -1,8 +1,152 @@
+use crate::MESSAGE_PAYLOAD_CAPACITY;
 use defmt::Format;
-use heapless::String;
+use heapless::{String, Vec};
 use serde::{Deserialize, Serialize};
 
 pub const TELEMETRY_DATA_POINT_MAX_LEN: usize = 256;
 
 #[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FormattedTelemetryDataPoint(pub String<TELEMETRY_DATA_POINT_MAX_LEN>);
+
+/// Maximum number of data points sent in a single batch.
+pub const TELEMETRY_DATA_POINT_BATCH_CAPACITY: usize = 16;
+
+/// Data points sent to the telemetry bridge in a single message.
+///
+/// Data points are only accepted while the serialised batch, along with its sequence number, still
+/// fits in a message.
+#[derive(Debug, Format, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryDataPointBatch(
+    Vec<FormattedTelemetryDataPoint, TELEMETRY_DATA_POINT_BATCH_CAPACITY>,
+);
+
+#[derive(Debug, Format, PartialEq, Eq)]
+pub struct BatchFull;
+
+impl TelemetryDataPointBatch {
+    /// Checks if a data point could be added to the batch.
+    pub fn fits(&self, data_point: &FormattedTelemetryDataPoint) -> bool {
+        !self.0.is_full()
+            && SEQUENCE_MAX_LEN + self.serialised_len() + serialised_len(data_point)
+                <= MESSAGE_PAYLOAD_CAPACITY
+    }
+
+    /// Adds a data point to the batch, if there is room for it.
+    pub fn push(&mut self, data_point: FormattedTelemetryDataPoint) -> Result<(), BatchFull> {
+        if self.fits(&data_point) {
+            self.0.push(data_point).map_err(|_| BatchFull)
+        } else {
+            Err(BatchFull)
+        }
+    }
+
+    pub fn data_points(&self) -> &[FormattedTelemetryDataPoint] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn serialised_len(&self) -> usize {
+        // The batch will never be long enough for the length to take more than a single byte
+        1 + self.0.iter().map(serialised_len).sum::<usize>()
+    }
+}
+
+impl IntoIterator for TelemetryDataPointBatch {
+    type Item = FormattedTelemetryDataPoint;
+    type IntoIter = <Vec<FormattedTelemetryDataPoint, TELEMETRY_DATA_POINT_BATCH_CAPACITY> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Maximum length of the batch sequence number when serialised with postcard, a varint `u32`.
+const SEQUENCE_MAX_LEN: usize = 5;
+
+/// Length of a data point when serialised with postcard, a varint length followed by the bytes.
+fn serialised_len(data_point: &FormattedTelemetryDataPoint) -> usize {
+    let len = data_point.0.len();
+    let len_len = if len < 0x80 { 1 } else { 2 };
+    len_len + len
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Message, telemetry_bridge::request::SendTelemetryDataPointBatch};
+
+    fn data_point(len: usize) -> FormattedTelemetryDataPoint {
+        let mut s = String::new();
+        for _ in 0..len {
+            s.push('x').unwrap();
+        }
+        FormattedTelemetryDataPoint(s)
+    }
+
+    #[test]
+    fn batch_of_short_data_points_is_limited_by_capacity() {
+        let mut batch = TelemetryDataPointBatch::default();
+        while batch.push(data_point(8)).is_ok() {}
+
+        assert_eq!(batch.len(), TELEMETRY_DATA_POINT_BATCH_CAPACITY);
+        assert!(
+            Message::new(&SendTelemetryDataPointBatch {
+                sequence: u32::MAX,
+                batch
+            })
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn batch_of_long_data_points_is_limited_by_message_size() {
+        let mut batch = TelemetryDataPointBatch::default();
+        while batch.push(data_point(120)).is_ok() {}
+
+        assert_eq!(batch.len(), 4);
+        assert!(
+            Message::new(&SendTelemetryDataPointBatch {
+                sequence: u32::MAX,
+                batch
+            })
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn full_batch_fits_in_message() {
+        for len in [1, 50, 126, 127, 128, 129, 200, TELEMETRY_DATA_POINT_MAX_LEN] {
+            let mut batch = TelemetryDataPointBatch::default();
+            while batch.push(data_point(len)).is_ok() {}
+
+            assert!(!batch.is_empty());
+            assert!(
+                Message::new(&SendTelemetryDataPointBatch {
+                    sequence: u32::MAX,
+                    batch
+                })
+                .is_ok(),
+                "data point length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn serialised_len_matches_postcard() {
+        let mut batch = TelemetryDataPointBatch::default();
+        batch.push(data_point(10)).unwrap();
+        batch.push(data_point(130)).unwrap();
+
+        let mut buffer = [0u8; MESSAGE_PAYLOAD_CAPACITY];
+        let serialised = postcard::to_slice(&batch, &mut buffer).unwrap();
+
+        assert_eq!(batch.serialised_len(), serialised.len());
+    }
+}
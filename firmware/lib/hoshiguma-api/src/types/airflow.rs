@@ -8,3 +8,14 @@ pub struct AirflowSensorMeasurementInner {
     pub differential_pressure: f32,
     pub temperature: f32,
 }
+
+/// Long term trend of the fume extraction airflow, used to detect the filter clogging.
+#[derive(Default, Debug, Format, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractionAirflowTrend {
+    /// Moving average of the mean differential pressure of each fan run, in Pa.
+    pub average: f32,
+    /// Highest moving average seen, taken to be the airflow with a clean filter, in Pa.
+    pub reference: f32,
+    /// Number of fan runs that have contributed to the average.
+    pub runs: u32,
+}
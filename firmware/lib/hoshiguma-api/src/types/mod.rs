@@ -7,6 +7,9 @@ pub use monitors::*;
 mod onewire_temperature;
 pub use onewire_temperature::*;
 
+mod runtime;
+pub use runtime::*;
+
 mod system;
 pub use system::*;
 
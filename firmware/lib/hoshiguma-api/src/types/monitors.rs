@@ -82,4 +82,22 @@ pub enum Monitor {
 
     /// Is the fume extraction airflow sensor reporting correctly?
     ExtractionAirflowSensorFunctional,
+
+    /// Is the compressor locked out due to low coolant flow or temperature?
+    CompressorLockout,
+
+    /// Has coolant been lost from the circuit over time?
+    CoolantLeak,
+
+    /// Is the fume extraction filter in good condition, based on the long term airflow trend?
+    ExtractionFilter,
+
+    /// Is there air assist pressure when the pump is running (and none when it is not)?
+    AirAssistPressure,
+
+    /// Is the temperature in the cutting area and extraction duct free of rapid rises (i.e. fire)?
+    TemperatureRateOfRise,
+
+    /// Are all components within their service intervals?
+    ServiceDue,
 }
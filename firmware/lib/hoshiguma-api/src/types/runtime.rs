@@ -0,0 +1,64 @@
+use core::time::Duration;
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumCount, EnumIter, EnumString};
+
+/// A part of the machine that requires periodic servicing.
+#[derive(
+    Debug,
+    Format,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumIter,
+    EnumCount,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Component {
+    LaserTube,
+    CoolantPump,
+    Compressor,
+    ExtractionFan,
+}
+
+/// Accumulated running time of a component.
+#[derive(Default, Debug, Format, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeCounter {
+    /// Total running time over the life of the machine.
+    pub total: Duration,
+    /// Running time since the component was last serviced.
+    pub since_service: Duration,
+}
+
+#[derive(Default, Debug, Format, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeCounters {
+    pub laser_tube: RuntimeCounter,
+    pub coolant_pump: RuntimeCounter,
+    pub compressor: RuntimeCounter,
+    pub extraction_fan: RuntimeCounter,
+}
+
+impl RuntimeCounters {
+    pub fn get(&self, component: Component) -> &RuntimeCounter {
+        match component {
+            Component::LaserTube => &self.laser_tube,
+            Component::CoolantPump => &self.coolant_pump,
+            Component::Compressor => &self.compressor,
+            Component::ExtractionFan => &self.extraction_fan,
+        }
+    }
+
+    pub fn get_mut(&mut self, component: Component) -> &mut RuntimeCounter {
+        match component {
+            Component::LaserTube => &mut self.laser_tube,
+            Component::CoolantPump => &mut self.coolant_pump,
+            Component::Compressor => &mut self.compressor,
+            Component::ExtractionFan => &mut self.extraction_fan,
+        }
+    }
+}
@@ -24,6 +24,12 @@ pub enum TemperatureSensor {
     CoolerPcb,
     CoolantReservoir,
 
+    /// Temperature of the fume extraction airflow, from the rear sensor board airflow sensor.
+    ExtractionAirflow,
+
+    /// A 1-Wire sensor on the rear sensor board, in the cutting area.
+    RearSensorBoardOnewire(OnewireAddress),
+
     UnknownOnewire(OnewireAddress),
 }
 
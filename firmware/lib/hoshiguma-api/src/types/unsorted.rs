@@ -72,6 +72,15 @@ pub enum AirAssistPump {
     Run,
 }
 
+#[derive(
+    Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum AirAssistPressure {
+    Low,
+    Normal,
+}
+
 #[derive(
     Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString,
 )]
@@ -126,6 +135,15 @@ pub enum AcBusPower {
     Off,
 }
 
+#[derive(
+    Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum EmergencyStop {
+    Released,
+    Pressed,
+}
+
 #[derive(
     Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString,
 )]
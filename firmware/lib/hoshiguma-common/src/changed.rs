@@ -27,6 +27,12 @@ impl<T: Clone + PartialEq> Default for ObservedValue<T> {
     }
 }
 
+impl<T: Clone + PartialEq> From<Option<T>> for ObservedValue<T> {
+    fn from(value: Option<T>) -> Self {
+        Self { value }
+    }
+}
+
 impl<T: Clone + PartialEq> Deref for ObservedValue<T> {
     type Target = Option<T>;
 
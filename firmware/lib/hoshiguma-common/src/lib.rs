@@ -7,3 +7,4 @@ pub mod network;
 pub mod remote_device_healthcheck;
 pub mod remote_state_reconciler;
 pub mod telemetry;
+pub mod telemetry_queue;
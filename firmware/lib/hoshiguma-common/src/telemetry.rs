@@ -20,3 +20,37 @@ pub fn format_influx_line<const LEN: usize>(
 
     Ok(line_str)
 }
+
+/// The series of an Influx line, i.e. the measurement and tag set.
+pub fn series(line: &str) -> &str {
+    let mut escaped = false;
+
+    for (idx, c) in line.char_indices() {
+        match c {
+            ' ' if !escaped => return &line[..idx],
+            '\\' if !escaped => escaped = true,
+            _ => escaped = false,
+        }
+    }
+
+    line
+}
+
+/// Checks if an Influx line has a timestamp, i.e. has a section after the field set.
+pub fn has_timestamp(line: &str) -> bool {
+    let mut escaped = false;
+    let mut quoted = false;
+    let mut sections = 1;
+
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ' ' if !quoted => sections += 1,
+            _ => {}
+        }
+    }
+
+    sections > 2
+}
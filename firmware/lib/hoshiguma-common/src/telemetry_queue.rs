@@ -0,0 +1,281 @@
+//! Prioritised queue of telemetry data points waiting to be sent.
+
+use crate::telemetry::series;
+use defmt::Format;
+use embassy_time::Instant;
+use heapless::Vec;
+use hoshiguma_api::telemetry_bridge::FormattedTelemetryDataPoint;
+
+/// How a data point is treated when the telemetry queue is under pressure.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TelemetryPriority {
+    /// Periodic measurements.
+    ///
+    /// Only the latest value of each series is kept while queued, these are the only data points
+    /// that are discarded when the queue is full.
+    Sample,
+
+    /// Changes of state.
+    ///
+    /// Every transition is kept, when the queue is full these displace samples or are moved to the
+    /// spool.
+    State,
+
+    /// Safety relevant events (monitors, interlock, trips, etc.).
+    ///
+    /// Every event is kept, when the queue is full these displace samples or are moved to the
+    /// spool, and are retried until they are delivered.
+    Event,
+}
+
+impl TelemetryPriority {
+    fn coalesce(&self) -> bool {
+        *self == Self::Sample
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedDataPoint {
+    pub priority: TelemetryPriority,
+    pub data_point: FormattedTelemetryDataPoint,
+    /// Uptime at which the data point was queued.
+    pub queued_at: Instant,
+}
+
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq)]
+pub enum QueueOutcome {
+    Queued,
+    /// Replaced the queued value of the same series.
+    Coalesced,
+    /// Queued by discarding an older sample.
+    Displaced,
+    /// Queued by moving an older data point that must not be discarded to the spool.
+    Spilled,
+    /// There was no room for the sample.
+    Discarded,
+}
+
+pub struct TelemetryQueue<const N: usize> {
+    /// Data points waiting to be sent, oldest first.
+    points: Vec<QueuedDataPoint, N>,
+}
+
+impl<const N: usize> Default for TelemetryQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> TelemetryQueue<N> {
+    pub const fn new() -> Self {
+        Self { points: Vec::new() }
+    }
+
+    /// Adds a data point, if the queue is full and there is no sample to discard then the oldest of
+    /// the lowest priority data points is passed to `spill`.
+    pub fn push(
+        &mut self,
+        point: QueuedDataPoint,
+        spill: impl FnOnce(QueuedDataPoint),
+    ) -> QueueOutcome {
+        if point.priority.coalesce() {
+            let point_series = series(&point.data_point.0);
+
+            if let Some(queued) = self
+                .points
+                .iter_mut()
+                .find(|p| p.priority == point.priority && series(&p.data_point.0) == point_series)
+            {
+                // The time it was queued at is used to timestamp the data point if it has no
+                // timestamp of its own, so must be that of the latest value
+                queued.data_point = point.data_point;
+                queued.queued_at = point.queued_at;
+                return QueueOutcome::Coalesced;
+            }
+        }
+
+        let mut outcome = QueueOutcome::Queued;
+
+        if self.points.is_full() {
+            // Find the oldest of the lowest priority data points
+            let (idx, priority) = self
+                .points
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, p)| p.priority)
+                .map(|(idx, p)| (idx, p.priority))
+                .expect("queue should not be empty when full");
+
+            outcome = match (priority, point.priority) {
+                (TelemetryPriority::Sample, _) => {
+                    self.points.remove(idx);
+                    QueueOutcome::Displaced
+                }
+                (_, TelemetryPriority::Sample) => return QueueOutcome::Discarded,
+                _ => {
+                    spill(self.points.remove(idx));
+                    QueueOutcome::Spilled
+                }
+            };
+        }
+
+        // There is always room by this point
+        let _ = self.points.push(point);
+
+        outcome
+    }
+
+    /// Takes the oldest of the highest priority data points, if it satisfies the predicate.
+    pub fn pop_if(
+        &mut self,
+        predicate: impl FnOnce(&mut QueuedDataPoint) -> bool,
+    ) -> Option<QueuedDataPoint> {
+        let priority = self.points.iter().map(|p| p.priority).max()?;
+        let idx = self.points.iter().position(|p| p.priority == priority)?;
+
+        if predicate(&mut self.points[idx]) {
+            Some(self.points.remove(idx))
+        } else {
+            None
+        }
+    }
+
+    /// Takes the data point that was queued earliest, regardless of priority.
+    pub fn pop_oldest(&mut self) -> Option<QueuedDataPoint> {
+        let idx = self
+            .points
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, p)| p.queued_at)
+            .map(|(idx, _)| idx)?;
+
+        Some(self.points.remove(idx))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn point(priority: TelemetryPriority, line: &str, queued_at: u64) -> QueuedDataPoint {
+        QueuedDataPoint {
+            priority,
+            data_point: FormattedTelemetryDataPoint(line.try_into().unwrap()),
+            queued_at: Instant::from_secs(queued_at),
+        }
+    }
+
+    fn no_spill(point: QueuedDataPoint) {
+        panic!("unexpected spill of {point:?}");
+    }
+
+    #[test]
+    fn coalesced_sample_is_queued_at_time_of_latest_value() {
+        let mut queue = TelemetryQueue::<4>::new();
+
+        let outcome = queue.push(
+            point(TelemetryPriority::Sample, "temp value=1", 1),
+            no_spill,
+        );
+        assert_eq!(outcome, QueueOutcome::Queued);
+
+        let outcome = queue.push(
+            point(TelemetryPriority::Sample, "temp value=2", 5),
+            no_spill,
+        );
+        assert_eq!(outcome, QueueOutcome::Coalesced);
+
+        assert_eq!(
+            queue.pop_if(|_| true),
+            Some(point(TelemetryPriority::Sample, "temp value=2", 5))
+        );
+        assert_eq!(queue.pop_if(|_| true), None);
+    }
+
+    #[test]
+    fn state_transitions_are_not_coalesced() {
+        let mut queue = TelemetryQueue::<4>::new();
+
+        for (value, queued_at) in [("on", 1), ("off", 2), ("on", 3)] {
+            let line: heapless::String<32> = heapless::format!("pump value=\"{value}\"").unwrap();
+            let outcome = queue.push(point(TelemetryPriority::State, &line, queued_at), no_spill);
+            assert_eq!(outcome, QueueOutcome::Queued);
+        }
+
+        assert_eq!(
+            queue.pop_if(|_| true),
+            Some(point(TelemetryPriority::State, "pump value=\"on\"", 1))
+        );
+        assert_eq!(
+            queue.pop_if(|_| true),
+            Some(point(TelemetryPriority::State, "pump value=\"off\"", 2))
+        );
+        assert_eq!(
+            queue.pop_if(|_| true),
+            Some(point(TelemetryPriority::State, "pump value=\"on\"", 3))
+        );
+    }
+
+    #[test]
+    fn full_queue_displaces_samples_before_spilling_events() {
+        let mut queue = TelemetryQueue::<2>::new();
+
+        queue.push(point(TelemetryPriority::Event, "trip value=1", 1), no_spill);
+        queue.push(
+            point(TelemetryPriority::Sample, "temp value=1", 2),
+            no_spill,
+        );
+
+        let outcome = queue.push(point(TelemetryPriority::Event, "trip value=2", 3), no_spill);
+        assert_eq!(outcome, QueueOutcome::Displaced);
+
+        let outcome = queue.push(
+            point(TelemetryPriority::Sample, "temp value=2", 4),
+            no_spill,
+        );
+        assert_eq!(outcome, QueueOutcome::Discarded);
+
+        let mut spilled = None;
+        let outcome = queue.push(point(TelemetryPriority::Event, "trip value=3", 5), |p| {
+            spilled = Some(p)
+        });
+        assert_eq!(outcome, QueueOutcome::Spilled);
+        assert_eq!(
+            spilled,
+            Some(point(TelemetryPriority::Event, "trip value=1", 1))
+        );
+
+        assert_eq!(
+            queue.pop_if(|_| true),
+            Some(point(TelemetryPriority::Event, "trip value=2", 3))
+        );
+        assert_eq!(
+            queue.pop_if(|_| true),
+            Some(point(TelemetryPriority::Event, "trip value=3", 5))
+        );
+    }
+    #[test]
+    fn oldest_is_popped_regardless_of_priority() {
+        let mut queue = TelemetryQueue::<4>::new();
+
+        queue.push(
+            point(TelemetryPriority::Sample, "temp value=1", 1),
+            no_spill,
+        );
+        queue.push(point(TelemetryPriority::Event, "trip value=1", 2), no_spill);
+        queue.push(
+            point(TelemetryPriority::Sample, "temp value=2", 3),
+            no_spill,
+        );
+
+        assert_eq!(
+            queue.pop_oldest(),
+            Some(point(TelemetryPriority::Event, "trip value=1", 2))
+        );
+        assert_eq!(
+            queue.pop_oldest(),
+            Some(point(TelemetryPriority::Sample, "temp value=2", 3))
+        );
+        assert_eq!(queue.pop_oldest(), None);
+    }
+}
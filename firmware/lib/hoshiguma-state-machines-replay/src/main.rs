@@ -0,0 +1,198 @@
+//! Reproduces the decisions of a state machine running on the orchestrator by replaying its
+//! recorded inputs on the host, using the same state machine code and simulated time.
+//!
+//! Replay starts from the checkpoint at the oldest input still held by the orchestrator, so the
+//! outputs match those originally produced even after the oldest inputs have been discarded.
+
+mod replay;
+mod simulated_time;
+
+use clap::Parser;
+use embassy_time::{Duration, Instant};
+use hoshiguma_api::{
+    API_PORT, CobsFramer, ExpectedResponse, Message, MessagePayload, ORCHESTRATOR_IP_ADDRESS,
+    orchestrator::{RecordedInput, RecordingCheckpoint, StateMachine, request},
+};
+use hoshiguma_state_machines as sm;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, TcpStream},
+};
+
+/// Fetch the recorded inputs of a state machine from the orchestrator and replay them.
+#[derive(Debug, Parser)]
+struct Args {
+    /// State machine to replay
+    machine: StateMachine,
+
+    /// Address of the orchestrator
+    #[arg(long, default_value_t = IpAddr::V4(ORCHESTRATOR_IP_ADDRESS))]
+    address: IpAddr,
+
+    /// Time in seconds to continue replaying for after the last input
+    #[arg(long, default_value_t = 0)]
+    run_on: u64,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut stream = TcpStream::connect((args.address, API_PORT)).unwrap();
+    let checkpoint = fetch_checkpoint(&mut stream, args.machine);
+    let recording = fetch_recording(&mut stream, args.machine, checkpoint.index);
+    drop(stream);
+
+    let run_on = Duration::from_secs(args.run_on);
+
+    macro_rules! replay {
+        ($module:ident) => {{
+            let input_channel = sm::$module::InputChannel::new();
+            let output_channel = sm::$module::OutputChannel::new();
+            let (mut runner, _communicator) = sm::$module::new(&input_channel, &output_channel);
+
+            simulated_time::advance_to(Instant::from_micros(checkpoint.uptime.as_micros() as u64));
+            runner.restore_snapshot(decode_snapshot::<sm::$module::Snapshot>(checkpoint));
+
+            replay::replay(
+                runner,
+                decode_inputs::<sm::$module::InputMessage>(recording),
+                run_on,
+                |input| {
+                    input_channel
+                        .try_send(input)
+                        .expect("state machine should have consumed previous inputs")
+                },
+                || output_channel.try_receive().ok(),
+            );
+        }};
+    }
+
+    // Only the state machines recorded by the orchestrator can have their state restored
+    match args.machine {
+        StateMachine::Cooling => replay!(cooling),
+        StateMachine::Interlock => replay!(interlock),
+        machine => {
+            eprintln!("Replaying the {machine} state machine is not supported");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Retrieves the checkpoint at the oldest input to a state machine still held by the orchestrator.
+fn fetch_checkpoint(stream: &mut TcpStream, machine: StateMachine) -> RecordingCheckpoint {
+    match send_request(stream, request::GetInputRecordingCheckpoint(machine)).0 {
+        Some(checkpoint) => checkpoint,
+        None => {
+            eprintln!(
+                "Inputs to the {machine} state machine are not recorded or none have been recorded yet"
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Retrieves every input to a state machine still held by the orchestrator from `start_index`,
+/// oldest first.
+fn fetch_recording(
+    stream: &mut TcpStream,
+    machine: StateMachine,
+    mut start_index: u32,
+) -> Vec<RecordedInput> {
+    let mut recording = Vec::new();
+
+    loop {
+        let Some(page) = send_request(
+            stream,
+            request::GetInputRecording {
+                machine,
+                start_index,
+            },
+        )
+        .0
+        else {
+            eprintln!("Inputs to the {machine} state machine are not recorded");
+            std::process::exit(1);
+        };
+
+        if page.oldest_index > start_index {
+            eprintln!(
+                "Inputs from {start_index} were discarded while the recording was being fetched, try again"
+            );
+            std::process::exit(1);
+        }
+
+        let Some(last) = page.inputs.last() else {
+            break;
+        };
+        start_index = last.index + 1;
+        recording.extend(page.inputs);
+
+        if start_index >= page.next_index {
+            break;
+        }
+    }
+
+    recording
+}
+
+fn decode_snapshot<S: DeserializeOwned>(checkpoint: RecordingCheckpoint) -> S {
+    let Some(snapshot) = checkpoint.snapshot else {
+        eprintln!(
+            "Checkpoint {} was too large to be recorded",
+            checkpoint.index
+        );
+        std::process::exit(1);
+    };
+
+    match postcard::from_bytes(&snapshot) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            eprintln!("Failed to decode checkpoint {}: {e}", checkpoint.index);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn decode_inputs<T: DeserializeOwned>(recording: Vec<RecordedInput>) -> Vec<(Instant, T)> {
+    recording
+        .into_iter()
+        .filter_map(|recorded| {
+            let time = Instant::from_micros(recorded.uptime.as_micros() as u64);
+
+            let Some(input) = recorded.input else {
+                eprintln!("Input {} was too large to be recorded", recorded.index);
+                return None;
+            };
+
+            match postcard::from_bytes(&input) {
+                Ok(input) => Some((time, input)),
+                Err(e) => {
+                    eprintln!("Failed to decode input {}: {e}", recorded.index);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn send_request<Req, Resp>(stream: &mut TcpStream, request: Req) -> Resp
+where
+    Req: ExpectedResponse<Response = Resp> + MessagePayload + Serialize,
+    Resp: MessagePayload + DeserializeOwned,
+{
+    let message = Message::new(&request).unwrap();
+    stream.write_all(&message.to_bytes().unwrap()).unwrap();
+
+    let mut framer = CobsFramer::<4096>::default();
+    loop {
+        let mut bytes = [0u8; 256];
+        let n = stream.read(&mut bytes).unwrap();
+        assert!(n > 0, "orchestrator disconnected");
+        framer.push(&bytes[..n]).unwrap();
+
+        if let Some(mut frame) = framer.next_message() {
+            return Message::from_bytes(&mut frame).unwrap().payload().unwrap();
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::simulated_time;
+use embassy_time::{Duration, Instant};
+use hoshiguma_state_machines::StateMachineRun;
+use std::{
+    fmt::Debug,
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Wake, Waker},
+};
+
+/// Waker that notes that the state machine has more work to do.
+#[derive(Default)]
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Feeds recorded inputs into a state machine at the times they were originally sent, printing
+/// every input and output.
+///
+/// Returns the outputs along with the time at which they were produced.
+///
+/// Time only advances between inputs and expiring timers, so timer driven behaviour occurs at the
+/// same time as it did originally.
+/// Replay continues for `run_on` after the last input, to show the outcome of any pending timers.
+pub(crate) fn replay<I: Debug, O: Debug>(
+    mut runner: impl StateMachineRun,
+    inputs: Vec<(Instant, I)>,
+    run_on: Duration,
+    mut send: impl FnMut(I),
+    mut receive: impl FnMut() -> Option<O>,
+) -> Vec<(Instant, O)> {
+    let mut outputs = Vec::new();
+
+    let Some(end) = inputs.last().map(|(time, _)| *time + run_on) else {
+        println!("Nothing to replay");
+        return outputs;
+    };
+
+    let woken = Arc::new(Woken::default());
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    let mut run = pin!(runner.run());
+    let mut inputs = inputs.into_iter().peekable();
+
+    loop {
+        // Run the state machine until it is waiting for an input or timer
+        loop {
+            woken.0.store(false, Ordering::SeqCst);
+            let _ = run.as_mut().poll(&mut cx);
+
+            while let Some(output) = receive() {
+                println!("{} output {:?}", format_time(Instant::now()), output);
+                outputs.push((Instant::now(), output));
+            }
+
+            if !woken.0.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+
+        let next_input = inputs.peek().map(|(time, _)| *time);
+        let next = match (next_input, simulated_time::next_expiration()) {
+            (Some(input), Some(timer)) => input.min(timer),
+            (Some(input), None) => input,
+            (None, Some(timer)) if timer <= end => timer,
+            (None, _) => break,
+        };
+
+        simulated_time::advance_to(next);
+
+        if next_input == Some(next)
+            && let Some((time, input)) = inputs.next()
+        {
+            println!("{} input  {:?}", format_time(time), input);
+            send(input);
+        }
+    }
+
+    outputs
+}
+
+fn format_time(time: Instant) -> String {
+    format!("{:>12.6}", time.as_micros() as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hoshiguma_api::{
+        DesiredMachinePower, Interlock, InterlockAction, MachineRun, Monitor, Severity,
+        hmi::AccessControlState,
+    };
+    use hoshiguma_state_machines::{
+        interlock,
+        machine_power::{self, InputChannel, InputMessage, OutputChannel, OutputMessage},
+    };
+    use std::sync::Mutex;
+    use strum::IntoEnumIterator;
+
+    /// Simulated time is shared by the whole process, so tests must replay one at a time.
+    static SIMULATED_TIME: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn timers_expire_at_their_original_time() {
+        let _time = SIMULATED_TIME.lock().unwrap();
+        simulated_time::reset();
+
+        let input_channel = InputChannel::new();
+        let output_channel = OutputChannel::new();
+        let (runner, _communicator) = machine_power::new(&input_channel, &output_channel);
+
+        let inputs = vec![
+            (
+                Instant::from_secs(1),
+                InputMessage::InterlockAction(InterlockAction::Normal),
+            ),
+            (
+                Instant::from_secs(2),
+                InputMessage::AccessControlState(AccessControlState::Granted),
+            ),
+            (
+                Instant::from_secs(3),
+                InputMessage::MachineRun(MachineRun::Running),
+            ),
+            (
+                Instant::from_secs(4),
+                InputMessage::AccessControlState(AccessControlState::Denied),
+            ),
+        ];
+
+        let outputs = replay(
+            runner,
+            inputs,
+            Duration::from_secs(15 * 60),
+            |input| input_channel.try_send(input).unwrap(),
+            || output_channel.try_receive().ok(),
+        );
+
+        let hold_end = Instant::from_secs(4 + 10 * 60);
+        assert_eq!(
+            outputs,
+            [
+                (
+                    Instant::from_secs(1),
+                    OutputMessage::Power(DesiredMachinePower::Off)
+                ),
+                (
+                    Instant::from_secs(2),
+                    OutputMessage::Power(DesiredMachinePower::On)
+                ),
+                (
+                    Instant::from_secs(4),
+                    OutputMessage::SessionHoldEnd(Some(hold_end))
+                ),
+                (hold_end, OutputMessage::Power(DesiredMachinePower::Off)),
+                (hold_end, OutputMessage::SessionHoldEnd(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_continues_from_restored_snapshot() {
+        let _time = SIMULATED_TIME.lock().unwrap();
+        simulated_time::reset();
+
+        // A job was running when a warning started the grace period, before the oldest held input
+        let mut monitor_states = interlock::MonitorStateMap::new();
+        for monitor in Monitor::iter() {
+            monitor_states.insert(monitor, Severity::Normal).unwrap();
+        }
+        monitor_states
+            .insert(Monitor::CoolerCommunication, Severity::Warning)
+            .unwrap();
+
+        let grace_period_end = Instant::from_secs(60);
+        let snapshot = interlock::Snapshot {
+            config: interlock::Config::default(),
+            monitor_states: monitor_states.clone(),
+            machine_run: MachineRun::Running,
+            grace_period: interlock::GracePeriod::Active {
+                until: grace_period_end,
+            },
+            output_states: Some(monitor_states),
+            output_interlock: Some(Interlock::OperationPermittedUntilIdle),
+            output_action: Some(InterlockAction::Normal),
+            output_grace_period_end: Some(Some(grace_period_end)),
+        };
+
+        // As it would be retrieved from the orchestrator
+        let mut buffer = [0u8; hoshiguma_api::orchestrator::STATE_MACHINE_SNAPSHOT_CAPACITY];
+        let snapshot = postcard::to_slice(&snapshot, &mut buffer).unwrap();
+        let snapshot = postcard::from_bytes(snapshot).unwrap();
+
+        let input_channel = interlock::InputChannel::new();
+        let output_channel = interlock::OutputChannel::new();
+        let (mut runner, _communicator) = interlock::new(&input_channel, &output_channel);
+
+        simulated_time::advance_to(Instant::from_secs(10));
+        runner.restore_snapshot(snapshot);
+
+        let inputs = vec![(
+            Instant::from_secs(20),
+            interlock::InputMessage::Monitor(Monitor::CoolerCommunication, Severity::Warning),
+        )];
+
+        let outputs = replay(
+            runner,
+            inputs,
+            Duration::from_secs(60),
+            |input| input_channel.try_send(input).unwrap(),
+            || output_channel.try_receive().ok(),
+        );
+
+        // Only the outputs that changed from the snapshot are produced, at their original times
+        assert_eq!(
+            outputs,
+            [
+                (
+                    grace_period_end,
+                    interlock::OutputMessage::GracePeriodEnd(None)
+                ),
+                (
+                    grace_period_end,
+                    interlock::OutputMessage::Action(InterlockAction::Disable)
+                ),
+            ]
+        );
+    }
+}
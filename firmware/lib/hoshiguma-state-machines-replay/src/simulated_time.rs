@@ -0,0 +1,70 @@
+//! Time driver for replaying recordings, where time only moves when explicitly advanced.
+//!
+//! Unlike `embassy_time::MockDriver` the time of the next pending timer can be queried, which
+//! allows time to be advanced to exactly the point at which each timer would have expired.
+
+use core::cell::RefCell;
+use critical_section::Mutex;
+use embassy_time::Instant;
+use embassy_time_driver::Driver;
+use embassy_time_queue_utils::queue_generic::Queue;
+use std::task::Waker;
+
+struct SimulatedTimeDriver(Mutex<RefCell<Inner>>);
+
+struct Inner {
+    now: u64,
+    queue: Queue,
+    next_expiration: u64,
+}
+
+embassy_time_driver::time_driver_impl!(
+    static DRIVER: SimulatedTimeDriver = SimulatedTimeDriver(Mutex::new(RefCell::new(Inner {
+        now: 0,
+        queue: Queue::new(),
+        next_expiration: u64::MAX,
+    })))
+);
+
+impl Driver for SimulatedTimeDriver {
+    fn now(&self) -> u64 {
+        critical_section::with(|cs| self.0.borrow_ref(cs).now)
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        critical_section::with(|cs| {
+            let inner = &mut *self.0.borrow_ref_mut(cs);
+            inner.queue.schedule_wake(at, waker);
+            inner.next_expiration = inner.queue.next_expiration(inner.now);
+        })
+    }
+}
+
+/// Time at which the next pending timer expires, if there is one.
+pub(crate) fn next_expiration() -> Option<Instant> {
+    critical_section::with(|cs| {
+        let next = DRIVER.0.borrow_ref(cs).next_expiration;
+        (next != u64::MAX).then(|| Instant::from_ticks(next))
+    })
+}
+
+/// Moves time back to zero and forgets any pending timers, so that each test replays from boot.
+#[cfg(test)]
+pub(crate) fn reset() {
+    critical_section::with(|cs| {
+        *DRIVER.0.borrow_ref_mut(cs) = Inner {
+            now: 0,
+            queue: Queue::new(),
+            next_expiration: u64::MAX,
+        };
+    })
+}
+
+/// Moves time forward, waking any timers that have expired by the new time.
+pub(crate) fn advance_to(time: Instant) {
+    critical_section::with(|cs| {
+        let inner = &mut *DRIVER.0.borrow_ref_mut(cs);
+        inner.now = inner.now.max(time.as_ticks());
+        inner.next_expiration = inner.queue.next_expiration(inner.now);
+    })
+}
@@ -1,8 +1,8 @@
 use crate::{assert_duration, assert_queue_empty};
 use embassy_sync::channel::Channel;
 use embassy_time::{Duration, Instant, Timer};
-use hoshiguma_api::{AcBusPower, AirAssistDemand, AirAssistPump};
-use hoshiguma_state_machines::air_assist::{InputMessage, OutputMessage};
+use hoshiguma_api::{AcBusPower, AirAssistDemand, AirAssistPressure, AirAssistPump, Severity};
+use hoshiguma_state_machines::air_assist::{Config, InputMessage, OutputMessage};
 
 pub(super) async fn test_basic() {
     let input_channel = Channel::new();
@@ -19,6 +19,10 @@ pub(super) async fn test_basic() {
             communicator.receive_output().await,
             OutputMessage::AirAssistPump(AirAssistPump::Idle)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Normal)
+        );
 
         communicator
             .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Demand))
@@ -73,3 +77,212 @@ pub(super) async fn test_basic() {
     })
     .await;
 }
+
+pub(super) async fn test_pressure() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::air_assist::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(20), runner, async || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Normal)
+        );
+
+        // Pressure builds shortly after the pump starts
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Demand))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Run)
+        );
+        Timer::after_millis(500).await;
+        communicator
+            .send_input(InputMessage::AirAssistPressure(AirAssistPressure::Normal))
+            .await;
+        Timer::after_secs(3).await;
+        assert_queue_empty!(communicator);
+
+        // Loss of pressure while the pump is running
+        communicator
+            .send_input(InputMessage::AirAssistPressure(AirAssistPressure::Low))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Critical)
+        );
+
+        communicator
+            .send_input(InputMessage::AirAssistPressure(AirAssistPressure::Normal))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Normal)
+        );
+
+        // Pressure that does not decay after the pump stops
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Idle))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Idle)
+        );
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Warning)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(5),
+            Duration::from_millis(50)
+        );
+
+        communicator
+            .send_input(InputMessage::AirAssistPressure(AirAssistPressure::Low))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Normal)
+        );
+
+        // Pressure that never builds after the pump starts
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Demand))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Run)
+        );
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Critical)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(2),
+            Duration::from_millis(50)
+        );
+
+        // Stopping the pump clears the alarm
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Idle))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Normal)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_configured_timeout() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) = hoshiguma_state_machines::air_assist::new_with_config(
+        &input_channel,
+        &output_channel,
+        Config {
+            timeout: Duration::from_secs(3),
+            ..Default::default()
+        },
+    );
+
+    crate::run_test(Duration::from_secs(20), runner, async || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::PressureSeverity(Severity::Normal)
+        );
+
+        // The configured run on time is used instead of the default
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Demand))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Run)
+        );
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Idle))
+            .await;
+
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Idle)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(3),
+            Duration::from_millis(50)
+        );
+
+        // The run on time can be changed while running
+        communicator
+            .update_config(Config {
+                timeout: Duration::from_millis(500),
+                ..Default::default()
+            })
+            .await;
+        assert_queue_empty!(communicator);
+
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Demand))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Run)
+        );
+        communicator
+            .send_input(InputMessage::AirAssistDemand(AirAssistDemand::Idle))
+            .await;
+
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirAssistPump(AirAssistPump::Idle)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_millis(500),
+            Duration::from_millis(50)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
@@ -1,8 +1,11 @@
 use crate::assert_queue_empty;
 use embassy_sync::channel::Channel;
 use embassy_time::{Duration, Timer};
-use hoshiguma_api::{Severity, cooler::CoolantRate};
-use hoshiguma_state_machines::coolant_rate::{InputMessage, OutputMessage};
+use hoshiguma_api::{
+    Severity,
+    cooler::{CoolantPumpState, CoolantRate, CoolantVolume},
+};
+use hoshiguma_state_machines::coolant_rate::{Config, InputMessage, OutputMessage};
 
 pub(super) async fn test_rate() {
     let input_channel = Channel::new();
@@ -23,6 +26,10 @@ pub(super) async fn test_rate() {
             communicator.receive_output().await,
             OutputMessage::SymmetrySeverity(Severity::Critical)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
 
         communicator
             .send_input(InputMessage::RateReturn(CoolantRate::new(2.48)))
@@ -37,6 +44,47 @@ pub(super) async fn test_rate() {
     .await;
 }
 
+pub(super) async fn test_rate_update_config() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::coolant_rate::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async move || {
+        communicator
+            .send_input(InputMessage::RateFlow(CoolantRate::new(4.0)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateSeverity(Severity::Warning)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SymmetrySeverity(Severity::Critical)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
+
+        // Lowering the threshold reassesses the current rate straight away
+        communicator
+            .update_config(Config {
+                rate_warn: CoolantRate::new(3.5),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateSeverity(Severity::Normal)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
 pub(super) async fn test_rate_symmetry_pump_start() {
     let input_channel = Channel::new();
     let output_channel = Channel::new();
@@ -58,6 +106,10 @@ pub(super) async fn test_rate_symmetry_pump_start() {
             communicator.receive_output().await,
             OutputMessage::SymmetrySeverity(Severity::Critical)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
 
         Timer::after_millis(5100).await;
 
@@ -112,6 +164,10 @@ pub(super) async fn test_rate_symmetry_pump_stop() {
             communicator.receive_output().await,
             OutputMessage::SymmetrySeverity(Severity::Critical)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
 
         Timer::after_millis(5100).await;
 
@@ -160,3 +216,228 @@ pub(super) async fn test_rate_symmetry_pump_stop() {
     })
     .await;
 }
+
+pub(super) async fn test_rate_symmetry_reversed() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::coolant_rate::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async move || {
+        communicator
+            .send_input(InputMessage::CoolantPumpState(CoolantPumpState::Run))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateSeverity(Severity::Critical)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SymmetrySeverity(Severity::Critical)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
+
+        Timer::after_millis(5100).await;
+
+        communicator
+            .send_input(InputMessage::RateFlow(CoolantRate::new(5.2)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateSeverity(Severity::Normal)
+        );
+
+        // More coolant returning than flowing is a sensor fault rather than a leak, so the
+        // severity is limited
+        communicator
+            .send_input(InputMessage::RateReturn(CoolantRate::new(5.5)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SymmetrySeverity(Severity::Information)
+        );
+
+        communicator
+            .send_input(InputMessage::RateReturn(CoolantRate::new(6.5)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SymmetrySeverity(Severity::Warning)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_leak_detection() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::coolant_rate::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(30), runner, async move || {
+        communicator
+            .send_input(InputMessage::CoolantPumpState(CoolantPumpState::Run))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateSeverity(Severity::Critical)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SymmetrySeverity(Severity::Critical)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
+
+        // Volumes are not integrated during the pump run up
+        communicator
+            .send_input(InputMessage::VolumeFlow(CoolantVolume::new(100.0)))
+            .await;
+        communicator
+            .send_input(InputMessage::VolumeReturn(CoolantVolume::new(50.0)))
+            .await;
+        assert_queue_empty!(communicator);
+
+        Timer::after_millis(5100).await;
+
+        // First sample in the window
+        communicator
+            .send_input(InputMessage::VolumeReturn(CoolantVolume::new(50.0)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakedVolume(CoolantVolume::new(0.0))
+        );
+
+        // Samples arriving faster than the sample interval are ignored
+        communicator
+            .send_input(InputMessage::VolumeFlow(CoolantVolume::new(101.0)))
+            .await;
+        communicator
+            .send_input(InputMessage::VolumeReturn(CoolantVolume::new(50.0)))
+            .await;
+        assert_queue_empty!(communicator);
+
+        Timer::after_secs(5).await;
+
+        communicator
+            .send_input(InputMessage::VolumeFlow(CoolantVolume::new(105.0)))
+            .await;
+        communicator
+            .send_input(InputMessage::VolumeReturn(CoolantVolume::new(54.4)))
+            .await;
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::LeakedVolume(v) if v > CoolantVolume::new(0.59) && v < CoolantVolume::new(0.61)
+        ));
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Warning)
+        );
+
+        Timer::after_secs(5).await;
+
+        communicator
+            .send_input(InputMessage::VolumeFlow(CoolantVolume::new(110.0)))
+            .await;
+        communicator
+            .send_input(InputMessage::VolumeReturn(CoolantVolume::new(58.8)))
+            .await;
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::LeakedVolume(v) if v > CoolantVolume::new(1.19) && v < CoolantVolume::new(1.21)
+        ));
+        assert_queue_empty!(communicator);
+
+        // Stopping the pump clears the integrated volume
+        communicator
+            .send_input(InputMessage::CoolantPumpState(CoolantPumpState::Idle))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_leak_detection_calibration_offset() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) = hoshiguma_state_machines::coolant_rate::new_with_config(
+        &input_channel,
+        &output_channel,
+        Config {
+            volume_sample_interval: Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+
+    crate::run_test(Duration::from_secs(20), runner, async move || {
+        communicator
+            .send_input(InputMessage::CoolantPumpState(CoolantPumpState::Run))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateSeverity(Severity::Critical)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SymmetrySeverity(Severity::Critical)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::LeakSeverity(Severity::Normal)
+        );
+
+        Timer::after_millis(5100).await;
+
+        // The return sensor reads consistently slightly lower than the flow sensor, the apparent
+        // leaked volume keeps growing but never becomes more than a warning.
+        for i in 0..=30 {
+            let flow = 100.0 + 5.0 * i as f64;
+            let ret = 50.0 + 4.96 * i as f64;
+
+            communicator
+                .send_input(InputMessage::VolumeFlow(CoolantVolume::new(flow)))
+                .await;
+            communicator
+                .send_input(InputMessage::VolumeReturn(CoolantVolume::new(ret)))
+                .await;
+            assert!(matches!(
+                communicator.receive_output().await,
+                OutputMessage::LeakedVolume(_)
+            ));
+
+            match i {
+                7 => assert_eq!(
+                    communicator.receive_output().await,
+                    OutputMessage::LeakSeverity(Severity::Information)
+                ),
+                13 => assert_eq!(
+                    communicator.receive_output().await,
+                    OutputMessage::LeakSeverity(Severity::Warning)
+                ),
+                _ => {}
+            }
+            assert_queue_empty!(communicator);
+
+            Timer::after_millis(110).await;
+        }
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
@@ -1,9 +1,9 @@
-use crate::assert_queue_empty;
+use crate::{assert_duration, assert_queue_empty};
 use embassy_sync::channel::Channel;
-use embassy_time::Duration;
+use embassy_time::{Duration, Instant, Timer};
 use hoshiguma_api::{
-    AcBusPower, TemperatureSensor, TemperatureSensorReading,
-    cooler::{CompressorState, CoolantPumpState, RadiatorFanState},
+    AcBusPower, EmergencyStop, MachineRun, Severity, TemperatureSensor, TemperatureSensorReading,
+    cooler::{CompressorState, CoolantPumpState, CoolantRate, RadiatorFanState},
 };
 use hoshiguma_state_machines::cooling::{InputMessage, OutputMessage};
 
@@ -14,8 +14,8 @@ pub(super) async fn test_basic() {
     let (runner, mut communicator) =
         hoshiguma_state_machines::cooling::new(&input_channel, &output_channel);
 
-    crate::run_test(Duration::from_secs(10), runner, async move || {
-        // Machine starts off - first update emits initial state for all three outputs.
+    crate::run_test(Duration::from_secs(20), runner, async move || {
+        // Machine starts off - first update emits initial state for all outputs.
         communicator
             .send_input(InputMessage::AcBusPower(AcBusPower::Off))
             .await;
@@ -31,6 +31,14 @@ pub(super) async fn test_basic() {
             communicator.receive_output().await,
             OutputMessage::Compressor(CompressorState::Idle)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
+
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(5.0)))
+            .await;
 
         // Turn the machine on with no temperature reading available.
         // Pump and fan start running; compressor has no temperature to act on so it
@@ -57,6 +65,9 @@ pub(super) async fn test_basic() {
             .await;
         assert_queue_empty!(communicator);
 
+        // Wait out the compressor start delay.
+        Timer::after_secs(10).await;
+
         // Reservoir temperature above the upper threshold (17.5°C): compressor turns on.
         communicator
             .send_input(InputMessage::Temperature(TemperatureSensorReading {
@@ -79,20 +90,509 @@ pub(super) async fn test_basic() {
             .await;
         assert_queue_empty!(communicator);
 
-        // Temperature falls below the lower threshold (17.0°C): compressor idles.
+        // E-stop the machine. Everything goes idle immediately, there is no run on and the
+        // compressor minimum run time does not apply to loss of power.
+        communicator
+            .send_input(InputMessage::EmergencyStop(EmergencyStop::Pressed))
+            .await;
+        assert_queue_empty!(communicator);
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::Off))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Idle)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_run_on() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::cooling::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(80), runner, async move || {
+        // Reservoir temperature in the hysteresis band, the compressor never runs.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(17.2),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
+
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(5.0)))
+            .await;
+
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Run)
+        );
+
+        // Power off, the pump and fan run on for the run on duration.
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::Off))
+            .await;
+        assert_queue_empty!(communicator);
+
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Idle)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(60),
+            Duration::from_millis(50)
+        );
+
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Run)
+        );
+
+        // Power off, then the reservoir cools down which ends the run on early.
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::Off))
+            .await;
+        assert_queue_empty!(communicator);
+
         communicator
             .send_input(InputMessage::Temperature(TemperatureSensorReading {
                 sensor: TemperatureSensor::CoolantReservoir,
                 reading: Ok(16.5),
             }))
             .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Idle)
+        );
+
+        // Warm the reservoir back up and power on.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(17.2),
+            }))
+            .await;
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Run)
+        );
+
+        // Power off while the E-stop is pressed, there is no run on.
+        communicator
+            .send_input(InputMessage::EmergencyStop(EmergencyStop::Pressed))
+            .await;
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::Off))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Idle)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_start_delay() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::cooling::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(20), runner, async move || {
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(20.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Idle)
+        );
         assert_eq!(
             communicator.receive_output().await,
             OutputMessage::Compressor(CompressorState::Idle)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
 
-        // Turn the machine off. Pump and fan go idle; compressor is already idle so
-        // no additional output is emitted for it.
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(5.0)))
+            .await;
+
+        // Power on with cooling demanded, the compressor is held off for the start delay.
+        let before = Instant::now();
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Run)
+        );
+        assert_queue_empty!(communicator);
+
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Run)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(10),
+            Duration::from_millis(50)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_minimum_run_and_off_time() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::cooling::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(270), runner, async move || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
+
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(5.0)))
+            .await;
+
+        // Wait out the compressor start delay.
+        Timer::after_secs(10).await;
+
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(20.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Run)
+        );
+        let started = Instant::now();
+
+        // Demand goes away almost immediately, the compressor keeps running for the minimum run time.
+        Timer::after_secs(1).await;
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(16.5),
+            }))
+            .await;
+        assert_queue_empty!(communicator);
+
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Idle)
+        );
+        let stopped = Instant::now();
+        assert_duration!(
+            started,
+            stopped,
+            Duration::from_secs(60),
+            Duration::from_millis(50)
+        );
+
+        // Demand returns almost immediately, the compressor stays off for the minimum off time.
+        Timer::after_secs(1).await;
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(20.0),
+            }))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // Demand going away again before the compressor restarts cancels the pending start.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(16.5),
+            }))
+            .await;
+
+        // The compressor does not start once the minimum off time has passed.
+        Timer::at(stopped + Duration::from_secs(181)).await;
+        assert_queue_empty!(communicator);
+
+        // Demand returning after the minimum off time starts the compressor immediately.
+        let demanded = Instant::now();
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(20.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Run)
+        );
+        assert!(Instant::now() - demanded <= Duration::from_millis(50));
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_compressor_lockout() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::cooling::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(20), runner, async move || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CoolantPump(CoolantPumpState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RadiatorFan(RadiatorFanState::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
+
+        // Low coolant flow is ignored while the pump runs up.
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(0.0)))
+            .await;
+        assert_queue_empty!(communicator);
+
+        Timer::after_secs(10).await;
+
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(5.0)))
+            .await;
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(20.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Run)
+        );
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // Coolant flow drops below the critical rate, the compressor is stopped immediately
+        // regardless of the minimum run time.
+        // This prevents cooling of a running job, so is a warning.
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(1.5)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Compressor(CompressorState::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Warning)
+        );
+
+        // Flow is restored, the lockout is released but the compressor remains off for the
+        // minimum off time.
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(5.0)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
+        assert_queue_empty!(communicator);
+
+        // Reservoir temperature below the freeze protection floor locks out the compressor.
+        // Cooling is not demanded at this temperature, so this is only informational.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(3.5),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Information)
+        );
+
+        // The lockout is retained until the reservoir has warmed past the release temperature.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(5.0),
+            }))
+            .await;
+        assert_queue_empty!(communicator);
+
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(6.5),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
+
+        // A lockout due to low flow does not bring the release temperature into effect, so the
+        // lockout ends as soon as flow is restored.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(5.0),
+            }))
+            .await;
+        assert_queue_empty!(communicator);
+
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(1.5)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Information)
+        );
+
+        communicator
+            .send_input(InputMessage::CoolantFlowRate(CoolantRate::new(5.0)))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
+
+        // Loss of AC bus power always clears the lockout.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(3.5),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Information)
+        );
+
+        communicator
+            .send_input(InputMessage::EmergencyStop(EmergencyStop::Pressed))
+            .await;
         communicator
             .send_input(InputMessage::AcBusPower(AcBusPower::Off))
             .await;
@@ -104,6 +604,10 @@ pub(super) async fn test_basic() {
             communicator.receive_output().await,
             OutputMessage::RadiatorFan(RadiatorFanState::Idle)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::CompressorLockoutSeverity(Severity::Normal)
+        );
 
         assert_queue_empty!(communicator);
     })
@@ -1,7 +1,9 @@
 use crate::assert_queue_empty;
 use embassy_sync::channel::Channel;
 use embassy_time::Duration;
-use hoshiguma_api::{AirflowSensorMeasurementInner, FumeExtractionFan, Severity};
+use hoshiguma_api::{
+    AirflowSensorMeasurementInner, ExtractionAirflowTrend, FumeExtractionFan, Severity,
+};
 use hoshiguma_state_machines::extraction_airflow::{InputMessage, OutputMessage};
 
 pub(super) async fn test_basic() {
@@ -115,3 +117,149 @@ pub(super) async fn test_basic() {
     })
     .await;
 }
+
+pub(super) async fn test_filter_trend() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::extraction_airflow::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(30), runner, async || {
+        // Restore a trend that has dropped a little over 24% from the reference
+        communicator
+            .send_input(InputMessage::RestoreTrend(ExtractionAirflowTrend {
+                average: 60.5,
+                reference: 80.0,
+                runs: 10,
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::FilterSeverity(Severity::Information)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::FunctionalSeverity(Severity::Normal)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::AirflowSeverity(Severity::Normal)
+        );
+
+        // A fan run with airflow below the average pulls the trend down further, the filter
+        // remains due for service but this is never more than informational
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Run))
+            .await;
+        embassy_time::Timer::after_millis(4100).await;
+        for _ in 0..10 {
+            communicator
+                .send_input(InputMessage::ExtractionAirflowReading(Ok(
+                    AirflowSensorMeasurementInner {
+                        differential_pressure: 53.0,
+                        temperature: 0.0,
+                    },
+                )))
+                .await;
+        }
+        assert_queue_empty!(communicator);
+
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Idle))
+            .await;
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::Trend(ExtractionAirflowTrend { average, reference: 80.0, runs: 11 })
+                if average > 59.7 && average < 59.8
+        ));
+        assert_queue_empty!(communicator);
+
+        // A run too short to be representative does not change the trend
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Run))
+            .await;
+        embassy_time::Timer::after_millis(4100).await;
+        for _ in 0..3 {
+            communicator
+                .send_input(InputMessage::ExtractionAirflowReading(Ok(
+                    AirflowSensorMeasurementInner {
+                        differential_pressure: 100.0,
+                        temperature: 0.0,
+                    },
+                )))
+                .await;
+        }
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Idle))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // Airflow recovers after the filter is serviced, the trend takes a number of runs to follow
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Run))
+            .await;
+        embassy_time::Timer::after_millis(4100).await;
+        for _ in 0..10 {
+            communicator
+                .send_input(InputMessage::ExtractionAirflowReading(Ok(
+                    AirflowSensorMeasurementInner {
+                        differential_pressure: 100.0,
+                        temperature: 0.0,
+                    },
+                )))
+                .await;
+        }
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Idle))
+            .await;
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::Trend(ExtractionAirflowTrend { average, reference: 80.0, runs: 12 })
+                if average > 63.7 && average < 63.8
+        ));
+        assert_queue_empty!(communicator);
+
+        // Resetting the trend after the filter is replaced clears the service indication, the
+        // reference is then set by the airflow with the new filter
+        communicator.send_input(InputMessage::ResetTrend).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Trend(ExtractionAirflowTrend::default())
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::FilterSeverity(Severity::Normal)
+        );
+        assert_queue_empty!(communicator);
+
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Run))
+            .await;
+        embassy_time::Timer::after_millis(4100).await;
+        for _ in 0..10 {
+            communicator
+                .send_input(InputMessage::ExtractionAirflowReading(Ok(
+                    AirflowSensorMeasurementInner {
+                        differential_pressure: 100.0,
+                        temperature: 0.0,
+                    },
+                )))
+                .await;
+        }
+        communicator
+            .send_input(InputMessage::FumeExtractionFan(FumeExtractionFan::Idle))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Trend(ExtractionAirflowTrend {
+                average: 100.0,
+                reference: 100.0,
+                runs: 1,
+            })
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
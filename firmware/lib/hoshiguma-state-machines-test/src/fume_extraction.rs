@@ -1,7 +1,9 @@
 use crate::{assert_duration, assert_queue_empty};
 use embassy_sync::channel::Channel;
 use embassy_time::{Duration, Instant, Timer};
-use hoshiguma_api::{AcBusPower, FumeExtractionFan, FumeExtractionMode, MachineRun};
+use hoshiguma_api::{
+    AcBusPower, AirflowSensorMeasurementInner, FumeExtractionFan, FumeExtractionMode, MachineRun,
+};
 use hoshiguma_state_machines::fume_extraction::{InputMessage, OutputMessage};
 
 pub(super) async fn test_basic() {
@@ -19,6 +21,10 @@ pub(super) async fn test_basic() {
             communicator.receive_output().await,
             OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::Automatic)
+        );
 
         communicator
             .send_input(InputMessage::MachineRun(MachineRun::Running))
@@ -53,6 +59,125 @@ pub(super) async fn test_basic() {
     .await;
 }
 
+pub(super) async fn test_run_on_job_length() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::fume_extraction::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(310), runner, async || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::Automatic)
+        );
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Run)
+        );
+
+        // A four minute job gets a one minute run on
+        Timer::after_secs(240).await;
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        assert_queue_empty!(communicator);
+
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(60),
+            Duration::from_millis(50)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_run_on_until_ambient() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::fume_extraction::new(&input_channel, &output_channel);
+
+    let reading = |temperature| {
+        InputMessage::ExtractionAirflowReading(Ok(AirflowSensorMeasurementInner {
+            differential_pressure: 0.0,
+            temperature,
+        }))
+    };
+
+    crate::run_test(Duration::from_secs(60), runner, async || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::Automatic)
+        );
+
+        // Ambient temperature is taken while the fan is stopped
+        communicator.send_input(reading(20.0)).await;
+        assert_queue_empty!(communicator);
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Run)
+        );
+
+        communicator.send_input(reading(30.0)).await;
+        Timer::after_millis(500).await;
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+
+        // Keeps running beyond the minimum run on while the airflow is warm
+        Timer::after_secs(50).await;
+        assert_queue_empty!(communicator);
+
+        communicator.send_input(reading(25.0)).await;
+        assert_queue_empty!(communicator);
+
+        // Stops once the airflow is near ambient
+        communicator.send_input(reading(21.5)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
 pub(super) async fn test_mode() {
     let input_channel = Channel::new();
     let output_channel = Channel::new();
@@ -68,6 +193,10 @@ pub(super) async fn test_mode() {
             communicator.receive_output().await,
             OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::Automatic)
+        );
 
         communicator
             .send_input(InputMessage::Mode(FumeExtractionMode::OverrideRun))
@@ -76,6 +205,10 @@ pub(super) async fn test_mode() {
             communicator.receive_output().await,
             OutputMessage::ExtractionFan(FumeExtractionFan::Run)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::OverrideRun)
+        );
 
         communicator
             .send_input(InputMessage::AcBusPower(AcBusPower::Off))
@@ -100,6 +233,65 @@ pub(super) async fn test_mode() {
             communicator.receive_output().await,
             OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
         );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::Automatic)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_mode_timeout() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::fume_extraction::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(310), runner, async || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::Automatic)
+        );
+
+        communicator
+            .send_input(InputMessage::Mode(FumeExtractionMode::OverrideRun))
+            .await;
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Run)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::OverrideRun)
+        );
+
+        // Override reverts to automatic after the timeout
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ExtractionFan(FumeExtractionFan::Idle)
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(300),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Mode(FumeExtractionMode::Automatic)
+        );
 
         assert_queue_empty!(communicator);
     })
@@ -3,7 +3,7 @@ use embassy_sync::channel::Channel;
 use embassy_time::{Duration, Instant, Timer};
 use heapless::Vec;
 use hoshiguma_api::{
-    DesiredMachinePower, Interlock, MachineRun, Monitor, Severity,
+    DesiredMachinePower, FumeExtractionMode, Interlock, MachineRun, Monitor, Severity,
     hmi::{AccessControlRawInput, OnscreenMessage, StatusScreenInfo},
 };
 use hoshiguma_state_machines::{
@@ -32,7 +32,10 @@ pub(super) async fn test_states() {
                 machine_power: DesiredMachinePower::Off,
                 interlock: Interlock::OperationDenied,
                 running: MachineRun::Idle,
-                messages: Vec::new()
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
             })
         );
         let end = Instant::now();
@@ -54,7 +57,10 @@ pub(super) async fn test_states() {
                 machine_power: DesiredMachinePower::On,
                 interlock: Interlock::OperationDenied,
                 running: MachineRun::Idle,
-                messages: Vec::new()
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
             })
         );
         let end = Instant::now();
@@ -75,7 +81,10 @@ pub(super) async fn test_states() {
                 machine_power: DesiredMachinePower::On,
                 interlock: Interlock::OperationPermitted,
                 running: MachineRun::Idle,
-                messages: Vec::new()
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
             })
         );
 
@@ -94,7 +103,29 @@ pub(super) async fn test_states() {
                 machine_power: DesiredMachinePower::On,
                 interlock: Interlock::OperationPermitted,
                 running: MachineRun::Running,
-                messages: Vec::new()
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
+            })
+        );
+
+        communicator
+            .send_input(InputMessage::FumeExtractionMode(
+                FumeExtractionMode::OverrideRun,
+            ))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::StatusScreen(StatusScreenInfo {
+                access_control: AccessControlRawInput::Granted,
+                machine_power: DesiredMachinePower::On,
+                interlock: Interlock::OperationPermitted,
+                running: MachineRun::Running,
+                fume_extraction_mode: FumeExtractionMode::OverrideRun,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
             })
         );
 
@@ -141,7 +172,10 @@ pub(super) async fn test_states_debounce() {
                 machine_power: DesiredMachinePower::On,
                 interlock: Interlock::OperationPermitted,
                 running: MachineRun::Idle,
-                messages: Vec::new()
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
             })
         );
 
@@ -166,7 +200,10 @@ pub(super) async fn test_states_debounce() {
                 machine_power: DesiredMachinePower::On,
                 interlock: Interlock::OperationPermitted,
                 running: MachineRun::Running,
-                messages: Vec::new()
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
             })
         );
 
@@ -174,6 +211,155 @@ pub(super) async fn test_states_debounce() {
     })
     .await;
 }
+pub(super) async fn test_grace_period_countdown() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::hmi_status_screen::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        communicator
+            .send_input(InputMessage::GracePeriodEnd(Some(
+                Instant::now() + Duration::from_secs(3),
+            )))
+            .await;
+
+        // The countdown is included with the debounced status
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::StatusScreen(StatusScreenInfo {
+                access_control: AccessControlRawInput::Idle,
+                machine_power: DesiredMachinePower::Off,
+                interlock: Interlock::OperationDenied,
+                running: MachineRun::Running,
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: Some(core::time::Duration::from_secs(3)),
+                session_hold_remaining: None,
+            })
+        );
+
+        // And then updated every second without any further input
+        let start = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::StatusScreen(StatusScreenInfo {
+                access_control: AccessControlRawInput::Idle,
+                machine_power: DesiredMachinePower::Off,
+                interlock: Interlock::OperationDenied,
+                running: MachineRun::Running,
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: Some(core::time::Duration::from_secs(2)),
+                session_hold_remaining: None,
+            })
+        );
+        let end = Instant::now();
+        assert_duration!(start, end, Duration::from_secs(1), Duration::from_millis(5));
+
+        // Grace period ends, countdown is removed
+        communicator
+            .send_input(InputMessage::GracePeriodEnd(None))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::StatusScreen(StatusScreenInfo {
+                access_control: AccessControlRawInput::Idle,
+                machine_power: DesiredMachinePower::Off,
+                interlock: Interlock::OperationDenied,
+                running: MachineRun::Running,
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
+            })
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_session_hold_countdown() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::hmi_status_screen::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::DesiredMachinePower(DesiredMachinePower::On))
+            .await;
+        communicator
+            .send_input(InputMessage::SessionHoldEnd(Some(
+                Instant::now() + Duration::from_secs(90),
+            )))
+            .await;
+
+        // The countdown is included with the debounced status
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::StatusScreen(StatusScreenInfo {
+                access_control: AccessControlRawInput::Idle,
+                machine_power: DesiredMachinePower::On,
+                interlock: Interlock::OperationDenied,
+                running: MachineRun::Idle,
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: Some(core::time::Duration::from_secs(90)),
+            })
+        );
+
+        // And then updated every second without any further input
+        let start = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::StatusScreen(StatusScreenInfo {
+                access_control: AccessControlRawInput::Idle,
+                machine_power: DesiredMachinePower::On,
+                interlock: Interlock::OperationDenied,
+                running: MachineRun::Idle,
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: Some(core::time::Duration::from_secs(89)),
+            })
+        );
+        let end = Instant::now();
+        assert_duration!(start, end, Duration::from_secs(1), Duration::from_millis(5));
+
+        // Power is removed and the hold ends, countdown is removed
+        communicator
+            .send_input(InputMessage::DesiredMachinePower(DesiredMachinePower::Off))
+            .await;
+        communicator
+            .send_input(InputMessage::SessionHoldEnd(None))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::StatusScreen(StatusScreenInfo {
+                access_control: AccessControlRawInput::Idle,
+                machine_power: DesiredMachinePower::Off,
+                interlock: Interlock::OperationDenied,
+                running: MachineRun::Idle,
+                fume_extraction_mode: FumeExtractionMode::Automatic,
+                messages: Vec::new(),
+                grace_period_remaining: None,
+                session_hold_remaining: None,
+            })
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
 pub(super) async fn test_statuses_to_messages() {
     let input_channel = Channel::new();
     let output_channel = Channel::new();
@@ -1,8 +1,8 @@
-use crate::assert_queue_empty;
+use crate::{assert_duration, assert_queue_empty};
 use embassy_sync::channel::Channel;
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 use hoshiguma_api::{Interlock, InterlockAction, MachineRun, Monitor, Severity};
-use hoshiguma_state_machines::interlock::{InputMessage, OutputMessage};
+use hoshiguma_state_machines::interlock::{Config, InputMessage, OutputMessage};
 use strum::{EnumCount, IntoEnumIterator};
 
 pub(super) async fn test_init_denied() {
@@ -53,7 +53,7 @@ pub(super) async fn test_become_happy_then_get_sad() {
             ));
 
             const FIRST_MONITOR: Monitor = Monitor::AcBusPower;
-            const LAST_MONITOR: Monitor = Monitor::ExtractionAirflowSensorFunctional;
+            const LAST_MONITOR: Monitor = Monitor::ServiceDue;
 
             if monitor == FIRST_MONITOR {
                 assert_eq!(
@@ -234,14 +234,110 @@ pub(super) async fn test_allow_until_idle() {
             OutputMessage::Interlock(Interlock::OperationPermittedUntilIdle)
         );
 
+        // The job is given a limited amount of time to finish
+        let start = Instant::now();
+        match communicator.receive_output().await {
+            OutputMessage::GracePeriodEnd(Some(end)) => {
+                assert_duration!(
+                    start,
+                    end,
+                    Duration::from_secs(10 * 60),
+                    Duration::from_millis(5)
+                );
+            }
+            other => panic!("Unexpected output {:?}", other),
+        }
+
         // The machine finishes, but the fault condition did not go away.
         communicator
             .send_input(InputMessage::MachineRun(MachineRun::Idle))
             .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::GracePeriodEnd(None)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Action(InterlockAction::Disable)
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_grace_period_expires_while_running() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) = hoshiguma_state_machines::interlock::new_with_config(
+        &input_channel,
+        &output_channel,
+        Config {
+            grace_period_max_duration: Duration::from_secs(2),
+        },
+    );
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        for monitor in Monitor::iter() {
+            if monitor == Monitor::InterlockTripped {
+                continue;
+            }
+
+            communicator
+                .send_input(InputMessage::Monitor(monitor, Severity::Normal))
+                .await;
+        }
+
+        // Consume all those mesaages that we do not care about for this test.
+        Timer::after_millis(10).await;
+        while communicator.receive_channel_len() > 0 {
+            let _ = communicator.receive_output().await;
+        }
+
+        // Simulate the machine running a job
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // Simulate a minor fuck up that should allow the machine to finish the current job
+        communicator
+            .send_input(InputMessage::Monitor(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Warning,
+            ))
+            .await;
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::States(_)
+        ));
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Interlock(Interlock::OperationPermittedUntilIdle)
+        );
+
+        let start = Instant::now();
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::GracePeriodEnd(Some(_))
+        ));
+
+        // The job is still running when the grace period expires, so the machine is disabled anyway
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::GracePeriodEnd(None)
+        );
         assert_eq!(
             communicator.receive_output().await,
             OutputMessage::Action(InterlockAction::Disable)
         );
+        assert_duration!(
+            start,
+            Instant::now(),
+            Duration::from_secs(2),
+            Duration::from_millis(5)
+        );
 
         assert_queue_empty!(communicator);
     })
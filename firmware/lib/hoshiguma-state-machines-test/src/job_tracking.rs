@@ -0,0 +1,174 @@
+use crate::{assert_duration, assert_queue_empty};
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, Timer};
+use hoshiguma_api::{
+    AirflowSensorMeasurementInner, DesiredMachinePower, Doors, InterlockAction, MachineRun,
+    TemperatureSensor, TemperatureSensorReading, orchestrator::JobEndReason,
+};
+use hoshiguma_state_machines::job_tracking::{InputMessage, OutputMessage};
+
+pub(super) async fn test_basic() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::job_tracking::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Normal))
+            .await;
+        communicator
+            .send_input(InputMessage::DesiredMachinePower(DesiredMachinePower::On))
+            .await;
+        communicator
+            .send_input(InputMessage::Doors(Doors::Closed))
+            .await;
+
+        // Readings outside of a job are not recorded
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(30.0),
+            }))
+            .await;
+        assert_queue_empty!(communicator);
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+
+        for (temperature, differential_pressure) in [(16.0, 80.0), (18.5, 65.0), (17.0, 70.0)] {
+            communicator
+                .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                    sensor: TemperatureSensor::CoolantReservoir,
+                    reading: Ok(temperature),
+                }))
+                .await;
+            communicator
+                .send_input(InputMessage::ExtractionAirflowReading(Ok(
+                    AirflowSensorMeasurementInner {
+                        differential_pressure,
+                        temperature: 25.0,
+                    },
+                )))
+                .await;
+        }
+
+        // Only the coolant reservoir is tracked
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::OrchastratorPcb,
+                reading: Ok(40.0),
+            }))
+            .await;
+
+        // Opening and closing the doors
+        communicator
+            .send_input(InputMessage::Doors(Doors::Open))
+            .await;
+        communicator
+            .send_input(InputMessage::Doors(Doors::Open))
+            .await;
+        communicator
+            .send_input(InputMessage::Doors(Doors::Closed))
+            .await;
+        assert_queue_empty!(communicator);
+
+        Timer::after_millis(500).await;
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        match communicator.receive_output().await {
+            OutputMessage::JobEnded(summary) => {
+                assert_duration!(
+                    Duration::from_ticks(0),
+                    summary.duration,
+                    Duration::from_millis(525),
+                    Duration::from_millis(10)
+                );
+                assert_eq!(summary.peak_coolant_reservoir_temperature, Some(18.5));
+                assert_eq!(summary.minimum_extraction_airflow, Some(65.0));
+                assert_eq!(summary.interlock_events, 0);
+                assert_eq!(summary.door_openings, 1);
+                assert_eq!(summary.end_reason, JobEndReason::Completed);
+            }
+        }
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_end_reason() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::job_tracking::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Normal))
+            .await;
+        communicator
+            .send_input(InputMessage::DesiredMachinePower(DesiredMachinePower::On))
+            .await;
+
+        // Job stopped by the interlock
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Disable))
+            .await;
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        match communicator.receive_output().await {
+            OutputMessage::JobEnded(summary) => {
+                assert_eq!(summary.peak_coolant_reservoir_temperature, None);
+                assert_eq!(summary.minimum_extraction_airflow, None);
+                assert_eq!(summary.interlock_events, 1);
+                assert_eq!(summary.end_reason, JobEndReason::Interlock);
+            }
+        }
+
+        // The interlock recovering during a job is counted, but the job still completes
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Normal))
+            .await;
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        match communicator.receive_output().await {
+            OutputMessage::JobEnded(summary) => {
+                assert_eq!(summary.interlock_events, 1);
+                assert_eq!(summary.end_reason, JobEndReason::Completed);
+            }
+        }
+
+        // Job stopped by power being removed
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        communicator
+            .send_input(InputMessage::DesiredMachinePower(DesiredMachinePower::Off))
+            .await;
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        match communicator.receive_output().await {
+            OutputMessage::JobEnded(summary) => {
+                assert_eq!(summary.interlock_events, 0);
+                assert_eq!(summary.end_reason, JobEndReason::PowerRemoved);
+            }
+        }
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
@@ -1,7 +1,7 @@
-use crate::assert_queue_empty;
+use crate::{assert_duration, assert_queue_empty};
 use embassy_sync::channel::Channel;
-use embassy_time::Duration;
-use hoshiguma_api::{DesiredMachinePower, InterlockAction, hmi::AccessControlState};
+use embassy_time::{Duration, Instant};
+use hoshiguma_api::{DesiredMachinePower, InterlockAction, MachineRun, hmi::AccessControlState};
 use hoshiguma_state_machines::machine_power::{InputMessage, OutputMessage};
 
 pub(super) async fn test_basic() {
@@ -68,3 +68,189 @@ pub(super) async fn test_basic() {
     })
     .await;
 }
+
+pub(super) async fn test_session_hold() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::machine_power::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Normal))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::Off)
+        );
+
+        communicator
+            .send_input(InputMessage::AccessControlState(
+                AccessControlState::Granted,
+            ))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::On)
+        );
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // Access ends during a job, power is held on
+        let before = Instant::now();
+        communicator
+            .send_input(InputMessage::AccessControlState(AccessControlState::Denied))
+            .await;
+        match communicator.receive_output().await {
+            OutputMessage::SessionHoldEnd(Some(end)) => {
+                assert_duration!(
+                    before,
+                    end,
+                    Duration::from_secs(10 * 60),
+                    Duration::from_millis(5)
+                );
+            }
+            other => panic!("Unexpected output {:?}", other),
+        }
+        assert_queue_empty!(communicator);
+
+        // Job ends, power is removed
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::Off)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SessionHoldEnd(None)
+        );
+        assert_queue_empty!(communicator);
+
+        // A new job without access does not turn the power back on
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_session_hold_shutdown() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::machine_power::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Normal))
+            .await;
+        communicator
+            .send_input(InputMessage::AccessControlState(
+                AccessControlState::Granted,
+            ))
+            .await;
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::Off)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::On)
+        );
+
+        communicator
+            .send_input(InputMessage::AccessControlState(AccessControlState::Denied))
+            .await;
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::SessionHoldEnd(Some(_))
+        ));
+
+        // Shutdown removes power immediately, even during a session hold
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Shutdown))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::Off)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::SessionHoldEnd(None)
+        );
+
+        // And the hold does not resume once the interlock recovers
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Normal))
+            .await;
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_snapshot() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::machine_power::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::InterlockAction(InterlockAction::Normal))
+            .await;
+        communicator
+            .send_input(InputMessage::AccessControlState(
+                AccessControlState::Granted,
+            ))
+            .await;
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::Off)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Power(DesiredMachinePower::On)
+        );
+        assert_queue_empty!(communicator);
+
+        // The snapshot reflects all inputs once the state machine is waiting again
+        let snapshot = hoshiguma_state_machines::machine_power::snapshot()
+            .await
+            .unwrap();
+        assert_eq!(snapshot.access_control, AccessControlState::Granted);
+        assert_eq!(snapshot.interlock, InterlockAction::Normal);
+        assert_eq!(snapshot.machine_run, MachineRun::Running);
+        assert_eq!(snapshot.session_hold_end, None);
+
+        communicator
+            .send_input(InputMessage::AccessControlState(AccessControlState::Denied))
+            .await;
+        assert!(matches!(
+            communicator.receive_output().await,
+            OutputMessage::SessionHoldEnd(Some(_))
+        ));
+        assert_queue_empty!(communicator);
+
+        let snapshot = hoshiguma_state_machines::machine_power::snapshot()
+            .await
+            .unwrap();
+        assert_eq!(snapshot.access_control, AccessControlState::Denied);
+        assert!(snapshot.session_hold_end.is_some());
+    })
+    .await;
+}
@@ -8,8 +8,11 @@ mod extraction_airflow;
 mod fume_extraction;
 mod hmi_status_screen;
 mod interlock;
+mod job_tracking;
 mod machine_power;
+mod runtime_counters;
 mod status_light;
+mod temperature_filter;
 mod temperatures;
 
 use defmt::info;
@@ -25,26 +28,62 @@ async fn main(_spawner: Spawner) {
     let _ = embassy_rp::init(Default::default());
 
     air_assist::test_basic().await;
+    air_assist::test_pressure().await;
+    air_assist::test_configured_timeout().await;
     coolant_rate::test_rate().await;
+    coolant_rate::test_rate_update_config().await;
     coolant_rate::test_rate_symmetry_pump_start().await;
     coolant_rate::test_rate_symmetry_pump_stop().await;
+    coolant_rate::test_rate_symmetry_reversed().await;
+    coolant_rate::test_leak_detection().await;
+    coolant_rate::test_leak_detection_calibration_offset().await;
     cooling::test_basic().await;
+    cooling::test_run_on().await;
+    cooling::test_start_delay().await;
+    cooling::test_minimum_run_and_off_time().await;
+    cooling::test_compressor_lockout().await;
     extraction_airflow::test_basic().await;
+    extraction_airflow::test_filter_trend().await;
     fume_extraction::test_basic().await;
+    fume_extraction::test_run_on_job_length().await;
+    fume_extraction::test_run_on_until_ambient().await;
     fume_extraction::test_mode().await;
+    fume_extraction::test_mode_timeout().await;
     hmi_status_screen::test_states().await;
     hmi_status_screen::test_states_debounce().await;
+    hmi_status_screen::test_grace_period_countdown().await;
+    hmi_status_screen::test_session_hold_countdown().await;
     hmi_status_screen::test_statuses_to_messages().await;
     interlock::test_init_denied().await;
     interlock::test_become_happy_then_get_sad().await;
     interlock::test_lockout().await;
     interlock::test_allow_until_idle().await;
+    interlock::test_grace_period_expires_while_running().await;
+    job_tracking::test_basic().await;
+    job_tracking::test_end_reason().await;
     machine_power::test_basic().await;
+    machine_power::test_session_hold().await;
+    machine_power::test_session_hold_shutdown().await;
+    machine_power::test_snapshot().await;
+    runtime_counters::test_accumulate().await;
+    runtime_counters::test_service_due().await;
     status_light::test_basic().await;
+    status_light::test_machine_protected().await;
+    status_light::test_maintenance().await;
+    status_light::test_job_complete().await;
+    temperature_filter::test_median().await;
+    temperature_filter::test_reset_value().await;
+    temperature_filter::test_step_change().await;
+    temperature_filter::test_rapid_rise().await;
     temperatures::test_basic().await;
     temperatures::test_electronics_temperature().await;
     temperatures::test_coolant_reservoir_temperature().await;
     temperatures::test_failed_sensor().await;
+    temperatures::test_low_temperature().await;
+    temperatures::test_configured_thresholds().await;
+    temperatures::test_rate_of_rise().await;
+    temperatures::test_rate_of_rise_idle().await;
+    temperatures::test_rate_of_rise_through_filter().await;
 
     info!("End of tests!");
 }
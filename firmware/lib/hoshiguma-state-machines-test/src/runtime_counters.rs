@@ -0,0 +1,139 @@
+use crate::assert_queue_empty;
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, Timer};
+use hoshiguma_api::{
+    Component, MachineRun, RuntimeCounter, RuntimeCounters, Severity, cooler::CoolantPumpState,
+};
+use hoshiguma_state_machines::runtime_counters::{InputMessage, OutputMessage};
+
+pub(super) async fn test_accumulate() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::runtime_counters::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        let restored = RuntimeCounters {
+            coolant_pump: RuntimeCounter {
+                total: core::time::Duration::from_secs(100),
+                since_service: core::time::Duration::from_secs(10),
+            },
+            ..Default::default()
+        };
+
+        communicator
+            .send_input(InputMessage::RestoreCounters(restored))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Counters(restored)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ServiceSeverity(Severity::Normal)
+        );
+
+        // Repeated states do not update the counters
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // Counters are emitted when a component starts and stops
+        communicator
+            .send_input(InputMessage::CoolantPumpState(CoolantPumpState::Run))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Counters(restored)
+        );
+
+        Timer::after_millis(500).await;
+
+        communicator
+            .send_input(InputMessage::CoolantPumpState(CoolantPumpState::Idle))
+            .await;
+        match communicator.receive_output().await {
+            OutputMessage::Counters(counters) => {
+                let run_time = counters.coolant_pump.total - restored.coolant_pump.total;
+                assert!(
+                    run_time >= core::time::Duration::from_millis(495)
+                        && run_time <= core::time::Duration::from_millis(505),
+                    "Run time {:?} is not approximately 500ms",
+                    run_time
+                );
+                assert_eq!(
+                    counters.coolant_pump.since_service - restored.coolant_pump.since_service,
+                    run_time
+                );
+                assert_eq!(counters.laser_tube, RuntimeCounter::default());
+            }
+            other => panic!("Unexpected output {:?}", other),
+        }
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_service_due() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::runtime_counters::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        // Laser tube is past its service interval
+        let restored = RuntimeCounters {
+            laser_tube: RuntimeCounter {
+                total: core::time::Duration::from_secs(1200 * 60 * 60),
+                since_service: core::time::Duration::from_secs(501 * 60 * 60),
+            },
+            ..Default::default()
+        };
+
+        communicator
+            .send_input(InputMessage::RestoreCounters(restored))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Counters(restored)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ServiceSeverity(Severity::Information)
+        );
+
+        // Resetting another component has no effect on the severity
+        communicator
+            .send_input(InputMessage::ResetServiceCounter(Component::Compressor))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Counters(restored)
+        );
+        assert_queue_empty!(communicator);
+
+        // Servicing the laser tube resets only the time since service
+        communicator
+            .send_input(InputMessage::ResetServiceCounter(Component::LaserTube))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Counters(RuntimeCounters {
+                laser_tube: RuntimeCounter {
+                    total: restored.laser_tube.total,
+                    since_service: core::time::Duration::ZERO,
+                },
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::ServiceSeverity(Severity::Normal)
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
@@ -1,6 +1,6 @@
-use crate::assert_queue_empty;
+use crate::{assert_duration, assert_queue_empty};
 use embassy_sync::channel::Channel;
-use embassy_time::Duration;
+use embassy_time::{Duration, Instant};
 use hoshiguma_api::{
     AcBusPower, Interlock, MachineRun,
     rear_sensor_board::{LightPattern, StatusLightSettings},
@@ -27,6 +27,7 @@ pub(super) async fn test_basic() {
                 red: LightPattern::ON,
                 amber: LightPattern::OFF,
                 green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
             })
         );
         assert_eq!(
@@ -35,6 +36,7 @@ pub(super) async fn test_basic() {
                 red: LightPattern::OFF,
                 amber: LightPattern::ON,
                 green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
             })
         );
         assert_eq!(
@@ -43,6 +45,7 @@ pub(super) async fn test_basic() {
                 red: LightPattern::OFF,
                 amber: LightPattern::OFF,
                 green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
             })
         );
         assert_eq!(
@@ -51,6 +54,7 @@ pub(super) async fn test_basic() {
                 red: LightPattern::ON,
                 amber: LightPattern::ON,
                 green: LightPattern::ON,
+                buzzer: LightPattern::ON,
             })
         );
 
@@ -61,6 +65,7 @@ pub(super) async fn test_basic() {
                 red: LightPattern::ON, // Interlock is OperationDenied by default
                 amber: LightPattern::OFF,
                 green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
             })
         );
 
@@ -74,6 +79,7 @@ pub(super) async fn test_basic() {
                 red: LightPattern::OFF,
                 amber: LightPattern::OFF,
                 green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
             })
         );
 
@@ -87,6 +93,7 @@ pub(super) async fn test_basic() {
                 red: LightPattern::OFF,
                 amber: LightPattern::BLINK_1HZ,
                 green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
             })
         );
 
@@ -102,9 +109,259 @@ pub(super) async fn test_basic() {
                 red: LightPattern::OFF,
                 amber: LightPattern::BLINK_1HZ,
                 green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
             })
         );
 
+        // Job continues within the grace period
+        communicator
+            .send_input(InputMessage::GracePeriodEnd(Some(
+                Instant::now() + Duration::from_secs(60),
+            )))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::BLINK_2HZ,
+                amber: LightPattern::BLINK_1HZ,
+                green: LightPattern::OFF,
+                buzzer: LightPattern::PULSE,
+            })
+        );
+
+        // Grace period is over
+        communicator
+            .send_input(InputMessage::GracePeriodEnd(None))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::BLINK_1HZ,
+                green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
+            })
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_machine_protected() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::status_light::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        // Startup sequence
+        for _ in 0..4 {
+            communicator.receive_output().await;
+        }
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::ON,
+                amber: LightPattern::OFF,
+                green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
+            })
+        );
+
+        // A latched trip is distinct from operation being denied
+        communicator
+            .send_input(InputMessage::Interlock(Interlock::MachineProtected))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::BLINK_5HZ,
+                amber: LightPattern::OFF,
+                green: LightPattern::OFF,
+                buzzer: LightPattern::BLINK_1HZ,
+            })
+        );
+
+        // And continues to be shown when the AC bus is turned off
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::Off))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // The startup sequence is always followed by the current settings
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        // Startup sequence
+        for _ in 0..4 {
+            communicator.receive_output().await;
+        }
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::BLINK_5HZ,
+                amber: LightPattern::OFF,
+                green: LightPattern::OFF,
+                buzzer: LightPattern::BLINK_1HZ,
+            })
+        );
+
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_maintenance() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::status_light::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        communicator
+            .send_input(InputMessage::Interlock(Interlock::OperationPermitted))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings::default())
+        );
+
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        // Startup sequence
+        for _ in 0..4 {
+            communicator.receive_output().await;
+        }
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::OFF,
+                green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
+            })
+        );
+
+        // Amber and green alternate in maintenance mode
+        communicator
+            .send_input(InputMessage::Maintenance(true))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::BLINK_1HZ,
+                green: LightPattern::BLINK_1HZ_INVERTED,
+                buzzer: LightPattern::OFF,
+            })
+        );
+
+        // Running takes precedence
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::BLINK_1HZ,
+                green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
+            })
+        );
+
+        communicator
+            .send_input(InputMessage::Maintenance(false))
+            .await;
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_job_complete() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::status_light::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(20), runner, async || {
+        communicator
+            .send_input(InputMessage::Interlock(Interlock::OperationPermitted))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings::default())
+        );
+
+        communicator
+            .send_input(InputMessage::AcBusPower(AcBusPower::On))
+            .await;
+        // Startup sequence
+        for _ in 0..4 {
+            communicator.receive_output().await;
+        }
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::OFF,
+                green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
+            })
+        );
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::BLINK_1HZ,
+                green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
+            })
+        );
+
+        // Green blinks for a while after the job finishes
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::OFF,
+                green: LightPattern::BLINK_2HZ,
+                buzzer: LightPattern::OFF,
+            })
+        );
+        let before = Instant::now();
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::Settings(StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::OFF,
+                green: LightPattern::ON,
+                buzzer: LightPattern::OFF,
+            })
+        );
+        let after = Instant::now();
+        assert_duration!(
+            before,
+            after,
+            Duration::from_secs(10),
+            Duration::from_millis(50)
+        );
+
         assert_queue_empty!(communicator);
     })
     .await;
@@ -0,0 +1,216 @@
+use crate::assert_queue_empty;
+use embassy_sync::channel::Channel;
+use embassy_time::Duration;
+use hoshiguma_api::{TemperatureReading, TemperatureSensor, TemperatureSensorReading};
+use hoshiguma_state_machines::temperature_filter::{
+    InputMessage, OutputMessage, StateMachineCommunicator,
+};
+
+async fn send_reading(
+    communicator: &mut StateMachineCommunicator<'_>,
+    sensor: TemperatureSensor,
+    reading: TemperatureReading,
+) {
+    communicator
+        .send_input(InputMessage::Temperature(TemperatureSensorReading {
+            sensor,
+            reading,
+        }))
+        .await;
+}
+
+fn filtered(sensor: TemperatureSensor, reading: TemperatureReading) -> OutputMessage {
+    OutputMessage::Temperature(TemperatureSensorReading { sensor, reading })
+}
+
+pub(super) async fn test_median() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperature_filter::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        let sensor = TemperatureSensor::CoolantReservoir;
+
+        // The first reading is passed on as is.
+        send_reading(&mut communicator, sensor, Ok(15.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(15.0))
+        );
+
+        // A glitch within the plausible step change is smoothed out by the median.
+        send_reading(&mut communicator, sensor, Ok(15.5)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(15.5))
+        );
+        send_reading(&mut communicator, sensor, Ok(22.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(15.5))
+        );
+        send_reading(&mut communicator, sensor, Ok(15.25)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(15.5))
+        );
+        send_reading(&mut communicator, sensor, Ok(15.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(15.25))
+        );
+
+        // Failed readings are passed on.
+        send_reading(&mut communicator, sensor, Err(())).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Err(()))
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_reset_value() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperature_filter::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        let sensor = TemperatureSensor::OrchastratorPcb;
+
+        // The power on reset value is rejected before any other reading.
+        send_reading(&mut communicator, sensor, Ok(85.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RejectedReadings(sensor, 1)
+        );
+
+        send_reading(&mut communicator, sensor, Ok(25.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(25.0))
+        );
+
+        // And after normal readings.
+        send_reading(&mut communicator, sensor, Ok(85.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RejectedReadings(sensor, 2)
+        );
+
+        // Rejections are counted per sensor.
+        send_reading(&mut communicator, TemperatureSensor::CoolerPcb, Ok(85.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RejectedReadings(TemperatureSensor::CoolerPcb, 1)
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_step_change() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperature_filter::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        let sensor = TemperatureSensor::ExtractionAirflow;
+
+        send_reading(&mut communicator, sensor, Ok(20.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(20.0))
+        );
+
+        // An implausible jump is rejected.
+        send_reading(&mut communicator, sensor, Ok(-40.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RejectedReadings(sensor, 1)
+        );
+
+        // As is a jump that is not continued by the following reading.
+        send_reading(&mut communicator, sensor, Ok(50.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RejectedReadings(sensor, 2)
+        );
+        send_reading(&mut communicator, sensor, Ok(21.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(21.0))
+        );
+
+        // A temperature that stays at the new value is believed from the second reading.
+        send_reading(&mut communicator, sensor, Ok(50.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RejectedReadings(sensor, 3)
+        );
+        send_reading(&mut communicator, sensor, Ok(50.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(50.0))
+        );
+
+        // Subsequent readings are filtered from the new value.
+        send_reading(&mut communicator, sensor, Ok(52.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(50.0))
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_rapid_rise() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperature_filter::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        let sensor = TemperatureSensor::ExtractionAirflow;
+
+        send_reading(&mut communicator, sensor, Ok(20.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(20.0))
+        );
+
+        // Each reading rises by more than the plausible step change, only the first is held back.
+        send_reading(&mut communicator, sensor, Ok(32.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RejectedReadings(sensor, 1)
+        );
+        send_reading(&mut communicator, sensor, Ok(44.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(44.0))
+        );
+        // Once the rise is established it is followed, subject to the median.
+        send_reading(&mut communicator, sensor, Ok(56.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(44.0))
+        );
+        send_reading(&mut communicator, sensor, Ok(68.0)).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            filtered(sensor, Ok(56.0))
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
@@ -1,8 +1,8 @@
 use crate::assert_queue_empty;
 use embassy_sync::channel::Channel;
 use embassy_time::{Duration, Timer};
-use hoshiguma_api::{Severity, TemperatureSensor, TemperatureSensorReading};
-use hoshiguma_state_machines::temperatures::{InputMessage, OutputMessage};
+use hoshiguma_api::{MachineRun, Monitor, Severity, TemperatureSensor, TemperatureSensorReading};
+use hoshiguma_state_machines::temperatures::{InputMessage, OutputMessage, TemperatureThresholds};
 
 pub(super) async fn test_basic() {
     let input_channel = Channel::new();
@@ -30,11 +30,18 @@ pub(super) async fn test_basic() {
         );
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::ElectronicsTemperatureSeverity(Severity::Critical)
+            OutputMessage::TemperatureSeverity(Monitor::ElectronicsTemperature, Severity::Critical)
         );
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::CoolantReservoirTemperatureSeverity(Severity::Critical)
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Critical
+            )
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateOfRiseSeverity(Severity::Normal)
         );
 
         // The second electronics sensor reports normally; both sensors are healthy so
@@ -47,7 +54,7 @@ pub(super) async fn test_basic() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::ElectronicsTemperatureSeverity(Severity::Normal)
+            OutputMessage::TemperatureSeverity(Monitor::ElectronicsTemperature, Severity::Normal)
         );
         assert_queue_empty!(communicator);
 
@@ -60,7 +67,10 @@ pub(super) async fn test_basic() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::CoolantReservoirTemperatureSeverity(Severity::Normal)
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Normal
+            )
         );
         assert_queue_empty!(communicator);
     })
@@ -110,7 +120,7 @@ pub(super) async fn test_electronics_temperature() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::ElectronicsTemperatureSeverity(Severity::Warning)
+            OutputMessage::TemperatureSeverity(Monitor::ElectronicsTemperature, Severity::Warning)
         );
         assert_queue_empty!(communicator);
 
@@ -123,7 +133,7 @@ pub(super) async fn test_electronics_temperature() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::ElectronicsTemperatureSeverity(Severity::Critical)
+            OutputMessage::TemperatureSeverity(Monitor::ElectronicsTemperature, Severity::Critical)
         );
         assert_queue_empty!(communicator);
 
@@ -136,7 +146,7 @@ pub(super) async fn test_electronics_temperature() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::ElectronicsTemperatureSeverity(Severity::Normal)
+            OutputMessage::TemperatureSeverity(Monitor::ElectronicsTemperature, Severity::Normal)
         );
         assert_queue_empty!(communicator);
 
@@ -150,7 +160,7 @@ pub(super) async fn test_electronics_temperature() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::ElectronicsTemperatureSeverity(Severity::Warning)
+            OutputMessage::TemperatureSeverity(Monitor::ElectronicsTemperature, Severity::Warning)
         );
         assert_queue_empty!(communicator);
     })
@@ -176,7 +186,10 @@ pub(super) async fn test_coolant_reservoir_temperature() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::CoolantReservoirTemperatureSeverity(Severity::Warning)
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Warning
+            )
         );
         assert_queue_empty!(communicator);
 
@@ -189,7 +202,10 @@ pub(super) async fn test_coolant_reservoir_temperature() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::CoolantReservoirTemperatureSeverity(Severity::Critical)
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Critical
+            )
         );
         assert_queue_empty!(communicator);
 
@@ -202,7 +218,10 @@ pub(super) async fn test_coolant_reservoir_temperature() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::CoolantReservoirTemperatureSeverity(Severity::Normal)
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Normal
+            )
         );
         assert_queue_empty!(communicator);
     })
@@ -228,7 +247,10 @@ pub(super) async fn test_failed_sensor() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::CoolantReservoirTemperatureSeverity(Severity::Critical)
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Critical
+            )
         );
         assert_queue_empty!(communicator);
 
@@ -241,9 +263,321 @@ pub(super) async fn test_failed_sensor() {
             .await;
         assert_eq!(
             communicator.receive_output().await,
-            OutputMessage::CoolantReservoirTemperatureSeverity(Severity::Normal)
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Normal
+            )
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_low_temperature() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperatures::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        baseline_all_sensors(&mut communicator).await;
+
+        // Temperature falls to warning level (low warn threshold: 10 °C).
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(9.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Warning
+            )
+        );
+        assert_queue_empty!(communicator);
+
+        // Temperature falls to critical level (low critical threshold: 5 °C).
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(3.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::TemperatureSeverity(
+                Monitor::CoolantReservoirTemperature,
+                Severity::Critical
+            )
+        );
+        assert_queue_empty!(communicator);
+
+        // Electronics have their own low thresholds (low warn threshold: 5 °C).
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolerPcb,
+                reading: Ok(4.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::TemperatureSeverity(Monitor::ElectronicsTemperature, Severity::Warning)
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_configured_thresholds() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) = hoshiguma_state_machines::temperatures::new_with_thresholds(
+        &input_channel,
+        &output_channel,
+        &[(
+            TemperatureSensor::ExtractionAirflow,
+            TemperatureThresholds {
+                monitor: Monitor::ExtractionAirflow,
+                low_critical: None,
+                low_warning: None,
+                high_warning: Some(40.0),
+                high_critical: None,
+            },
+        )],
+    );
+
+    crate::run_test(Duration::from_secs(10), runner, async || {
+        // Only the configured sensor is checked.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::ExtractionAirflow,
+                reading: Ok(-10.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::FunctionalSeverity(Severity::Normal)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::TemperatureSeverity(Monitor::ExtractionAirflow, Severity::Normal)
+        );
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateOfRiseSeverity(Severity::Normal)
+        );
+        assert_queue_empty!(communicator);
+
+        // Sensors without thresholds are ignored.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::CoolantReservoir,
+                reading: Ok(50.0),
+            }))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // Omitted thresholds are never reached.
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor: TemperatureSensor::ExtractionAirflow,
+                reading: Ok(90.0),
+            }))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::TemperatureSeverity(Monitor::ExtractionAirflow, Severity::Warning)
         );
         assert_queue_empty!(communicator);
     })
     .await;
 }
+
+/// Sends a ramp of readings from a sensor, at 100ms intervals.
+async fn send_ramp(
+    communicator: &mut hoshiguma_state_machines::temperatures::StateMachineCommunicator<'_>,
+    sensor: TemperatureSensor,
+    start: f32,
+    step: f32,
+    count: usize,
+) {
+    for i in 0..count {
+        communicator
+            .send_input(InputMessage::Temperature(TemperatureSensorReading {
+                sensor,
+                reading: Ok(start + step * i as f32),
+            }))
+            .await;
+        Timer::after_millis(100).await;
+    }
+}
+
+pub(super) async fn test_rate_of_rise() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperatures::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(20), runner, async || {
+        baseline_all_sensors(&mut communicator).await;
+
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_queue_empty!(communicator);
+
+        // A slow rise in the extraction airflow temperature is normal
+        send_ramp(
+            &mut communicator,
+            TemperatureSensor::ExtractionAirflow,
+            20.0,
+            0.05,
+            50,
+        )
+        .await;
+        assert_queue_empty!(communicator);
+
+        // As is a single bad reading
+        send_ramp(
+            &mut communicator,
+            TemperatureSensor::ExtractionAirflow,
+            85.0,
+            0.0,
+            1,
+        )
+        .await;
+        send_ramp(
+            &mut communicator,
+            TemperatureSensor::ExtractionAirflow,
+            22.5,
+            0.0,
+            5,
+        )
+        .await;
+        assert_queue_empty!(communicator);
+
+        // A rapid rise while running is a fire
+        send_ramp(
+            &mut communicator,
+            TemperatureSensor::ExtractionAirflow,
+            22.5,
+            1.0,
+            10,
+        )
+        .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateOfRiseSeverity(Severity::Fatal)
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_rate_of_rise_idle() {
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperatures::new(&input_channel, &output_channel);
+
+    crate::run_test(Duration::from_secs(20), runner, async || {
+        baseline_all_sensors(&mut communicator).await;
+
+        // A rapid rise on a cutting area sensor while idle is a warning
+        let sensor = TemperatureSensor::RearSensorBoardOnewire(42);
+        send_ramp(&mut communicator, sensor, 20.0, 0.0, 5).await;
+        assert_queue_empty!(communicator);
+
+        send_ramp(&mut communicator, sensor, 20.0, 2.0, 5).await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateOfRiseSeverity(Severity::Warning)
+        );
+        assert_queue_empty!(communicator);
+
+        // And becomes fatal if a job is started
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Running))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateOfRiseSeverity(Severity::Fatal)
+        );
+
+        // And back to a warning once the job has stopped
+        communicator
+            .send_input(InputMessage::MachineRun(MachineRun::Idle))
+            .await;
+        assert_eq!(
+            communicator.receive_output().await,
+            OutputMessage::RateOfRiseSeverity(Severity::Warning)
+        );
+        assert_queue_empty!(communicator);
+    })
+    .await;
+}
+
+pub(super) async fn test_rate_of_rise_through_filter() {
+    use hoshiguma_state_machines::{StateMachineRun, temperature_filter};
+
+    let input_channel = Channel::new();
+    let output_channel = Channel::new();
+
+    let (runner, mut communicator) =
+        hoshiguma_state_machines::temperatures::new(&input_channel, &output_channel);
+
+    let filter_input_channel = Channel::new();
+    let filter_output_channel = Channel::new();
+
+    let (mut filter_runner, mut filter_communicator) =
+        temperature_filter::new(&filter_input_channel, &filter_output_channel);
+
+    crate::run_test(Duration::from_secs(20), runner, async || {
+        let test = async {
+            baseline_all_sensors(&mut communicator).await;
+
+            communicator
+                .send_input(InputMessage::MachineRun(MachineRun::Running))
+                .await;
+            assert_queue_empty!(communicator);
+
+            // A rise faster than the filter's plausible step change still reaches the fire check
+            let sensor = TemperatureSensor::ExtractionAirflow;
+            for temperature in [20.0, 20.0, 20.0, 32.0, 44.0, 56.0, 68.0] {
+                filter_communicator
+                    .send_input(temperature_filter::InputMessage::Temperature(
+                        TemperatureSensorReading {
+                            sensor,
+                            reading: Ok(temperature),
+                        },
+                    ))
+                    .await;
+
+                if let temperature_filter::OutputMessage::Temperature(reading) =
+                    filter_communicator.receive_output().await
+                {
+                    communicator
+                        .send_input(InputMessage::Temperature(reading))
+                        .await;
+                }
+
+                Timer::after_millis(100).await;
+            }
+
+            assert_eq!(
+                communicator.receive_output().await,
+                OutputMessage::RateOfRiseSeverity(Severity::Fatal)
+            );
+            assert_queue_empty!(communicator);
+        };
+
+        embassy_futures::select::select(filter_runner.run(), test).await;
+    })
+    .await;
+}
@@ -1,43 +1,89 @@
 use defmt::{Format, debug, info};
-use embassy_futures::select::{Either, select};
+use embassy_futures::select::{Either3, select3};
 use embassy_time::{Duration, Instant};
-use hoshiguma_api::{AcBusPower, AirAssistDemand, AirAssistPump};
+use hoshiguma_api::{AcBusPower, AirAssistDemand, AirAssistPressure, AirAssistPump, Severity};
 use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 4);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     AcBusPower(AcBusPower),
     AirAssistDemand(AirAssistDemand),
+    AirAssistPressure(AirAssistPressure),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum OutputMessage {
     AirAssistPump(AirAssistPump),
+    PressureSeverity(Severity),
 }
 
 pub struct State {
+    config: Config,
+
     machine_power: AcBusPower,
     state: RunPhase,
 
+    pressure: AirAssistPressure,
+    pump_change_time: Instant,
+    pressure_check_time: Option<Instant>,
+
     output_pump: ObservedValue<AirAssistPump>,
+    output_pressure_severity: ObservedValue<Severity>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+
             machine_power: AcBusPower::Off,
             state: RunPhase::Idle,
 
+            pressure: AirAssistPressure::Low,
+            pump_change_time: Instant::now(),
+            pressure_check_time: None,
+
             output_pump: ObservedValue::default(),
+            output_pressure_severity: ObservedValue::default(),
+        }
+    }
+}
+
+impl State {
+    fn pressure_severity(&self) -> Severity {
+        let pump = self.output_pump.unwrap_or(AirAssistPump::Idle);
+        let since_pump_change = Instant::now() - self.pump_change_time;
+
+        match (pump, self.pressure) {
+            // A failed pump or blocked line means no air at the nozzle, which risks a fire
+            (AirAssistPump::Run, AirAssistPressure::Low)
+                if since_pump_change >= self.config.pressure_runup_time =>
+            {
+                Severity::Critical
+            }
+            // Pressure without the pump running suggests a stuck pressure switch, in which case a
+            // loss of pressure would not be detected
+            (AirAssistPump::Idle, AirAssistPressure::Normal)
+                if since_pump_change >= self.config.pressure_decay_time =>
+            {
+                Severity::Warning
+            }
+            _ => Severity::Normal,
         }
     }
 }
 
-#[derive(Format, Clone)]
-enum RunPhase {
+#[derive(Debug, Format, Clone, Serialize, Deserialize)]
+pub enum RunPhase {
     Idle,
-    RunOn { until: Instant },
+    RunOn {
+        #[serde(with = "crate::serde_instant")]
+        until: Instant,
+    },
     Demand,
 }
 
@@ -51,7 +97,63 @@ impl From<&RunPhase> for AirAssistPump {
     }
 }
 
-const TIMEOUT: Duration = Duration::from_secs(1);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Time the pump runs on for after demand ends.
+    #[serde(with = "crate::serde_duration")]
+    pub timeout: Duration,
+
+    /// Time allowed for the air assist pressure to build after the pump is started.
+    #[serde(with = "crate::serde_duration")]
+    pub pressure_runup_time: Duration,
+
+    /// Time allowed for the air assist pressure to decay after the pump is stopped.
+    #[serde(with = "crate::serde_duration")]
+    pub pressure_decay_time: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            pressure_runup_time: Duration::from_secs(2),
+            pressure_decay_time: Duration::from_secs(5),
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub machine_power: AcBusPower,
+    pub phase: RunPhase,
+    pub pressure: AirAssistPressure,
+    #[serde(with = "crate::serde_instant")]
+    pub pump_change_time: Instant,
+    #[serde(with = "crate::serde_instant::option")]
+    pub pressure_check_time: Option<Instant>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            machine_power: self.machine_power,
+            phase: self.state.clone(),
+            pressure: self.pressure,
+            pump_change_time: self.pump_change_time,
+            pressure_check_time: self.pressure_check_time,
+        }
+    }
+}
 
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
@@ -63,26 +165,38 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                     None
                 });
 
-            match select(self.input_channel.receive(), run_on_timer).await {
-                Either::First(InputMessage::AcBusPower(power)) => {
+            let pressure_check_timer = MaybeTimer::at(self.state.pressure_check_time);
+
+            match select3(self.receive_input(), run_on_timer, pressure_check_timer).await {
+                Either3::First(InputMessage::AcBusPower(power)) => {
                     self.state.machine_power = power;
                 }
-                Either::First(InputMessage::AirAssistDemand(demand)) => {
+                Either3::First(InputMessage::AirAssistDemand(demand)) => {
                     self.state.state = match demand {
                         AirAssistDemand::Idle => match self.state.state {
                             RunPhase::Idle => RunPhase::Idle,
                             RunPhase::RunOn { until } => RunPhase::RunOn { until },
                             RunPhase::Demand => RunPhase::RunOn {
-                                until: Instant::now() + TIMEOUT,
+                                until: Instant::now() + self.state.config.timeout,
                             },
                         },
                         AirAssistDemand::Demand => RunPhase::Demand,
                     };
                 }
-                Either::Second(()) => {
+                Either3::First(InputMessage::AirAssistPressure(pressure)) => {
+                    self.state.pressure = pressure;
+                }
+                Either3::First(InputMessage::UpdateConfig(config)) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+                Either3::Second(()) => {
                     debug!("Run on timer expired");
                     self.state.state = RunPhase::Idle;
                 }
+                Either3::Third(()) => {
+                    debug!("Pressure check timer expired");
+                    self.state.pressure_check_time = None;
+                }
             }
 
             info!("Air assist state {}", self.state.state);
@@ -93,14 +207,38 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                 AcBusPower::On => (&self.state.state).into(),
             };
 
+            let mut pump_changed = false;
             self.state
                 .output_pump
                 .update_and_async(output, async |v| {
+                    pump_changed = true;
                     self.output_channel
                         .send(OutputMessage::AirAssistPump(v))
                         .await;
                 })
                 .await;
+
+            // Reassess the pressure once it has had time to settle after the pump changes state
+            if pump_changed {
+                let now = Instant::now();
+                self.state.pump_change_time = now;
+                self.state.pressure_check_time = Some(
+                    now + match output {
+                        AirAssistPump::Idle => self.state.config.pressure_decay_time,
+                        AirAssistPump::Run => self.state.config.pressure_runup_time,
+                    },
+                );
+            }
+
+            let severity = self.state.pressure_severity();
+            self.state
+                .output_pressure_severity
+                .update_and_async(severity, async |v| {
+                    self.output_channel
+                        .send(OutputMessage::PressureSeverity(v))
+                        .await;
+                })
+                .await;
         }
     }
 }
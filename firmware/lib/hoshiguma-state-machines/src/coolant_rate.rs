@@ -1,47 +1,144 @@
-use defmt::{debug, info};
+use defmt::{Format, debug, info, warn};
 use embassy_time::{Duration, Instant};
+use heapless::Deque;
 use hoshiguma_api::{
     Severity,
-    cooler::{CoolantPumpState, CoolantRate},
+    cooler::{CoolantPumpState, CoolantRate, CoolantVolume},
 };
 use hoshiguma_common::changed::ObservedValue;
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 4);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     CoolantPumpState(CoolantPumpState),
     RateFlow(CoolantRate),
     RateReturn(CoolantRate),
+    /// Total volume of coolant that has passed the flow sensor.
+    VolumeFlow(CoolantVolume),
+    /// Total volume of coolant that has passed the return sensor.
+    VolumeReturn(CoolantVolume),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum OutputMessage {
     RateSeverity(Severity),
     SymmetrySeverity(Severity),
+    LeakSeverity(Severity),
+    /// Estimated volume of coolant lost over the leak detection window.
+    LeakedVolume(CoolantVolume),
 }
 
 pub struct State {
+    config: Config,
+
     pump_state: CoolantPumpState,
     pump_state_change: Instant,
 
     flow: Option<CoolantRate>,
     ret: Option<CoolantRate>,
 
+    volume_flow: Option<CoolantVolume>,
+    volume_samples: Deque<VolumeSample, VOLUME_SAMPLE_CAPACITY>,
+
     output_rate_severity: ObservedValue<Severity>,
     output_symmetry_severity: ObservedValue<Severity>,
+    output_leak_severity: ObservedValue<Severity>,
+}
+
+#[derive(Debug, Format, Clone, Copy)]
+struct VolumeSample {
+    time: Instant,
+    flow: CoolantVolume,
+    ret: CoolantVolume,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+
             pump_state: CoolantPumpState::Idle,
             pump_state_change: Instant::now(),
 
             flow: None,
             ret: None,
 
+            volume_flow: None,
+            volume_samples: Deque::new(),
+
             output_rate_severity: ObservedValue::default(),
             output_symmetry_severity: ObservedValue::default(),
+            output_leak_severity: ObservedValue::default(),
+        }
+    }
+}
+
+impl State {
+    fn pump_running_steadily(&self, now: Instant) -> bool {
+        self.pump_state == CoolantPumpState::Run
+            && (now - self.pump_state_change) >= self.config.pump_run_up_time
+    }
+
+    /// Records a sample of the total flow and return volumes, discarding samples that have left the
+    /// leak detection window.
+    ///
+    /// Returns true if a sample was recorded.
+    fn record_volume_sample(&mut self, sample: VolumeSample) -> bool {
+        if let Some(last) = self.volume_samples.back() {
+            if sample.flow < last.flow || sample.ret < last.ret {
+                // Totals went backwards, the cooler has restarted
+                debug!("Coolant volume totals reset");
+                self.volume_samples.clear();
+            } else if sample.time - last.time < self.config.volume_sample_interval {
+                return false;
+            }
+        }
+
+        while let Some(first) = self.volume_samples.front()
+            && (sample.time - first.time > self.config.leak_window || self.volume_samples.is_full())
+        {
+            self.volume_samples.pop_front();
+        }
+
+        self.volume_samples.push_back(sample).unwrap();
+        true
+    }
+
+    /// Volume of coolant that left via the flow sensor but did not come back via the return sensor
+    /// over the samples in the leak detection window.
+    fn leaked_volume(&self) -> CoolantVolume {
+        match (self.volume_samples.front(), self.volume_samples.back()) {
+            (Some(first), Some(last)) => (last.flow - first.flow) - (last.ret - first.ret),
+            _ => CoolantVolume::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub pump_state: CoolantPumpState,
+    #[serde(with = "crate::serde_instant")]
+    pub pump_state_change: Instant,
+    pub flow: Option<CoolantRate>,
+    pub ret: Option<CoolantRate>,
+    pub volume_samples: usize,
+    pub leaked_volume: CoolantVolume,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pump_state: self.pump_state,
+            pump_state_change: self.pump_state_change,
+            flow: self.flow,
+            ret: self.ret,
+            volume_samples: self.volume_samples.len(),
+            leaked_volume: self.leaked_volume(),
         }
     }
 }
@@ -49,10 +146,13 @@ impl Default for State {
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
-            match self.input_channel.receive().await {
+            match self.receive_input().await {
                 InputMessage::CoolantPumpState(state) => {
                     self.state.pump_state = state;
                     self.state.pump_state_change = Instant::now();
+
+                    // Only integrate volume while the pump is running steadily
+                    self.state.volume_samples.clear();
                 }
                 InputMessage::RateFlow(rate) => {
                     self.state.flow = Some(rate);
@@ -60,10 +160,34 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                 InputMessage::RateReturn(rate) => {
                     self.state.ret = Some(rate);
                 }
+                InputMessage::VolumeFlow(volume) => {
+                    self.state.volume_flow = Some(volume);
+                }
+                InputMessage::UpdateConfig(config) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+                InputMessage::VolumeReturn(volume) => {
+                    let now = Instant::now();
+
+                    if let Some(flow) = self.state.volume_flow
+                        && self.state.pump_running_steadily(now)
+                        && self.state.record_volume_sample(VolumeSample {
+                            time: now,
+                            flow,
+                            ret: volume,
+                        })
+                    {
+                        let leaked = self.state.leaked_volume();
+                        debug!("leaked volume {}", leaked);
+                        self.output_channel
+                            .send(OutputMessage::LeakedVolume(leaked))
+                            .await;
+                    }
+                }
             }
 
             let severity = match self.state.flow {
-                Some(rate) => flow_rate_to_severity(rate),
+                Some(rate) => flow_rate_to_severity(&self.state.config, rate),
                 None => Severity::Critical,
             };
             info!("flow rate {} = severity {}", self.state.flow, severity);
@@ -79,11 +203,11 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
             let severity = if let (Some(flow), Some(ret)) = (self.state.flow, self.state.ret) {
                 let difference = flow - ret;
                 debug!("difference {}", difference);
-                let severity = rate_symmetry_to_severity(difference);
+                let severity = rate_symmetry_to_severity(&self.state.config, difference);
 
                 if severity > Severity::Information
                     && self.state.pump_state == CoolantPumpState::Run
-                    && (Instant::now() - self.state.pump_state_change) < PUMP_RUN_UP_TIME
+                    && !self.state.pump_running_steadily(Instant::now())
                 {
                     // Limit severity during the pump run up period
                     Severity::Information
@@ -105,37 +229,138 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                         .await;
                 })
                 .await;
+
+            let severity =
+                leaked_volume_to_severity(&self.state.config, self.state.leaked_volume());
+            info!("leak severity {}", severity);
+            self.state
+                .output_leak_severity
+                .update_and_async(severity, async |v| {
+                    self.output_channel
+                        .send(OutputMessage::LeakSeverity(v))
+                        .await;
+                })
+                .await;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Time taken for the coolant flow to stabalise after the coolant pump has been started.
+    /// Taking into account flow and return rate sampling interval.
+    #[serde(with = "crate::serde_duration")]
+    pub pump_run_up_time: Duration,
+
+    pub rate_warn: CoolantRate,
+    pub rate_critical: CoolantRate,
+
+    pub symmetry_information: CoolantRate,
+    pub symmetry_warn: CoolantRate,
+    pub symmetry_fatal: CoolantRate,
+
+    /// More coolant returning than flowing cannot be caused by a leak, it indicates a sensor or
+    /// calibration fault so is not treated as seriously.
+    pub symmetry_reverse_information: CoolantRate,
+    pub symmetry_reverse_warn: CoolantRate,
+
+    /// Period over which the difference between flow and return volume is integrated.
+    #[serde(with = "crate::serde_duration")]
+    pub leak_window: Duration,
+
+    /// Minimum time between recorded volume samples.
+    #[serde(with = "crate::serde_duration")]
+    pub volume_sample_interval: Duration,
+
+    /// Leaked volume is at most a warning, a small calibration offset between the flow and return
+    /// sensors accumulates over the window just as a slow leak does.
+    /// Leaks fast enough to be dangerous are caught by the rate symmetry severity.
+    pub leak_information: CoolantVolume,
+    pub leak_warn: CoolantVolume,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            pump_run_up_time: Duration::from_secs(5),
+
+            rate_warn: CoolantRate::new(4.5),
+            rate_critical: CoolantRate::new(2.0),
+
+            symmetry_information: CoolantRate::new(0.1),
+            symmetry_warn: CoolantRate::new(0.25),
+            symmetry_fatal: CoolantRate::new(0.5),
+
+            symmetry_reverse_information: CoolantRate::new(-0.25),
+            symmetry_reverse_warn: CoolantRate::new(-0.5),
+
+            leak_window: Duration::from_secs(300),
+            volume_sample_interval: Duration::from_secs(5),
+
+            leak_information: CoolantVolume::new(0.25),
+            leak_warn: CoolantVolume::new(0.5),
         }
     }
 }
 
-/// Time taken for the coolant flow to stabalise after the coolant pump has been started.
-/// Taking into account flow and return rate sampling interval.
-const PUMP_RUN_UP_TIME: Duration = Duration::from_secs(5);
+impl crate::ConfigurableState for State {
+    type Config = Config;
 
-fn flow_rate_to_severity(rate: CoolantRate) -> Severity {
-    const WARN: CoolantRate = CoolantRate::new(4.5);
-    const CRITICAL: CoolantRate = CoolantRate::new(2.0);
+    fn configure(&mut self, mut config: Config) {
+        // Samples held are at least the interval apart, the interval is lengthened if that would
+        // not be enough to span the leak window
+        let min_interval = Duration::from_ticks(
+            config
+                .leak_window
+                .as_ticks()
+                .div_ceil(VOLUME_SAMPLE_CAPACITY as u64 - 1),
+        );
+        if config.volume_sample_interval < min_interval {
+            warn!(
+                "Volume sample interval is too short to cover the leak window, using {} ms",
+                min_interval.as_millis()
+            );
+            config.volume_sample_interval = min_interval;
+        }
 
-    if rate < CRITICAL {
+        self.config = config;
+    }
+}
+
+fn flow_rate_to_severity(config: &Config, rate: CoolantRate) -> Severity {
+    if rate < config.rate_critical {
         Severity::Critical
-    } else if rate < WARN {
+    } else if rate < config.rate_warn {
         Severity::Warning
     } else {
         Severity::Normal
     }
 }
 
-fn rate_symmetry_to_severity(difference: CoolantRate) -> Severity {
-    const INFORMATION: CoolantRate = CoolantRate::new(0.1);
-    const WARN: CoolantRate = CoolantRate::new(0.25);
-    const FATAL: CoolantRate = CoolantRate::new(0.5);
-
-    if difference > FATAL {
+fn rate_symmetry_to_severity(config: &Config, difference: CoolantRate) -> Severity {
+    if difference > config.symmetry_fatal {
         Severity::Fatal
-    } else if difference > WARN {
+    } else if difference > config.symmetry_warn {
+        Severity::Warning
+    } else if difference > config.symmetry_information {
+        Severity::Information
+    } else if difference < config.symmetry_reverse_warn {
+        Severity::Warning
+    } else if difference < config.symmetry_reverse_information {
+        Severity::Information
+    } else {
+        Severity::Normal
+    }
+}
+
+/// Enough samples to cover the default leak detection window, a configured volume sample interval
+/// is lengthened if necessary so that this still covers the window.
+const VOLUME_SAMPLE_CAPACITY: usize = 64;
+
+fn leaked_volume_to_severity(config: &Config, leaked: CoolantVolume) -> Severity {
+    if leaked > config.leak_warn {
         Severity::Warning
-    } else if difference > INFORMATION {
+    } else if leaked > config.leak_information {
         Severity::Information
     } else {
         Severity::Normal
@@ -1,15 +1,24 @@
-use defmt::debug;
+use defmt::{Format, debug, info};
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant};
 use hoshiguma_api::{
-    AcBusPower, TemperatureReading, TemperatureSensor, TemperatureSensorReading,
-    cooler::{CompressorState, CoolantPumpState, RadiatorFanState},
+    AcBusPower, EmergencyStop, MachineRun, Severity, TemperatureReading, TemperatureSensor,
+    TemperatureSensorReading,
+    cooler::{CompressorState, CoolantPumpState, CoolantRate, RadiatorFanState},
 };
-use hoshiguma_common::changed::ObservedValue;
+use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 4);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     AcBusPower(AcBusPower),
+    EmergencyStop(EmergencyStop),
+    MachineRun(MachineRun),
     Temperature(TemperatureSensorReading),
+    CoolantFlowRate(CoolantRate),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
@@ -17,81 +26,426 @@ pub enum OutputMessage {
     CoolantPump(CoolantPumpState),
     RadiatorFan(RadiatorFanState),
     Compressor(CompressorState),
+    CompressorLockoutSeverity(Severity),
 }
 
 pub struct State {
+    config: Config,
+
     ac_bus_power: AcBusPower,
+    emergency_stop: EmergencyStop,
+    machine_run: MachineRun,
     reservoir_temperature: TemperatureReading,
+    coolant_flow_rate: Option<CoolantRate>,
+    phase: RunPhase,
+
+    /// Time at which the AC bus was last powered on, if it is currently on.
+    powered_since: Option<Instant>,
+    /// Compressor state requested by the temperature control band.
+    compressor_demand: CompressorState,
+    /// Time at which the compressor last changed state, if it has since boot.
+    compressor_changed_at: Option<Instant>,
+    /// Time at which a compressor state change that is currently being held back is permitted.
+    compressor_hold_until: Option<Instant>,
+    /// Is the compressor prevented from running due to the risk of freezing the evaporator?
+    compressor_lockout: bool,
+    /// Is the compressor locked out due to low reservoir temperature?
+    /// Kept separately as this determines which freeze protection threshold applies.
+    low_temperature_lockout: bool,
 
     output_coolant_pump: ObservedValue<CoolantPumpState>,
     output_radiator_fan: ObservedValue<RadiatorFanState>,
     output_compressor: ObservedValue<CompressorState>,
+    output_compressor_lockout_severity: ObservedValue<Severity>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+
             ac_bus_power: AcBusPower::Off,
+            emergency_stop: EmergencyStop::Released,
+            machine_run: MachineRun::Idle,
             reservoir_temperature: Err(()),
+            coolant_flow_rate: None,
+            phase: RunPhase::Idle,
+
+            powered_since: None,
+            compressor_demand: CompressorState::Idle,
+            compressor_changed_at: None,
+            compressor_hold_until: None,
+            compressor_lockout: false,
+            low_temperature_lockout: false,
 
             output_coolant_pump: ObservedValue::default(),
             output_radiator_fan: ObservedValue::default(),
             output_compressor: ObservedValue::default(),
+            output_compressor_lockout_severity: ObservedValue::default(),
+        }
+    }
+}
+
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RunPhase {
+    Idle,
+    RunOn {
+        #[serde(with = "crate::serde_instant")]
+        until: Instant,
+    },
+    Demand,
+}
+
+impl State {
+    /// Determines if the compressor should be locked out to protect the evaporator from freezing.
+    ///
+    /// Coolant flow is only considered once it has had time to establish after power on.
+    fn update_compressor_lockout(&mut self, now: Instant) {
+        let flow_settled = self
+            .powered_since
+            .is_some_and(|t| now >= t + self.config.coolant_flow_settle_time);
+
+        let low_flow = flow_settled
+            && self
+                .coolant_flow_rate
+                .is_none_or(|rate| rate < self.config.compressor_lockout_coolant_rate);
+
+        // The release threshold only applies once locked out by low temperature, a lockout due to low
+        // flow must not move the point at which the compressor is locked out for low temperature
+        self.low_temperature_lockout = match self.reservoir_temperature {
+            Ok(temperature) if self.low_temperature_lockout => {
+                temperature < self.config.freeze_protection_release_temperature
+            }
+            Ok(temperature) => temperature < self.config.freeze_protection_temperature,
+            Err(_) => false,
+        };
+
+        self.compressor_lockout = low_flow || self.low_temperature_lockout;
+    }
+
+    /// Applies the compressor anti-short-cycle limits to the demanded compressor state.
+    ///
+    /// If the demanded state is not yet permitted then the current state is retained and the time
+    /// at which the change will be permitted is recorded.
+    fn limit_compressor(&mut self, demand: CompressorState, now: Instant) -> CompressorState {
+        let current = (*self.output_compressor).unwrap_or(CompressorState::Idle);
+
+        let permitted_at = match (current, demand) {
+            (CompressorState::Idle, CompressorState::Run) => {
+                let start_delay_end = self
+                    .powered_since
+                    .map(|t| t + self.config.compressor_start_delay);
+                let min_off_end = self
+                    .compressor_changed_at
+                    .map(|t| t + self.config.compressor_minimum_off_time);
+                start_delay_end.max(min_off_end)
+            }
+            (CompressorState::Run, CompressorState::Idle) => self
+                .compressor_changed_at
+                .map(|t| t + self.config.compressor_minimum_run_time),
+            _ => None,
+        };
+
+        match permitted_at {
+            Some(permitted_at) if now < permitted_at => {
+                self.compressor_hold_until = Some(permitted_at);
+                current
+            }
+            _ => {
+                self.compressor_hold_until = None;
+                if demand != current {
+                    self.compressor_changed_at = Some(now);
+                }
+                demand
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Reservoir temperature above which the compressor is demanded.
+    pub upper_temperature: f32,
+    /// Reservoir temperature below which the compressor is no longer demanded.
+    pub lower_temperature: f32,
+
+    /// Delay between the AC bus being powered on and the compressor being permitted to start.
+    #[serde(with = "crate::serde_duration")]
+    pub compressor_start_delay: Duration,
+
+    /// Minimum time the compressor is run for once started.
+    #[serde(with = "crate::serde_duration")]
+    pub compressor_minimum_run_time: Duration,
+
+    /// Minimum time the compressor is stopped for before being restarted, allowing the refrigerant
+    /// pressures to equalise.
+    #[serde(with = "crate::serde_duration")]
+    pub compressor_minimum_off_time: Duration,
+
+    /// Reservoir temperature below which the compressor is locked out.
+    pub freeze_protection_temperature: f32,
+
+    /// Reservoir temperature above which a compressor lockout due to low temperature is released.
+    pub freeze_protection_release_temperature: f32,
+
+    /// Coolant rate below which the compressor is locked out.
+    /// Matches the critical threshold of the coolant rate monitor.
+    pub compressor_lockout_coolant_rate: CoolantRate,
+
+    /// Time allowed for coolant flow to establish after the coolant pump is started, before low flow
+    /// locks out the compressor.
+    /// This is shorter than the compressor start delay, so the compressor never starts without flow.
+    #[serde(with = "crate::serde_duration")]
+    pub coolant_flow_settle_time: Duration,
+
+    /// Maximum time the coolant pump and radiator fan continue to run for after the AC bus is powered off.
+    #[serde(with = "crate::serde_duration")]
+    pub run_on_duration: Duration,
+
+    /// Reservoir temperature below which the coolant pump and radiator fan run on is ended early.
+    pub run_on_end_temperature: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        const LOWER_TEMPERATURE: f32 = 17.0;
+
+        Self {
+            upper_temperature: 17.5,
+            lower_temperature: LOWER_TEMPERATURE,
+            compressor_start_delay: Duration::from_secs(10),
+            compressor_minimum_run_time: Duration::from_secs(60),
+            compressor_minimum_off_time: Duration::from_secs(180),
+            freeze_protection_temperature: 4.0,
+            freeze_protection_release_temperature: 6.0,
+            compressor_lockout_coolant_rate: CoolantRate::new(2.0),
+            coolant_flow_settle_time: Duration::from_secs(8),
+            run_on_duration: Duration::from_secs(60),
+            run_on_end_temperature: LOWER_TEMPERATURE,
         }
     }
 }
 
-const UPPER_TEMPERATURE: f32 = 17.5;
-const LOWER_TEMPERATURE: f32 = 17.0;
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub config: Config,
+    pub ac_bus_power: AcBusPower,
+    pub emergency_stop: EmergencyStop,
+    pub machine_run: MachineRun,
+    pub reservoir_temperature: TemperatureReading,
+    pub coolant_flow_rate: Option<CoolantRate>,
+    pub phase: RunPhase,
+    #[serde(with = "crate::serde_instant::option")]
+    pub powered_since: Option<Instant>,
+    pub compressor_demand: CompressorState,
+    #[serde(with = "crate::serde_instant::option")]
+    pub compressor_changed_at: Option<Instant>,
+    #[serde(with = "crate::serde_instant::option")]
+    pub compressor_hold_until: Option<Instant>,
+    pub compressor_lockout: bool,
+    pub low_temperature_lockout: bool,
+    pub output_coolant_pump: Option<CoolantPumpState>,
+    pub output_radiator_fan: Option<RadiatorFanState>,
+    pub output_compressor: Option<CompressorState>,
+    pub output_compressor_lockout_severity: Option<Severity>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            config: self.config.clone(),
+            ac_bus_power: self.ac_bus_power,
+            emergency_stop: self.emergency_stop,
+            machine_run: self.machine_run,
+            reservoir_temperature: self.reservoir_temperature,
+            coolant_flow_rate: self.coolant_flow_rate,
+            phase: self.phase.clone(),
+            powered_since: self.powered_since,
+            compressor_demand: self.compressor_demand,
+            compressor_changed_at: self.compressor_changed_at,
+            compressor_hold_until: self.compressor_hold_until,
+            compressor_lockout: self.compressor_lockout,
+            low_temperature_lockout: self.low_temperature_lockout,
+            output_coolant_pump: *self.output_coolant_pump,
+            output_radiator_fan: *self.output_radiator_fan,
+            output_compressor: *self.output_compressor,
+            output_compressor_lockout_severity: *self.output_compressor_lockout_severity,
+        }
+    }
+}
+
+impl crate::RestoreSnapshot for State {
+    fn restore(snapshot: Snapshot) -> Self {
+        Self {
+            config: snapshot.config,
+            ac_bus_power: snapshot.ac_bus_power,
+            emergency_stop: snapshot.emergency_stop,
+            machine_run: snapshot.machine_run,
+            reservoir_temperature: snapshot.reservoir_temperature,
+            coolant_flow_rate: snapshot.coolant_flow_rate,
+            phase: snapshot.phase,
+            powered_since: snapshot.powered_since,
+            compressor_demand: snapshot.compressor_demand,
+            compressor_changed_at: snapshot.compressor_changed_at,
+            compressor_hold_until: snapshot.compressor_hold_until,
+            compressor_lockout: snapshot.compressor_lockout,
+            low_temperature_lockout: snapshot.low_temperature_lockout,
+            output_coolant_pump: snapshot.output_coolant_pump.into(),
+            output_radiator_fan: snapshot.output_radiator_fan.into(),
+            output_compressor: snapshot.output_compressor.into(),
+            output_compressor_lockout_severity: snapshot.output_compressor_lockout_severity.into(),
+        }
+    }
+}
 
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
-            match self.input_channel.receive().await {
-                InputMessage::AcBusPower(state) => {
+            let run_on_timer = if let RunPhase::RunOn { until } = self.state.phase {
+                Some(until)
+            } else {
+                None
+            };
+
+            match select(
+                self.receive_input(),
+                MaybeTimer::at(self.state.compressor_hold_until.or(run_on_timer)),
+            )
+            .await
+            {
+                Either::First(InputMessage::AcBusPower(state)) => {
                     self.state.ac_bus_power = state;
                 }
-                InputMessage::Temperature(reading) => {
+                Either::First(InputMessage::EmergencyStop(state)) => {
+                    self.state.emergency_stop = state;
+                }
+                Either::First(InputMessage::MachineRun(state)) => {
+                    self.state.machine_run = state;
+                }
+                Either::First(InputMessage::Temperature(reading)) => {
                     if reading.sensor == TemperatureSensor::CoolantReservoir {
                         self.state.reservoir_temperature = reading.reading;
                     }
                 }
+                Either::First(InputMessage::CoolantFlowRate(rate)) => {
+                    self.state.coolant_flow_rate = Some(rate);
+                }
+                Either::First(InputMessage::UpdateConfig(config)) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+                Either::Second(()) => {
+                    debug!("Cooling timer expired");
+                }
             }
 
-            let (pump, fan, compressor) = if self.state.ac_bus_power == AcBusPower::On {
-                let compressor = if let Ok(temperature) = self.state.reservoir_temperature {
-                    if temperature > UPPER_TEMPERATURE {
-                        Some(CompressorState::Run)
-                    } else if temperature < LOWER_TEMPERATURE {
-                        Some(CompressorState::Idle)
-                    } else {
-                        None
+            let now = Instant::now();
+
+            let reservoir_cold = self
+                .state
+                .reservoir_temperature
+                .is_ok_and(|t| t < self.state.config.run_on_end_temperature);
+
+            let phase = if self.state.ac_bus_power == AcBusPower::On {
+                RunPhase::Demand
+            } else if self.state.emergency_stop == EmergencyStop::Pressed {
+                // Technically the cooler can run if the machine AC bus is off, however the E-stop should stop everything
+                RunPhase::Idle
+            } else {
+                match self.state.phase {
+                    RunPhase::Idle => RunPhase::Idle,
+                    RunPhase::RunOn { until } => {
+                        if now >= until || reservoir_cold {
+                            RunPhase::Idle
+                        } else {
+                            RunPhase::RunOn { until }
+                        }
+                    }
+                    RunPhase::Demand => {
+                        if reservoir_cold {
+                            RunPhase::Idle
+                        } else {
+                            RunPhase::RunOn {
+                                until: now + self.state.config.run_on_duration,
+                            }
+                        }
                     }
+                }
+            };
+
+            if phase != self.state.phase {
+                info!("Cooling phase {}", phase);
+                self.state.phase = phase;
+            }
+
+            let (pump, fan, compressor) = if let RunPhase::Demand = self.state.phase {
+                if self.state.powered_since.is_none() {
+                    self.state.powered_since = Some(now);
+                }
+
+                // Keep the old demand if in the hysteresis band or if the temperature is unavailable, otherwise update to the new demand.
+                if let Ok(temperature) = self.state.reservoir_temperature {
+                    if temperature > self.state.config.upper_temperature {
+                        self.state.compressor_demand = CompressorState::Run;
+                    } else if temperature < self.state.config.lower_temperature {
+                        self.state.compressor_demand = CompressorState::Idle;
+                    }
+                }
+
+                self.state.update_compressor_lockout(now);
+
+                let compressor = if self.state.compressor_lockout {
+                    // Stopping the compressor due to a lockout is not subject to the minimum run time
+                    if *self.state.output_compressor == Some(CompressorState::Run) {
+                        self.state.compressor_changed_at = Some(now);
+                    }
+                    self.state.compressor_hold_until = None;
+                    CompressorState::Idle
                 } else {
-                    None
+                    self.state
+                        .limit_compressor(self.state.compressor_demand, now)
                 };
 
-                // Keep the old setting if in the hysteresis band or if the temperature is unavailable, otherwise update to the new demand.
-                let compressor = compressor.unwrap_or_else(|| {
-                    // If there is no previous setting then default to off.
-                    (*self.state.output_compressor).unwrap_or(CompressorState::Idle)
-                });
-
                 // Coolant pump and radiator fan always run when the machine is on
                 (CoolantPumpState::Run, RadiatorFanState::Run, compressor)
             } else {
-                // Everything off if the AC bus power is off
-                // Technically the cooler can run if the machine AC bus is off, however this also allows the cooler to be turned off when the E-stop is pressed
-                (
-                    CoolantPumpState::Idle,
-                    RadiatorFanState::Idle,
-                    CompressorState::Idle,
-                )
+                self.state.powered_since = None;
+                self.state.compressor_hold_until = None;
+                self.state.compressor_lockout = false;
+                self.state.low_temperature_lockout = false;
+
+                // Stopping the compressor due to loss of power is not subject to the minimum run time
+                if *self.state.output_compressor == Some(CompressorState::Run) {
+                    self.state.compressor_changed_at = Some(now);
+                }
+
+                match self.state.phase {
+                    // Keep coolant circulating through the (potentially still hot) tube after the AC bus is powered off
+                    RunPhase::RunOn { until: _ } => (
+                        CoolantPumpState::Run,
+                        RadiatorFanState::Run,
+                        CompressorState::Idle,
+                    ),
+                    _ => (
+                        CoolantPumpState::Idle,
+                        RadiatorFanState::Idle,
+                        CompressorState::Idle,
+                    ),
+                }
             };
 
             debug!(
-                "coolant pump {}, radiator fan {}, compressor {}",
-                pump, fan, compressor
+                "coolant pump {}, radiator fan {}, compressor {}, compressor lockout {}",
+                pump, fan, compressor, self.state.compressor_lockout
             );
 
             self.state
@@ -118,6 +472,25 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                     self.output_channel.send(OutputMessage::Compressor(v)).await;
                 })
                 .await;
+
+            // A lockout is only a problem if it is keeping the compressor off while a job needs cooling
+            let lockout_severity = match (
+                self.state.compressor_lockout,
+                self.state.machine_run,
+                self.state.compressor_demand,
+            ) {
+                (false, _, _) => Severity::Normal,
+                (true, MachineRun::Running, CompressorState::Run) => Severity::Warning,
+                (true, _, _) => Severity::Information,
+            };
+            self.state
+                .output_compressor_lockout_severity
+                .update_and_async(lockout_severity, async |v| {
+                    self.output_channel
+                        .send(OutputMessage::CompressorLockoutSeverity(v))
+                        .await;
+                })
+                .await;
         }
     }
 }
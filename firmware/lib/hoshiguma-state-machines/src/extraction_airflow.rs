@@ -2,90 +2,264 @@ use defmt::info;
 use embassy_futures::select::{Either, select};
 use embassy_time::{Duration, Instant};
 use hoshiguma_api::{
-    AirflowSensorMeasurement, AirflowSensorMeasurementInner, FumeExtractionFan, Severity,
+    AirflowSensorMeasurement, AirflowSensorMeasurementInner, ExtractionAirflowTrend,
+    FumeExtractionFan, Severity,
 };
 use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 4);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     FumeExtractionFan(FumeExtractionFan),
     ExtractionAirflowReading(AirflowSensorMeasurement),
+    /// Restores the long term airflow trend, e.g. from persistent storage.
+    RestoreTrend(ExtractionAirflowTrend),
+    /// Discards the long term airflow trend after the filter has been replaced, so that the filter
+    /// is assessed against the airflow achieved with the new filter.
+    ResetTrend,
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum OutputMessage {
     FunctionalSeverity(Severity),
     AirflowSeverity(Severity),
+    /// The long term airflow trend has been updated at the end of a fan run.
+    Trend(ExtractionAirflowTrend),
+    FilterSeverity(Severity),
 }
 
 pub struct State {
+    config: Config,
+
     fan_state: FumeExtractionFan,
     fan_state_change_time: Instant,
 
     airflow_reading: AirflowSensorMeasurementInner,
     airflow_reading_age: Instant,
 
+    /// Sum and count of the differential pressure readings taken during the current fan run.
+    run_pressure_sum: f32,
+    run_pressure_count: u32,
+
+    trend: ExtractionAirflowTrend,
+
     output_functional_severity: ObservedValue<Severity>,
     output_airflow_severity: ObservedValue<Severity>,
+    output_filter_severity: ObservedValue<Severity>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+
             fan_state: FumeExtractionFan::Idle,
             fan_state_change_time: Instant::now(),
 
             airflow_reading: AirflowSensorMeasurementInner::default(),
             airflow_reading_age: Instant::now(),
 
+            run_pressure_sum: 0.0,
+            run_pressure_count: 0,
+
+            trend: ExtractionAirflowTrend::default(),
+
             output_functional_severity: ObservedValue::default(),
             output_airflow_severity: ObservedValue::default(),
+            output_filter_severity: ObservedValue::default(),
         }
     }
 }
 
-/// Warning differential pressure in Pa.
-const WARN: f32 = 52.0;
+impl State {
+    /// Incorporates the mean differential pressure of the fan run that just ended into the long
+    /// term trend.
+    ///
+    /// Returns true if the trend was updated, i.e. the run was long enough to be representative.
+    fn complete_fan_run(&mut self) -> bool {
+        let sum = core::mem::take(&mut self.run_pressure_sum);
+        let count = core::mem::take(&mut self.run_pressure_count);
 
-/// Critical differential pressure in Pa.
-const CRITICAL: f32 = 45.0;
+        if count < self.config.trend_minimum_readings_per_run {
+            return false;
+        }
 
-/// Amount of time it typically takes the fan to reach normal operating airflow after it is powered
-/// on from stationary.
-/// This can be quite conservative as very little fumes will be produced in the first few seconds
-/// of a job.
-const FAN_RUNUP_TIME: Duration = Duration::from_secs(4);
+        let mean = sum / count as f32;
 
-/// Maximum age of an airflow reading for it to be considered good.
-/// Alarm will be raised if there has not been a good reading for this long.
-const MAX_AGE_FOR_GOOD_READING: Duration = Duration::from_secs(20);
+        self.trend.average = if self.trend.runs == 0 {
+            mean
+        } else {
+            self.trend.average + (mean - self.trend.average) * self.config.trend_weight
+        };
+        self.trend.reference = self.trend.reference.max(self.trend.average);
+        self.trend.runs = self.trend.runs.saturating_add(1);
+
+        true
+    }
+
+    fn filter_severity(&self) -> Severity {
+        if self.trend.runs < self.config.trend_minimum_runs || self.trend.reference <= 0.0 {
+            return Severity::Normal;
+        }
+
+        let drop_percent = (1.0 - (self.trend.average / self.trend.reference)) * 100.0;
+
+        if drop_percent >= self.config.filter_service_drop_percent {
+            Severity::Information
+        } else {
+            Severity::Normal
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Warning differential pressure in Pa.
+    pub warn: f32,
+
+    /// Critical differential pressure in Pa.
+    pub critical: f32,
+
+    /// Amount of time it typically takes the fan to reach normal operating airflow after it is
+    /// powered on from stationary.
+    /// This can be quite conservative as very little fumes will be produced in the first few
+    /// seconds of a job.
+    #[serde(with = "crate::serde_duration")]
+    pub fan_runup_time: Duration,
+
+    /// Maximum age of an airflow reading for it to be considered good.
+    /// Alarm will be raised if there has not been a good reading for this long.
+    #[serde(with = "crate::serde_duration")]
+    pub max_age_for_good_reading: Duration,
+
+    /// Weight given to the mean differential pressure of the most recent fan run in the long term
+    /// moving average.
+    pub trend_weight: f32,
+
+    /// Minimum number of airflow readings in a fan run for it to be included in the trend.
+    pub trend_minimum_readings_per_run: u32,
+
+    /// Minimum number of fan runs in the trend before the filter condition is assessed.
+    pub trend_minimum_runs: u32,
+
+    /// Drop in the long term average airflow, as a percentage of the reference, at which the filter
+    /// is reported as due for service.
+    /// This is only ever informational, an actual loss of airflow is reported by the airflow
+    /// severity.
+    pub filter_service_drop_percent: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            warn: 52.0,
+            critical: 45.0,
+            fan_runup_time: Duration::from_secs(4),
+            max_age_for_good_reading: Duration::from_secs(20),
+            trend_weight: 0.1,
+            trend_minimum_readings_per_run: 10,
+            trend_minimum_runs: 5,
+            filter_service_drop_percent: 15.0,
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub fan_state: FumeExtractionFan,
+    #[serde(with = "crate::serde_instant")]
+    pub fan_state_change_time: Instant,
+    pub airflow_reading: AirflowSensorMeasurementInner,
+    #[serde(with = "crate::serde_instant")]
+    pub airflow_reading_age: Instant,
+    pub run_pressure_count: u32,
+    pub trend: ExtractionAirflowTrend,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            fan_state: self.fan_state,
+            fan_state_change_time: self.fan_state_change_time,
+            airflow_reading: self.airflow_reading,
+            airflow_reading_age: self.airflow_reading_age,
+            run_pressure_count: self.run_pressure_count,
+            trend: self.trend,
+        }
+    }
+}
 
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
             // Time to wake to send an alarm if no good reading is sent before the reading expires.
             // Only wake once to do this.
-            let reading_expiry_time = self.state.airflow_reading_age + MAX_AGE_FOR_GOOD_READING;
+            let reading_expiry_time =
+                self.state.airflow_reading_age + self.state.config.max_age_for_good_reading;
             let reading_expiry_time = if Instant::now() < reading_expiry_time {
                 Some(reading_expiry_time)
             } else {
                 None
             };
 
-            match select(
-                self.input_channel.receive(),
-                MaybeTimer::at(reading_expiry_time),
-            )
-            .await
-            {
+            match select(self.receive_input(), MaybeTimer::at(reading_expiry_time)).await {
                 Either::First(InputMessage::FumeExtractionFan(state)) => {
+                    if self.state.fan_state == FumeExtractionFan::Run
+                        && state == FumeExtractionFan::Idle
+                        && self.state.complete_fan_run()
+                    {
+                        info!("Airflow trend {}", self.state.trend);
+                        self.output_channel
+                            .send(OutputMessage::Trend(self.state.trend))
+                            .await;
+                        self.update_filter_severity().await;
+                    }
+
                     self.state.fan_state = state;
                     self.state.fan_state_change_time = Instant::now();
                 }
                 Either::First(InputMessage::ExtractionAirflowReading(Ok(state))) => {
                     self.state.airflow_reading = state;
                     self.state.airflow_reading_age = Instant::now();
+
+                    // Accumulate the airflow during the fan run, once it has stabilised
+                    if self.state.fan_state == FumeExtractionFan::Run
+                        && Instant::now() - self.state.fan_state_change_time
+                            >= self.state.config.fan_runup_time
+                    {
+                        self.state.run_pressure_sum += state.differential_pressure;
+                        self.state.run_pressure_count += 1;
+                    }
+                }
+                Either::First(InputMessage::RestoreTrend(trend)) => {
+                    info!("Restored airflow trend {}", trend);
+                    self.state.trend = trend;
+                    self.update_filter_severity().await;
+                }
+                Either::First(InputMessage::ResetTrend) => {
+                    info!("Airflow trend reset");
+                    self.state.trend = ExtractionAirflowTrend::default();
+                    self.output_channel
+                        .send(OutputMessage::Trend(self.state.trend))
+                        .await;
+                    self.update_filter_severity().await;
+                }
+                Either::First(InputMessage::UpdateConfig(config)) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                    self.update_filter_severity().await;
                 }
                 Either::First(InputMessage::ExtractionAirflowReading(Err(()))) => {}
                 Either::Second(_) => {
@@ -95,7 +269,7 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
 
             // Check age of last good reading
             let reading_age = Instant::now() - self.state.airflow_reading_age;
-            let severity = if reading_age > MAX_AGE_FOR_GOOD_READING {
+            let severity = if reading_age > self.state.config.max_age_for_good_reading {
                 Severity::Critical
             } else {
                 Severity::Normal
@@ -123,13 +297,17 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                     }
                     FumeExtractionFan::Run => {
                         let time_fan_running = Instant::now() - self.state.fan_state_change_time;
-                        let severity = if time_fan_running < FAN_RUNUP_TIME {
+                        let severity = if time_fan_running < self.state.config.fan_runup_time {
                             // Fan is still running up, ignore airflow reading until fan and airflow will have stabilised
                             Severity::Normal
                         } else {
-                            if self.state.airflow_reading.differential_pressure > WARN {
+                            if self.state.airflow_reading.differential_pressure
+                                > self.state.config.warn
+                            {
                                 Severity::Normal
-                            } else if self.state.airflow_reading.differential_pressure > CRITICAL {
+                            } else if self.state.airflow_reading.differential_pressure
+                                > self.state.config.critical
+                            {
                                 Severity::Warning
                             } else {
                                 Severity::Critical
@@ -157,3 +335,18 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
         }
     }
 }
+
+impl<'a> StateMachineRunner<'a> {
+    async fn update_filter_severity(&mut self) {
+        let severity = self.state.filter_severity();
+        info!("filter severity {}", severity);
+        self.state
+            .output_filter_severity
+            .update_and_async(severity, async |v| {
+                self.output_channel
+                    .send(OutputMessage::FilterSeverity(v))
+                    .await;
+            })
+            .await;
+    }
+}
@@ -1,38 +1,61 @@
-use defmt::{Format, debug, info};
-use embassy_futures::select::{Either, select};
+use defmt::{Format, debug, info, warn};
+use embassy_futures::select::{Either3, select3};
 use embassy_time::{Duration, Instant};
-use hoshiguma_api::{AcBusPower, FumeExtractionFan, FumeExtractionMode, MachineRun};
+use hoshiguma_api::{
+    AcBusPower, AirflowSensorMeasurement, FumeExtractionFan, FumeExtractionMode, MachineRun,
+};
 use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 4);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     AcBusPower(AcBusPower),
     MachineRun(MachineRun),
     Mode(FumeExtractionMode),
+    ExtractionAirflowReading(AirflowSensorMeasurement),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum OutputMessage {
     ExtractionFan(FumeExtractionFan),
+    Mode(FumeExtractionMode),
 }
 
 pub struct State {
+    config: Config,
+
     machine_power: AcBusPower,
     mode: FumeExtractionMode,
+    override_until: Option<Instant>,
     state: RunPhase,
 
+    job_started: Option<Instant>,
+    ambient_temperature: Option<f32>,
+    airflow_temperature: Option<f32>,
+
     output_fan: ObservedValue<FumeExtractionFan>,
+    output_mode: ObservedValue<FumeExtractionMode>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+
             machine_power: AcBusPower::Off,
             mode: FumeExtractionMode::Automatic,
+            override_until: None,
             state: RunPhase::Idle,
 
+            job_started: None,
+            ambient_temperature: None,
+            airflow_temperature: None,
+
             output_fan: ObservedValue::default(),
+            output_mode: ObservedValue::default(),
         }
     }
 }
@@ -46,10 +69,19 @@ impl From<&State> for FumeExtractionFan {
     }
 }
 
-#[derive(Format, Clone)]
-enum RunPhase {
+#[derive(Debug, Format, Clone, Serialize, Deserialize)]
+pub enum RunPhase {
     Idle,
-    RunOn { until: Instant },
+    RunOn {
+        #[serde(with = "crate::serde_instant")]
+        until: Instant,
+        #[serde(with = "crate::serde_instant")]
+        limit: Instant,
+    },
+    Cooldown {
+        #[serde(with = "crate::serde_instant")]
+        until: Instant,
+    },
     Demand,
 }
 
@@ -57,46 +89,195 @@ impl From<&RunPhase> for FumeExtractionFan {
     fn from(state: &RunPhase) -> Self {
         match state {
             RunPhase::Idle => Self::Idle,
-            RunPhase::RunOn { until: _ } => Self::Run,
+            RunPhase::RunOn { .. } => Self::Run,
+            RunPhase::Cooldown { .. } => Self::Run,
             RunPhase::Demand => Self::Run,
         }
     }
 }
 
-const TIMEOUT: Duration = Duration::from_secs(45);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Shortest time the fan is run for after a job ends.
+    #[serde(with = "crate::serde_duration")]
+    pub run_on_minimum: Duration,
+
+    /// Longest time the fan is run for after a job ends, including any time spent waiting for the
+    /// extraction airflow to cool.
+    #[serde(with = "crate::serde_duration")]
+    pub run_on_maximum: Duration,
+
+    /// The run-on is this fraction of the job duration (before being limited to the above range).
+    pub run_on_job_duration_divisor: u32,
+
+    /// Whether to keep running after the run-on until the extraction airflow has cooled to near
+    /// ambient temperature.
+    pub run_on_until_ambient: bool,
+
+    /// Extraction airflow temperature above ambient that is considered to have cooled.
+    pub ambient_temperature_tolerance: f32,
+
+    /// Time after which an extraction override reverts to automatic control.
+    #[serde(with = "crate::serde_duration")]
+    pub override_timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            run_on_minimum: Duration::from_secs(45),
+            run_on_maximum: Duration::from_secs(15 * 60),
+            run_on_job_duration_divisor: 4,
+            run_on_until_ambient: true,
+            ambient_temperature_tolerance: 2.0,
+            override_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl Config {
+    /// The run-on for a job, a zero divisor gives the longest run-on and the maximum takes
+    /// precedence over the minimum if they are the wrong way around.
+    fn run_on_duration(&self, job_duration: Duration) -> Duration {
+        job_duration
+            .checked_div(self.run_on_job_duration_divisor)
+            .unwrap_or(self.run_on_maximum)
+            .max(self.run_on_minimum)
+            .min(self.run_on_maximum)
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        if config.run_on_job_duration_divisor == 0 {
+            warn!("Run-on job duration divisor is zero, the maximum run-on will always be used");
+        }
+        if config.run_on_minimum > config.run_on_maximum {
+            warn!("Minimum run-on is longer than the maximum, the maximum will always be used");
+        }
+
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub machine_power: AcBusPower,
+    pub mode: FumeExtractionMode,
+    #[serde(with = "crate::serde_instant::option")]
+    pub override_until: Option<Instant>,
+    pub phase: RunPhase,
+    #[serde(with = "crate::serde_instant::option")]
+    pub job_started: Option<Instant>,
+    pub ambient_temperature: Option<f32>,
+    pub airflow_temperature: Option<f32>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            machine_power: self.machine_power,
+            mode: self.mode,
+            override_until: self.override_until,
+            phase: self.state.clone(),
+            job_started: self.job_started,
+            ambient_temperature: self.ambient_temperature,
+            airflow_temperature: self.airflow_temperature,
+        }
+    }
+}
 
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
-            let run_on_timer =
-                MaybeTimer::at(if let RunPhase::RunOn { until } = self.state.state {
-                    Some(until)
-                } else {
-                    None
-                });
-
-            match select(self.input_channel.receive(), run_on_timer).await {
-                Either::First(InputMessage::AcBusPower(state)) => {
+            let run_on_timer = MaybeTimer::at(match self.state.state {
+                RunPhase::RunOn { until, .. } => Some(until),
+                RunPhase::Cooldown { until } => Some(until),
+                _ => None,
+            });
+
+            let override_timer = MaybeTimer::at(self.state.override_until);
+
+            match select3(self.receive_input(), run_on_timer, override_timer).await {
+                Either3::First(InputMessage::AcBusPower(state)) => {
                     self.state.machine_power = state;
                 }
-                Either::First(InputMessage::MachineRun(state)) => {
-                    self.state.state = match state {
-                        MachineRun::Idle => match self.state.state {
-                            RunPhase::Idle => RunPhase::Idle,
-                            RunPhase::RunOn { until } => RunPhase::RunOn { until },
-                            RunPhase::Demand => RunPhase::RunOn {
-                                until: Instant::now() + TIMEOUT,
-                            },
-                        },
-                        MachineRun::Running => RunPhase::Demand,
+                Either3::First(InputMessage::MachineRun(state)) => {
+                    self.state.state = match (state, self.state.state.clone()) {
+                        (MachineRun::Idle, RunPhase::Demand) => {
+                            let now = Instant::now();
+                            let job_duration = self
+                                .state
+                                .job_started
+                                .take()
+                                .map(|start| now - start)
+                                .unwrap_or_default();
+                            let run_on = self.state.config.run_on_duration(job_duration);
+                            info!("Job ran for {}, running on for {}", job_duration, run_on);
+
+                            RunPhase::RunOn {
+                                until: now + run_on,
+                                limit: now + self.state.config.run_on_maximum,
+                            }
+                        }
+                        (MachineRun::Idle, phase) => phase,
+                        (MachineRun::Running, RunPhase::Demand) => RunPhase::Demand,
+                        (MachineRun::Running, _) => {
+                            self.state.job_started = Some(Instant::now());
+                            RunPhase::Demand
+                        }
                     };
                 }
-                Either::First(InputMessage::Mode(mode)) => {
+                Either3::First(InputMessage::Mode(mode)) => {
                     self.state.mode = mode;
+                    self.state.override_until = match mode {
+                        FumeExtractionMode::Automatic => None,
+                        FumeExtractionMode::OverrideRun => {
+                            Some(Instant::now() + self.state.config.override_timeout)
+                        }
+                    };
+                }
+                Either3::First(InputMessage::ExtractionAirflowReading(reading)) => {
+                    self.state.airflow_temperature = reading.ok().map(|r| r.temperature);
+
+                    match self.state.state {
+                        // With the fan stopped the extraction airflow sensor reads ambient
+                        RunPhase::Idle if self.state.airflow_temperature.is_some() => {
+                            self.state.ambient_temperature = self.state.airflow_temperature;
+                        }
+                        RunPhase::Cooldown { .. } if !self.is_airflow_above_ambient() => {
+                            debug!("Extraction airflow has cooled to ambient");
+                            self.state.state = RunPhase::Idle;
+                        }
+                        _ => {}
+                    }
+                }
+                Either3::First(InputMessage::UpdateConfig(config)) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+                Either3::Second(()) => {
+                    self.state.state = match self.state.state {
+                        RunPhase::RunOn { limit, .. }
+                            if self.state.config.run_on_until_ambient
+                                && self.is_airflow_above_ambient() =>
+                        {
+                            debug!("Run on timer expired, waiting for extraction airflow to cool");
+                            RunPhase::Cooldown { until: limit }
+                        }
+                        _ => {
+                            debug!("Run on timer expired");
+                            RunPhase::Idle
+                        }
+                    };
                 }
-                Either::Second(()) => {
-                    debug!("Run on timer expired");
-                    self.state.state = RunPhase::Idle;
+                Either3::Third(()) => {
+                    info!("Override timer expired, returning to automatic mode");
+                    self.state.mode = FumeExtractionMode::Automatic;
+                    self.state.override_until = None;
                 }
             }
 
@@ -116,6 +297,27 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                         .await;
                 })
                 .await;
+
+            self.state
+                .output_mode
+                .update_and_async(self.state.mode, async |v| {
+                    self.output_channel.send(OutputMessage::Mode(v)).await;
+                })
+                .await;
+        }
+    }
+}
+
+impl<'a> StateMachineRunner<'a> {
+    fn is_airflow_above_ambient(&self) -> bool {
+        match (
+            self.state.airflow_temperature,
+            self.state.ambient_temperature,
+        ) {
+            (Some(airflow), Some(ambient)) => {
+                airflow > ambient + self.state.config.ambient_temperature_tolerance
+            }
+            _ => false,
         }
     }
 }
@@ -4,20 +4,26 @@ use embassy_futures::select::{Either, select};
 use embassy_time::{Duration, Instant};
 use heapless::Vec;
 use hoshiguma_api::{
-    DesiredMachinePower, Interlock, MachineRun, Monitor, Severity,
+    DesiredMachinePower, FumeExtractionMode, Interlock, MachineRun, Monitor, Severity,
     hmi::{AccessControlRawInput, OnscreenMessage, StatusScreenInfo},
 };
 use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 use strum::EnumCount;
 
 crate::state_machine!(InputMessage, OutputMessage, State, 8);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     AccessControlRawInput(AccessControlRawInput),
     DesiredMachinePower(DesiredMachinePower),
     Interlock(Interlock),
     MachineRun(MachineRun),
+    FumeExtractionMode(FumeExtractionMode),
     MonitorStates(MonitorStateMap),
+    GracePeriodEnd(#[serde(with = "crate::serde_instant::option")] Option<Instant>),
+    SessionHoldEnd(#[serde(with = "crate::serde_instant::option")] Option<Instant>),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
@@ -26,7 +32,11 @@ pub enum OutputMessage {
 }
 
 pub struct State {
+    config: Config,
+
     current: StatusScreenInfo,
+    grace_period_end: Option<Instant>,
+    session_hold_end: Option<Instant>,
     next_emit_time: Option<Instant>,
     last_emitted: ObservedValue<StatusScreenInfo>,
 }
@@ -38,24 +48,81 @@ impl Default for State {
             machine_power: DesiredMachinePower::Off,
             interlock: Interlock::OperationDenied,
             running: MachineRun::Idle,
+            fume_extraction_mode: FumeExtractionMode::Automatic,
             messages: Vec::new(),
+            grace_period_remaining: None,
+            session_hold_remaining: None,
         };
 
         Self {
+            config: Config::default(),
+
             current: default_status,
+            grace_period_end: None,
+            session_hold_end: None,
             next_emit_time: None,
             last_emitted: ObservedValue::default(),
         }
     }
 }
 
-const DEBOUNCE_DURATION: Duration = Duration::from_millis(50);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Time inputs are allowed to settle for before the status screen is emitted.
+    #[serde(with = "crate::serde_duration")]
+    pub debounce_duration: Duration,
+
+    /// Interval at which the status screen is refreshed while a countdown is shown.
+    #[serde(with = "crate::serde_duration")]
+    pub countdown_update_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debounce_duration: Duration::from_millis(50),
+            countdown_update_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub current: StatusScreenInfo,
+    #[serde(with = "crate::serde_instant::option")]
+    pub grace_period_end: Option<Instant>,
+    #[serde(with = "crate::serde_instant::option")]
+    pub session_hold_end: Option<Instant>,
+    #[serde(with = "crate::serde_instant::option")]
+    pub next_emit_time: Option<Instant>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            current: self.current.clone(),
+            grace_period_end: self.grace_period_end,
+            session_hold_end: self.session_hold_end,
+            next_emit_time: self.next_emit_time,
+        }
+    }
+}
 
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
             match select(
-                self.input_channel.receive(),
+                self.receive_input(),
                 MaybeTimer::at(self.state.next_emit_time),
             )
             .await
@@ -74,12 +141,25 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                         InputMessage::MachineRun(state) => {
                             self.state.current.running = state;
                         }
+                        InputMessage::FumeExtractionMode(mode) => {
+                            self.state.current.fume_extraction_mode = mode;
+                        }
                         InputMessage::MonitorStates(states) => {
                             self.state.current.messages = monitor_statuses_to_messages(states);
                         }
+                        InputMessage::GracePeriodEnd(end) => {
+                            self.state.grace_period_end = end;
+                        }
+                        InputMessage::SessionHoldEnd(end) => {
+                            self.state.session_hold_end = end;
+                        }
+                        InputMessage::UpdateConfig(config) => {
+                            crate::ConfigurableState::configure(&mut self.state, config);
+                        }
                     }
 
-                    self.state.next_emit_time = Some(Instant::now() + DEBOUNCE_DURATION);
+                    self.state.next_emit_time =
+                        Some(Instant::now() + self.state.config.debounce_duration);
                     info!(
                         "Received new input, scheduling status screen emit at {}",
                         self.state.next_emit_time
@@ -90,7 +170,18 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                         "Status screen debounce timer expired, emitting new status screen ({})",
                         Instant::now()
                     );
-                    self.state.next_emit_time = None;
+                    self.state.current.grace_period_remaining =
+                        self.state.grace_period_end.map(countdown_remaining);
+                    self.state.current.session_hold_remaining =
+                        self.state.session_hold_end.map(countdown_remaining);
+
+                    // Keep the countdowns updated for as long as there are any
+                    self.state.next_emit_time = self
+                        .state
+                        .grace_period_end
+                        .or(self.state.session_hold_end)
+                        .map(|_| Instant::now() + self.state.config.countdown_update_interval);
+
                     self.state
                         .last_emitted
                         .update_and_async(self.state.current.clone(), async |v| {
@@ -105,6 +196,12 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     }
 }
 
+fn countdown_remaining(end: Instant) -> core::time::Duration {
+    // Round up to the next second, so that the countdown reaches zero as the period ends
+    let remaining = end.saturating_duration_since(Instant::now());
+    core::time::Duration::from_secs(remaining.as_millis().div_ceil(1000))
+}
+
 pub fn monitor_statuses_to_messages(monitor_states: MonitorStateMap) -> Vec<OnscreenMessage, 8> {
     // First sort the states by severity, most severe first.
     let mut monitor_states: Vec<_, { Monitor::COUNT }> = monitor_states.into_iter().collect();
@@ -138,5 +235,11 @@ fn monitor_to_text(monitor: Monitor) -> &'static str {
         Monitor::CoolantReservoirTemperature => "Coolant Reservoir Temp.",
         Monitor::ExtractionAirflow => "Extraction Airflow Low",
         Monitor::ExtractionAirflowSensorFunctional => "Airflow Sensor Fault",
+        Monitor::CompressorLockout => "Compressor Lockout",
+        Monitor::CoolantLeak => "Coolant Leak",
+        Monitor::ExtractionFilter => "Filter Service Due",
+        Monitor::AirAssistPressure => "Air Assist Pressure",
+        Monitor::TemperatureRateOfRise => "Rapid Temperature Rise",
+        Monitor::ServiceDue => "Service Due",
     }
 }
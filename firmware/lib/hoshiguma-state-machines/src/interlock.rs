@@ -1,14 +1,19 @@
-use defmt::info;
+use defmt::{Format, info, warn};
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant};
 use heapless::LinearMap;
 use hoshiguma_api::{Interlock, InterlockAction, MachineRun, Monitor, Severity};
-use hoshiguma_common::changed::ObservedValue;
+use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 use strum::{EnumCount, IntoEnumIterator};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 32);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     Monitor(Monitor, Severity),
     MachineRun(MachineRun),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
@@ -16,17 +21,23 @@ pub enum OutputMessage {
     States(MonitorStateMap),
     Interlock(Interlock),
     Action(InterlockAction),
+    /// The time at which the current grace period ends, if one is in progress.
+    GracePeriodEnd(Option<Instant>),
 }
 
 pub type MonitorStateMap = LinearMap<Monitor, Severity, { Monitor::COUNT }>;
 
 pub struct State {
+    config: Config,
+
     monitor_states: MonitorStateMap,
     machine_run: MachineRun,
+    grace_period: GracePeriod,
 
     output_states: ObservedValue<MonitorStateMap>,
     output_interlock: ObservedValue<Interlock>,
     output_action: ObservedValue<InterlockAction>,
+    output_grace_period_end: ObservedValue<Option<Instant>>,
 }
 
 impl Default for State {
@@ -42,16 +53,67 @@ impl Default for State {
             .unwrap();
 
         Self {
+            config: Config::default(),
+
             monitor_states,
             machine_run: MachineRun::Idle,
+            grace_period: GracePeriod::Inactive,
 
             output_states: ObservedValue::default(),
             output_interlock: ObservedValue::default(),
             output_action: ObservedValue::default(),
+            // Only changes to the grace period are of interest, there is no grace period to begin with.
+            output_grace_period_end: ObservedValue::new(None),
+        }
+    }
+}
+
+/// Tracks how long a job may continue once the interlock has become
+/// `Interlock::OperationPermittedUntilIdle`.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GracePeriod {
+    Inactive,
+    Active {
+        #[serde(with = "crate::serde_instant")]
+        until: Instant,
+    },
+    Expired,
+}
+
+impl GracePeriod {
+    fn end(&self) -> Option<Instant> {
+        match self {
+            GracePeriod::Active { until } => Some(*until),
+            _ => None,
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Maximum amount of time a job may continue to run for once the interlock has moved to
+    /// `Interlock::OperationPermittedUntilIdle`.
+    /// After this the machine is disabled, even if the job has not finished.
+    #[serde(with = "crate::serde_duration")]
+    pub grace_period_max_duration: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            grace_period_max_duration: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
 impl State {
     /// Gets the overall severity across all monitors, i.e. the most severe individual monitor.
     fn overall_severity(&self) -> Severity {
@@ -63,11 +125,61 @@ impl State {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub config: Config,
+    pub monitor_states: MonitorStateMap,
+    pub machine_run: MachineRun,
+    pub grace_period: GracePeriod,
+    pub output_states: Option<MonitorStateMap>,
+    pub output_interlock: Option<Interlock>,
+    pub output_action: Option<InterlockAction>,
+    #[serde(with = "crate::serde_instant::option_option")]
+    pub output_grace_period_end: Option<Option<Instant>>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            config: self.config.clone(),
+            monitor_states: self.monitor_states.clone(),
+            machine_run: self.machine_run,
+            grace_period: self.grace_period,
+            output_states: (*self.output_states).clone(),
+            output_interlock: *self.output_interlock,
+            output_action: *self.output_action,
+            output_grace_period_end: *self.output_grace_period_end,
+        }
+    }
+}
+
+impl crate::RestoreSnapshot for State {
+    fn restore(snapshot: Snapshot) -> Self {
+        Self {
+            config: snapshot.config,
+            monitor_states: snapshot.monitor_states,
+            machine_run: snapshot.machine_run,
+            grace_period: snapshot.grace_period,
+            output_states: snapshot.output_states.into(),
+            output_interlock: snapshot.output_interlock.into(),
+            output_action: snapshot.output_action.into(),
+            output_grace_period_end: snapshot.output_grace_period_end.into(),
+        }
+    }
+}
+
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
-            match self.input_channel.receive().await {
-                InputMessage::Monitor(monitor, severity) => {
+            match select(
+                self.receive_input(),
+                MaybeTimer::at(self.state.grace_period.end()),
+            )
+            .await
+            {
+                Either::First(InputMessage::Monitor(monitor, severity)) => {
                     assert_ne!(
                         monitor,
                         Monitor::InterlockTripped,
@@ -94,9 +206,16 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                             .unwrap();
                     }
                 }
-                InputMessage::MachineRun(machine_run) => {
+                Either::First(InputMessage::MachineRun(machine_run)) => {
                     self.state.machine_run = machine_run;
                 }
+                Either::First(InputMessage::UpdateConfig(config)) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+                Either::Second(()) => {
+                    warn!("Grace period expired");
+                    self.state.grace_period = GracePeriod::Expired;
+                }
             }
 
             let severity = self.state.overall_severity();
@@ -118,10 +237,41 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                 })
                 .await;
 
+            // A grace period is only relevant while a job is allowed to finish
+            let grace_period = match (interlock, self.state.machine_run) {
+                (Interlock::OperationPermittedUntilIdle, MachineRun::Running) => {
+                    match self.state.grace_period {
+                        GracePeriod::Inactive => GracePeriod::Active {
+                            until: Instant::now() + self.state.config.grace_period_max_duration,
+                        },
+                        other => other,
+                    }
+                }
+                _ => GracePeriod::Inactive,
+            };
+
+            if grace_period != self.state.grace_period {
+                info!("Grace period {}", grace_period);
+                self.state.grace_period = grace_period;
+            }
+
+            // Send grace period output
+            self.state
+                .output_grace_period_end
+                .update_and_async(self.state.grace_period.end(), async |v| {
+                    self.output_channel
+                        .send(OutputMessage::GracePeriodEnd(v))
+                        .await;
+                })
+                .await;
+
             let action = match interlock {
                 Interlock::OperationPermitted => InterlockAction::Normal,
                 Interlock::OperationPermittedUntilIdle => match self.state.machine_run {
-                    MachineRun::Running => InterlockAction::Normal,
+                    MachineRun::Running => match self.state.grace_period {
+                        GracePeriod::Expired => InterlockAction::Disable,
+                        _ => InterlockAction::Normal,
+                    },
                     MachineRun::Idle => InterlockAction::Disable,
                 },
                 Interlock::OperationDenied => InterlockAction::Disable,
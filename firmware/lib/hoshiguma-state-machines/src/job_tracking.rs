@@ -0,0 +1,200 @@
+use defmt::{Format, info};
+use embassy_time::{Duration, Instant};
+use hoshiguma_api::{
+    AirflowSensorMeasurement, DesiredMachinePower, Doors, InterlockAction, MachineRun,
+    TemperatureSensor, TemperatureSensorReading, orchestrator::JobEndReason,
+};
+use serde::{Deserialize, Serialize};
+
+crate::state_machine!(InputMessage, OutputMessage, State, 8);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InputMessage {
+    MachineRun(MachineRun),
+    InterlockAction(InterlockAction),
+    Doors(Doors),
+    DesiredMachinePower(DesiredMachinePower),
+    Temperature(TemperatureSensorReading),
+    ExtractionAirflowReading(AirflowSensorMeasurement),
+    UpdateConfig(Config),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OutputMessage {
+    JobEnded(JobSummary),
+}
+
+/// Summary of a job, produced when the job ends.
+#[derive(Debug, Format, Clone, PartialEq)]
+pub struct JobSummary {
+    pub duration: Duration,
+    pub peak_coolant_reservoir_temperature: Option<f32>,
+    pub minimum_extraction_airflow: Option<f32>,
+    pub interlock_events: u16,
+    pub door_openings: u16,
+    pub end_reason: JobEndReason,
+}
+
+#[derive(Default)]
+pub struct State {
+    config: Config,
+
+    interlock_action: Option<InterlockAction>,
+    doors: Option<Doors>,
+    power: Option<DesiredMachinePower>,
+    job: Option<Job>,
+}
+
+impl State {
+    /// Why a job ending now has ended, based on what is currently stopping the machine.
+    fn end_reason(&self) -> JobEndReason {
+        if self
+            .interlock_action
+            .is_some_and(|action| action != InterlockAction::Normal)
+        {
+            JobEndReason::Interlock
+        } else if self.power == Some(DesiredMachinePower::Off) {
+            JobEndReason::PowerRemoved
+        } else {
+            JobEndReason::Completed
+        }
+    }
+}
+
+/// A job that is currently running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    #[serde(with = "crate::serde_instant")]
+    start: Instant,
+    peak_coolant_reservoir_temperature: Option<f32>,
+    minimum_extraction_airflow: Option<f32>,
+    interlock_events: u16,
+    door_openings: u16,
+}
+
+impl Job {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            peak_coolant_reservoir_temperature: None,
+            minimum_extraction_airflow: None,
+            interlock_events: 0,
+            door_openings: 0,
+        }
+    }
+
+    fn summary(&self, end_reason: JobEndReason) -> JobSummary {
+        JobSummary {
+            duration: Instant::now() - self.start,
+            peak_coolant_reservoir_temperature: self.peak_coolant_reservoir_temperature,
+            minimum_extraction_airflow: self.minimum_extraction_airflow,
+            interlock_events: self.interlock_events,
+            door_openings: self.door_openings,
+            end_reason,
+        }
+    }
+}
+
+/// Job tracking has no tunable parameters.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Config {}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub interlock_action: Option<InterlockAction>,
+    pub doors: Option<Doors>,
+    pub power: Option<DesiredMachinePower>,
+    /// The job that is currently running, if any.
+    pub job: Option<Job>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            interlock_action: self.interlock_action,
+            doors: self.doors,
+            power: self.power,
+            job: self.job.clone(),
+        }
+    }
+}
+
+impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
+    async fn run(&mut self) -> ! {
+        loop {
+            match self.receive_input().await {
+                InputMessage::MachineRun(MachineRun::Running) => {
+                    if self.state.job.is_none() {
+                        info!("Job started");
+                        self.state.job = Some(Job::new());
+                    }
+                }
+                InputMessage::MachineRun(MachineRun::Idle) => {
+                    if let Some(job) = self.state.job.take() {
+                        let summary = job.summary(self.state.end_reason());
+                        info!("Job ended: {}", summary);
+                        self.output_channel
+                            .send(OutputMessage::JobEnded(summary))
+                            .await;
+                    }
+                }
+                InputMessage::InterlockAction(action) => {
+                    if let Some(job) = &mut self.state.job
+                        && self.state.interlock_action != Some(action)
+                    {
+                        job.interlock_events = job.interlock_events.saturating_add(1);
+                    }
+                    self.state.interlock_action = Some(action);
+                }
+                InputMessage::Doors(doors) => {
+                    if let Some(job) = &mut self.state.job
+                        && doors == Doors::Open
+                        && self.state.doors != Some(Doors::Open)
+                    {
+                        job.door_openings = job.door_openings.saturating_add(1);
+                    }
+                    self.state.doors = Some(doors);
+                }
+                InputMessage::DesiredMachinePower(power) => {
+                    self.state.power = Some(power);
+                }
+                InputMessage::Temperature(TemperatureSensorReading {
+                    sensor: TemperatureSensor::CoolantReservoir,
+                    reading: Ok(temperature),
+                }) => {
+                    if let Some(job) = &mut self.state.job {
+                        job.peak_coolant_reservoir_temperature = Some(
+                            job.peak_coolant_reservoir_temperature
+                                .map_or(temperature, |peak| peak.max(temperature)),
+                        );
+                    }
+                }
+                InputMessage::Temperature(_) => {}
+                InputMessage::ExtractionAirflowReading(Ok(reading)) => {
+                    if let Some(job) = &mut self.state.job {
+                        job.minimum_extraction_airflow = Some(
+                            job.minimum_extraction_airflow
+                                .map_or(reading.differential_pressure, |minimum| {
+                                    minimum.min(reading.differential_pressure)
+                                }),
+                        );
+                    }
+                }
+                InputMessage::ExtractionAirflowReading(Err(_)) => {}
+                InputMessage::UpdateConfig(config) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+            }
+        }
+    }
+}
@@ -7,21 +7,65 @@ pub mod extraction_airflow;
 pub mod fume_extraction;
 pub mod hmi_status_screen;
 pub mod interlock;
+pub mod job_tracking;
 pub mod machine_power;
+pub mod recording;
+pub mod runtime_counters;
+mod serde_duration;
+mod serde_instant;
 pub mod status_light;
+pub mod temperature_filter;
 pub mod temperatures;
 
-pub struct StateMachineRunner<InputChannel, OutputChannel, State: Default> {
+pub struct StateMachineRunner<InputChannel, OutputChannel, State: Default, Recorder> {
     input_channel: InputChannel,
     output_channel: OutputChannel,
     state: State,
+    recorder: Option<Recorder>,
 }
 
+impl<InputChannel, OutputChannel, State: Default + RestoreSnapshot, Recorder>
+    StateMachineRunner<InputChannel, OutputChannel, State, Recorder>
+{
+    /// Replaces the state with one recreated from a snapshot, before the state machine is run.
+    pub fn restore_snapshot(&mut self, snapshot: State::Snapshot) {
+        self.state = State::restore(snapshot);
+    }
+}
+
+/// Longest time to wait for a state machine to provide a snapshot, it only does so while waiting for
+/// an input so will not respond if it is not running.
+pub const SNAPSHOT_TIMEOUT: embassy_time::Duration = embassy_time::Duration::from_millis(100);
+
 #[allow(async_fn_in_trait)]
 pub trait StateMachineRun {
     async fn run(&mut self) -> !;
 }
 
+/// Provides a serialisable copy of the internal state of a state machine, for introspection.
+pub trait StateSnapshot {
+    type Snapshot: Clone + serde::Serialize + serde::de::DeserializeOwned;
+
+    fn snapshot(&self) -> Self::Snapshot;
+}
+
+/// Allows a state to be recreated from a snapshot, so that recorded inputs can be replayed from
+/// part way through.
+///
+/// Only implemented by state machines whose inputs are recorded.
+pub trait RestoreSnapshot: StateSnapshot {
+    fn restore(snapshot: Self::Snapshot) -> Self;
+}
+
+/// Allows the tunable parameters of a state machine to be set at construction and replaced while
+/// it runs.
+pub trait ConfigurableState {
+    type Config: Clone + Default;
+
+    /// Applies a new configuration, replacing the current one.
+    fn configure(&mut self, config: Self::Config);
+}
+
 #[macro_export]
 macro_rules! state_machine {
     ($input_msg: ty, $output_msg: ty, $state: ty, $channel_size: expr) => {
@@ -52,6 +96,10 @@ macro_rules! state_machine {
             >,
             OutputChannelSender<'a>,
             $state,
+            &'a dyn $crate::recording::InputRecorder<
+                $input_msg,
+                <$state as $crate::StateSnapshot>::Snapshot,
+            >,
         >;
 
         pub struct StateMachineCommunicator<'a> {
@@ -74,6 +122,14 @@ macro_rules! state_machine {
                 self.input_channel.send(message).await;
             }
 
+            /// Replaces the configuration of the running state machine.
+            pub async fn update_config(
+                &self,
+                config: <$state as $crate::ConfigurableState>::Config,
+            ) {
+                self.send_input(<$input_msg>::UpdateConfig(config)).await;
+            }
+
             pub fn receive_channel_len(&self) -> usize {
                 self.output_channel.len()
             }
@@ -83,15 +139,96 @@ macro_rules! state_machine {
             }
         }
 
+        static SNAPSHOT_LOCK: embassy_sync::mutex::Mutex<
+            embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+            (),
+        > = embassy_sync::mutex::Mutex::new(());
+        static SNAPSHOT_REQUEST: embassy_sync::signal::Signal<
+            embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+            (),
+        > = embassy_sync::signal::Signal::new();
+        static SNAPSHOT_RESPONSE: embassy_sync::signal::Signal<
+            embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+            <$state as $crate::StateSnapshot>::Snapshot,
+        > = embassy_sync::signal::Signal::new();
+
+        /// The state of the state machine, taken the next time it waits for an input.
+        ///
+        /// Returns `None` if the state machine is not running.
+        pub async fn snapshot() -> Option<<$state as $crate::StateSnapshot>::Snapshot> {
+            let _lock = SNAPSHOT_LOCK.lock().await;
+
+            SNAPSHOT_RESPONSE.reset();
+            SNAPSHOT_REQUEST.signal(());
+
+            let snapshot = embassy_time::WithTimeout::with_timeout(
+                SNAPSHOT_RESPONSE.wait(),
+                $crate::SNAPSHOT_TIMEOUT,
+            )
+            .await
+            .ok();
+
+            SNAPSHOT_REQUEST.reset();
+            snapshot
+        }
+
+        impl<'a> StateMachineRunner<'a> {
+            /// Records every input received by the state machine from now on.
+            pub fn record_inputs(
+                &mut self,
+                recorder: &'a dyn $crate::recording::InputRecorder<
+                    $input_msg,
+                    <$state as $crate::StateSnapshot>::Snapshot,
+                >,
+            ) {
+                self.recorder = Some(recorder);
+            }
+
+            /// Waits for the next input, providing snapshots of the current state whenever they are
+            /// requested in the meantime.
+            async fn receive_input(&self) -> $input_msg {
+                use embassy_futures::select::{Either, select};
+
+                loop {
+                    match select(self.input_channel.receive(), SNAPSHOT_REQUEST.wait()).await {
+                        Either::First(input) => {
+                            // The state has not changed while waiting, so is still that before the
+                            // input
+                            if let Some(recorder) = self.recorder {
+                                recorder.record(&input, &|| {
+                                    $crate::StateSnapshot::snapshot(&self.state)
+                                });
+                            }
+
+                            return input;
+                        }
+                        Either::Second(()) => {
+                            SNAPSHOT_RESPONSE.signal($crate::StateSnapshot::snapshot(&self.state));
+                        }
+                    }
+                }
+            }
+        }
+
         pub fn new<'a>(
             input_channel: &'a InputChannel,
             output_channel: &'a OutputChannel,
         ) -> (StateMachineRunner<'a>, StateMachineCommunicator<'a>) {
-            let runner = StateMachineRunner {
+            new_with_config(input_channel, output_channel, Default::default())
+        }
+
+        pub fn new_with_config<'a>(
+            input_channel: &'a InputChannel,
+            output_channel: &'a OutputChannel,
+            config: <$state as $crate::ConfigurableState>::Config,
+        ) -> (StateMachineRunner<'a>, StateMachineCommunicator<'a>) {
+            let mut runner = StateMachineRunner {
                 input_channel: input_channel.receiver(),
                 output_channel: output_channel.sender(),
                 state: Default::default(),
+                recorder: None,
             };
+            $crate::ConfigurableState::configure(&mut runner.state, config);
 
             let communicator = StateMachineCommunicator {
                 input_channel: input_channel.sender(),
@@ -1,33 +1,96 @@
-use defmt::info;
-use hoshiguma_api::{DesiredMachinePower, InterlockAction, hmi::AccessControlState};
-use hoshiguma_common::changed::ObservedValue;
+use defmt::{info, warn};
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant};
+use hoshiguma_api::{DesiredMachinePower, InterlockAction, MachineRun, hmi::AccessControlState};
+use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 8);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     AccessControlState(AccessControlState),
     InterlockAction(InterlockAction),
+    MachineRun(MachineRun),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum OutputMessage {
     Power(DesiredMachinePower),
+    /// Time at which power will be removed, if access has ended while a job is running.
+    SessionHoldEnd(Option<Instant>),
 }
 
 pub struct State {
+    config: Config,
+
     access_control: AccessControlState,
     interlock: InterlockAction,
+    machine_run: MachineRun,
+    session_hold_end: Option<Instant>,
 
     output_power: ObservedValue<DesiredMachinePower>,
+    output_session_hold_end: ObservedValue<Option<Instant>>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+
             access_control: AccessControlState::Denied,
             interlock: InterlockAction::Shutdown,
+            machine_run: MachineRun::Idle,
+            session_hold_end: None,
 
             output_power: ObservedValue::default(),
+            output_session_hold_end: ObservedValue::new(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Longest time that power is kept on for a running job to finish after access has ended.
+    #[serde(with = "crate::serde_duration")]
+    pub session_hold_maximum_duration: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            session_hold_maximum_duration: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub access_control: AccessControlState,
+    pub interlock: InterlockAction,
+    pub machine_run: MachineRun,
+    #[serde(with = "crate::serde_instant::option")]
+    pub session_hold_end: Option<Instant>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            access_control: self.access_control,
+            interlock: self.interlock,
+            machine_run: self.machine_run,
+            session_hold_end: self.session_hold_end,
         }
     }
 }
@@ -35,25 +98,52 @@ impl Default for State {
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
-            match self.input_channel.receive().await {
-                InputMessage::AccessControlState(state) => {
+            let hold_expired = match select(
+                self.receive_input(),
+                MaybeTimer::at(self.state.session_hold_end),
+            )
+            .await
+            {
+                Either::First(InputMessage::AccessControlState(state)) => {
                     self.state.access_control = state;
+                    false
                 }
-                InputMessage::InterlockAction(state) => {
+                Either::First(InputMessage::InterlockAction(state)) => {
                     self.state.interlock = state;
+                    false
+                }
+                Either::First(InputMessage::MachineRun(state)) => {
+                    self.state.machine_run = state;
+                    false
+                }
+                Either::First(InputMessage::UpdateConfig(config)) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                    false
                 }
+                Either::Second(()) => {
+                    warn!("Session hold expired");
+                    self.state.session_hold_end = None;
+                    true
+                }
+            };
+
+            // Power is still on at the point the hold expires, which must not start a new hold
+            if !hold_expired {
+                self.update_session_hold();
             }
 
-            let power = if self.state.access_control == AccessControlState::Granted
-                && self.state.interlock != InterlockAction::Shutdown
+            let power = if self.state.interlock == InterlockAction::Shutdown {
+                DesiredMachinePower::Off
+            } else if self.state.access_control == AccessControlState::Granted
+                || self.state.session_hold_end.is_some()
             {
                 DesiredMachinePower::On
             } else {
                 DesiredMachinePower::Off
             };
             info!(
-                "access control {} + interlock {} = desired power {}",
-                self.state.access_control, self.state.interlock, power
+                "access control {} + interlock {} + session hold {} = desired power {}",
+                self.state.access_control, self.state.interlock, self.state.session_hold_end, power
             );
 
             self.state
@@ -62,6 +152,36 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
                     self.output_channel.send(OutputMessage::Power(v)).await;
                 })
                 .await;
+
+            self.state
+                .output_session_hold_end
+                .update_and_async(self.state.session_hold_end, async |v| {
+                    self.output_channel
+                        .send(OutputMessage::SessionHoldEnd(v))
+                        .await;
+                })
+                .await;
+        }
+    }
+}
+
+impl<'a> StateMachineRunner<'a> {
+    fn update_session_hold(&mut self) {
+        let powered = *self.state.output_power == Some(DesiredMachinePower::On);
+
+        if self.state.access_control == AccessControlState::Granted
+            || self.state.interlock == InterlockAction::Shutdown
+            || self.state.machine_run == MachineRun::Idle
+        {
+            // Nothing to hold power for, or power must be removed regardless
+            if self.state.session_hold_end.take().is_some() {
+                info!("Session hold ended");
+            }
+        } else if powered && self.state.session_hold_end.is_none() {
+            // Access has ended part way through a job, allow it to finish
+            let end = Instant::now() + self.state.config.session_hold_maximum_duration;
+            warn!("Access ended while running, holding power until {}", end);
+            self.state.session_hold_end = Some(end);
         }
     }
 }
@@ -0,0 +1,173 @@
+//! Optional recording of the inputs to a state machine, so that its decisions can be reproduced
+//! after the fact by replaying the inputs.
+//!
+//! Alongside the inputs a snapshot of the state is recorded from time to time, so that a replay can
+//! start from the state the state machine was in at the oldest input that is still held.
+
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Instant;
+use heapless::Deque;
+use serde::{Deserialize, Serialize};
+
+/// Something that records the inputs received by a state machine.
+pub trait InputRecorder<T, S>: Sync {
+    /// Records an input, `snapshot` gives the state of the state machine immediately before the
+    /// input is acted on and is only called when a snapshot is needed.
+    fn record(&self, input: &T, snapshot: &dyn Fn() -> S);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedInput<T> {
+    /// Sequence number of the input, incremented for every input recorded since boot.
+    pub index: u32,
+    /// Time at which the input was received by the state machine.
+    #[serde(with = "crate::serde_instant")]
+    pub time: Instant,
+    pub input: T,
+}
+
+/// The state of a state machine immediately before it acted on a recorded input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint<S> {
+    /// Index of the input that followed the snapshot.
+    pub index: u32,
+    /// Time at which the snapshot was taken.
+    #[serde(with = "crate::serde_instant")]
+    pub time: Instant,
+    pub snapshot: S,
+}
+
+/// Ring buffer of the most recent inputs to a state machine.
+///
+/// A checkpoint is taken every `N / 2` inputs. Once full, the inputs before the second most recent
+/// checkpoint are discarded, so that there is always a checkpoint at the oldest held input and
+/// between `N / 2` and `N` inputs are held.
+pub struct InputRecording<T, S, const N: usize> {
+    inner: CriticalSectionMutex<RefCell<Inner<T, S, N>>>,
+}
+
+struct Inner<T, S, const N: usize> {
+    inputs: Deque<RecordedInput<T>, N>,
+    checkpoints: Deque<Checkpoint<S>, 2>,
+    next_index: u32,
+}
+
+impl<T, S, const N: usize> Default for InputRecording<T, S, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, S, const N: usize> InputRecording<T, S, N> {
+    pub const fn new() -> Self {
+        Self {
+            inner: CriticalSectionMutex::new(RefCell::new(Inner {
+                inputs: Deque::new(),
+                checkpoints: Deque::new(),
+                next_index: 0,
+            })),
+        }
+    }
+
+    /// Index of the oldest input still held, any inputs before this have been evicted.
+    pub fn oldest_index(&self) -> u32 {
+        self.inner.lock(|inner| {
+            let inner = inner.borrow();
+            inner
+                .inputs
+                .front()
+                .map(|input| input.index)
+                .unwrap_or(inner.next_index)
+        })
+    }
+
+    /// Index that the next recorded input will be given.
+    pub fn next_index(&self) -> u32 {
+        self.inner.lock(|inner| inner.borrow().next_index)
+    }
+
+    /// Calls `f` for each held input with an index equal to or greater than `start_index`, oldest
+    /// first, until it returns false.
+    pub fn for_each_from(&self, start_index: u32, mut f: impl FnMut(&RecordedInput<T>) -> bool) {
+        self.inner.lock(|inner| {
+            for input in inner
+                .borrow()
+                .inputs
+                .iter()
+                .filter(|input| input.index >= start_index)
+            {
+                if !f(input) {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Calls `f` with the checkpoint at the oldest held input, if any inputs have been recorded.
+    pub fn with_oldest_checkpoint<R>(&self, f: impl FnOnce(Option<&Checkpoint<S>>) -> R) -> R {
+        self.inner
+            .lock(|inner| f(inner.borrow().checkpoints.front()))
+    }
+}
+
+impl<T, S, const N: usize> Inner<T, S, N> {
+    fn checkpoint_due(&self) -> bool {
+        let index = self.next_index;
+
+        // Recording an input when full evicts the oldest checkpoint, which may be the only one
+        (self.inputs.is_full() && self.checkpoints.len() < 2)
+            || self
+                .checkpoints
+                .back()
+                .is_none_or(|c| index.wrapping_sub(c.index) as usize >= N / 2)
+    }
+}
+
+impl<T: Clone + Send, S: Send, const N: usize> InputRecorder<T, S> for InputRecording<T, S, N> {
+    fn record(&self, input: &T, snapshot: &dyn Fn() -> S) {
+        let time = Instant::now();
+
+        // Only the state machine records inputs, so whether a checkpoint is due does not change
+        // while the snapshot is taken outside of the critical section
+        let snapshot = self
+            .inner
+            .lock(|inner| inner.borrow().checkpoint_due())
+            .then(snapshot);
+
+        self.inner.lock(|inner| {
+            let mut inner = inner.borrow_mut();
+            let index = inner.next_index;
+            inner.next_index = inner.next_index.wrapping_add(1);
+
+            if inner.inputs.is_full() {
+                inner.checkpoints.pop_front();
+
+                let keep_from = inner.checkpoints.front().map_or(index, |c| c.index);
+                while inner
+                    .inputs
+                    .front()
+                    .is_some_and(|input| input.index != keep_from)
+                {
+                    inner.inputs.pop_front();
+                }
+            }
+
+            if let Some(snapshot) = snapshot {
+                let checkpoint = Checkpoint {
+                    index,
+                    time,
+                    snapshot,
+                };
+                inner.checkpoints.push_back(checkpoint).ok();
+            }
+
+            let input = RecordedInput {
+                index,
+                time,
+                input: input.clone(),
+            };
+            inner.inputs.push_back(input).ok();
+        });
+    }
+}
@@ -0,0 +1,252 @@
+use defmt::info;
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant};
+use hoshiguma_api::{
+    Component, FumeExtractionFan, MachineRun, RuntimeCounters, Severity,
+    cooler::{CompressorState, CoolantPumpState},
+};
+use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
+use strum::{EnumCount, IntoEnumIterator};
+
+crate::state_machine!(InputMessage, OutputMessage, State, 8);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InputMessage {
+    /// Counters loaded from persistent storage at startup.
+    RestoreCounters(RuntimeCounters),
+    MachineRun(MachineRun),
+    CoolantPumpState(CoolantPumpState),
+    CompressorState(CompressorState),
+    FumeExtractionFan(FumeExtractionFan),
+    /// A component has been serviced.
+    ResetServiceCounter(Component),
+    UpdateConfig(Config),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OutputMessage {
+    /// Updated counters, to be persisted.
+    Counters(RuntimeCounters),
+    ServiceSeverity(Severity),
+}
+
+#[derive(Default)]
+pub struct State {
+    config: Config,
+
+    counters: RuntimeCounters,
+    /// When each component started running, or was last accounted for, indexed by component.
+    running_since: [Option<Instant>; Component::COUNT],
+    next_update: Option<Instant>,
+
+    output_service_severity: ObservedValue<Severity>,
+}
+
+impl State {
+    /// Adds the time each running component has run since it was last accounted for.
+    fn accumulate(&mut self, now: Instant) {
+        for component in Component::iter() {
+            if let Some(since) = &mut self.running_since[component as usize] {
+                let elapsed: core::time::Duration = (now - *since).into();
+                *since = now;
+
+                let counter = self.counters.get_mut(component);
+                counter.total += elapsed;
+                counter.since_service += elapsed;
+            }
+        }
+    }
+
+    /// Starts or stops counting for a component.
+    ///
+    /// Returns true if the component changed between running and not running.
+    fn set_running(&mut self, now: Instant, component: Component, running: bool) -> bool {
+        let since = &mut self.running_since[component as usize];
+        match (since.is_some(), running) {
+            (false, true) => {
+                *since = Some(now);
+                true
+            }
+            (true, false) => {
+                *since = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn any_running(&self) -> bool {
+        self.running_since.iter().any(Option::is_some)
+    }
+
+    fn service_severity(&self) -> Severity {
+        if Component::iter().any(|component| {
+            self.counters.get(component).since_service
+                >= self.config.service_interval(component).into()
+        }) {
+            Severity::Information
+        } else {
+            Severity::Normal
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Running time between services of the laser tube.
+    #[serde(with = "crate::serde_duration")]
+    pub laser_tube_service_interval: Duration,
+
+    /// Running time between services of the coolant pump.
+    #[serde(with = "crate::serde_duration")]
+    pub coolant_pump_service_interval: Duration,
+
+    /// Running time between services of the compressor.
+    #[serde(with = "crate::serde_duration")]
+    pub compressor_service_interval: Duration,
+
+    /// Running time between services of the extraction fan.
+    #[serde(with = "crate::serde_duration")]
+    pub extraction_fan_service_interval: Duration,
+
+    /// Interval at which counters are emitted (and therefore persisted) while anything is running.
+    #[serde(with = "crate::serde_duration")]
+    pub update_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        const HOUR: u64 = 60 * 60;
+
+        Self {
+            laser_tube_service_interval: Duration::from_secs(500 * HOUR),
+            coolant_pump_service_interval: Duration::from_secs(2000 * HOUR),
+            compressor_service_interval: Duration::from_secs(2000 * HOUR),
+            extraction_fan_service_interval: Duration::from_secs(1000 * HOUR),
+            update_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Config {
+    /// Running time between services of a component.
+    fn service_interval(&self, component: Component) -> Duration {
+        match component {
+            Component::LaserTube => self.laser_tube_service_interval,
+            Component::CoolantPump => self.coolant_pump_service_interval,
+            Component::Compressor => self.compressor_service_interval,
+            Component::ExtractionFan => self.extraction_fan_service_interval,
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub counters: RuntimeCounters,
+    /// Components that are currently running.
+    pub running: [bool; Component::COUNT],
+    #[serde(with = "crate::serde_instant::option")]
+    pub next_update: Option<Instant>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            counters: self.counters,
+            running: self.running_since.map(|since| since.is_some()),
+            next_update: self.next_update,
+        }
+    }
+}
+
+impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
+    async fn run(&mut self) -> ! {
+        loop {
+            let now =
+                match select(self.receive_input(), MaybeTimer::at(self.state.next_update)).await {
+                    Either::First(input) => {
+                        let now = Instant::now();
+                        self.state.accumulate(now);
+
+                        let changed = match input {
+                            InputMessage::RestoreCounters(counters) => {
+                                info!("Restored runtime counters: {}", counters);
+                                self.state.counters = counters;
+                                true
+                            }
+                            InputMessage::MachineRun(state) => self.state.set_running(
+                                now,
+                                Component::LaserTube,
+                                state == MachineRun::Running,
+                            ),
+                            InputMessage::CoolantPumpState(state) => self.state.set_running(
+                                now,
+                                Component::CoolantPump,
+                                state == CoolantPumpState::Run,
+                            ),
+                            InputMessage::CompressorState(state) => self.state.set_running(
+                                now,
+                                Component::Compressor,
+                                state == CompressorState::Run,
+                            ),
+                            InputMessage::FumeExtractionFan(state) => self.state.set_running(
+                                now,
+                                Component::ExtractionFan,
+                                state == FumeExtractionFan::Run,
+                            ),
+                            InputMessage::ResetServiceCounter(component) => {
+                                info!("Resetting service counter for {}", component);
+                                self.state.counters.get_mut(component).since_service =
+                                    core::time::Duration::ZERO;
+                                true
+                            }
+                            InputMessage::UpdateConfig(config) => {
+                                crate::ConfigurableState::configure(&mut self.state, config);
+                                true
+                            }
+                        };
+
+                        if !changed {
+                            continue;
+                        }
+                        now
+                    }
+                    Either::Second(()) => {
+                        let now = Instant::now();
+                        self.state.accumulate(now);
+                        now
+                    }
+                };
+
+            self.state.next_update = self
+                .state
+                .any_running()
+                .then_some(now + self.state.config.update_interval);
+
+            self.output_channel
+                .send(OutputMessage::Counters(self.state.counters))
+                .await;
+
+            let severity = self.state.service_severity();
+            self.state
+                .output_service_severity
+                .update_and_async(severity, async |v| {
+                    self.output_channel
+                        .send(OutputMessage::ServiceSeverity(v))
+                        .await;
+                })
+                .await;
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Serialisation of instants as microseconds since boot, for use with `#[serde(with = "...")]` in
+//! state snapshots.
+
+use embassy_time::Instant;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub(crate) fn serialize<S: Serializer>(
+    instant: &Instant,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(instant.as_micros())
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Instant, D::Error> {
+    u64::deserialize(deserializer).map(Instant::from_micros)
+}
+
+pub(crate) mod option {
+    use embassy_time::Instant;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        instant: &Option<Instant>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        instant
+            .map(|instant| instant.as_micros())
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Instant>, D::Error> {
+        Option::<u64>::deserialize(deserializer).map(|micros| micros.map(Instant::from_micros))
+    }
+}
+
+pub(crate) mod option_option {
+    use embassy_time::Instant;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        instant: &Option<Option<Instant>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        instant
+            .map(|instant| instant.map(|instant| instant.as_micros()))
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Option<Instant>>, D::Error> {
+        Option::<Option<u64>>::deserialize(deserializer)
+            .map(|micros| micros.map(|micros| micros.map(Instant::from_micros)))
+    }
+}
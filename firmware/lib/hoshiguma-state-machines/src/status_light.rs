@@ -1,17 +1,24 @@
 use defmt::debug;
-use embassy_time::{Duration, Ticker};
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant, Ticker};
 use hoshiguma_api::{
     AcBusPower, Interlock, MachineRun,
     rear_sensor_board::{LightPattern, StatusLightSettings},
 };
-use hoshiguma_common::changed::ObservedValue;
+use hoshiguma_common::{changed::ObservedValue, maybe_timer::MaybeTimer};
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 8);
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     AcBusPower(AcBusPower),
     MachineRun(MachineRun),
     Interlock(Interlock),
+    GracePeriodEnd(#[serde(with = "crate::serde_instant::option")] Option<Instant>),
+    /// Whether any automatic control is being overridden.
+    Maintenance(bool),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
@@ -20,9 +27,14 @@ pub enum OutputMessage {
 }
 
 pub struct State {
+    config: Config,
+
     power: AcBusPower,
     run: MachineRun,
     interlock: Interlock,
+    grace_period: bool,
+    maintenance: bool,
+    job_complete_until: Option<Instant>,
 
     output_settings: ObservedValue<StatusLightSettings>,
 }
@@ -30,68 +42,117 @@ pub struct State {
 impl Default for State {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+
             power: AcBusPower::Off,
             run: MachineRun::Idle,
             interlock: Interlock::OperationDenied,
+            grace_period: false,
+            maintenance: false,
+            job_complete_until: None,
 
             output_settings: ObservedValue::default(),
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Time for which the green lamp blinks after a job finishes.
+    #[serde(with = "crate::serde_duration")]
+    pub job_complete_indication_time: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            job_complete_indication_time: Duration::from_secs(10),
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub power: AcBusPower,
+    pub run: MachineRun,
+    pub interlock: Interlock,
+    pub grace_period: bool,
+    pub maintenance: bool,
+    #[serde(with = "crate::serde_instant::option")]
+    pub job_complete_until: Option<Instant>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            power: self.power,
+            run: self.run,
+            interlock: self.interlock,
+            grace_period: self.grace_period,
+            maintenance: self.maintenance,
+            job_complete_until: self.job_complete_until,
+        }
+    }
+}
+
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
-            match self.input_channel.receive().await {
-                InputMessage::AcBusPower(state) => {
+            match select(
+                self.receive_input(),
+                MaybeTimer::at(self.state.job_complete_until),
+            )
+            .await
+            {
+                Either::First(InputMessage::AcBusPower(state)) => {
                     if state == AcBusPower::On {
                         startup_sequence(self.output_channel).await;
+
+                        // Always follow the startup sequence with the current settings
+                        self.state.output_settings = ObservedValue::default();
                     }
 
                     self.state.power = state;
                 }
-                InputMessage::MachineRun(state) => {
+                Either::First(InputMessage::MachineRun(state)) => {
+                    self.state.job_complete_until = match (self.state.run, state) {
+                        (MachineRun::Running, MachineRun::Idle) => {
+                            Some(Instant::now() + self.state.config.job_complete_indication_time)
+                        }
+                        (_, MachineRun::Running) => None,
+                        _ => self.state.job_complete_until,
+                    };
                     self.state.run = state;
                 }
-                InputMessage::Interlock(state) => {
+                Either::First(InputMessage::Interlock(state)) => {
                     self.state.interlock = state;
                 }
+                Either::First(InputMessage::GracePeriodEnd(end)) => {
+                    self.state.grace_period = end.is_some();
+                }
+                Either::First(InputMessage::Maintenance(maintenance)) => {
+                    self.state.maintenance = maintenance;
+                }
+                Either::First(InputMessage::UpdateConfig(config)) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+                Either::Second(()) => {
+                    debug!("Job complete indication expired");
+                    self.state.job_complete_until = None;
+                }
             }
 
-            let settings = match self.state.power {
-                AcBusPower::On => StatusLightSettings {
-                    // Red lamp is lit when operation of the machine is denied
-                    red: if self.state.run == MachineRun::Running {
-                        match self.state.interlock {
-                            Interlock::OperationDenied => LightPattern::ON,
-                            _ => LightPattern::OFF,
-                        }
-                    } else {
-                        match self.state.interlock {
-                            Interlock::OperationDenied | Interlock::OperationPermittedUntilIdle => {
-                                LightPattern::ON
-                            }
-                            _ => LightPattern::OFF,
-                        }
-                    },
-                    // Amber light flashes when machine is running
-                    amber: match self.state.run {
-                        MachineRun::Idle => LightPattern::OFF,
-                        MachineRun::Running => LightPattern::BLINK_1HZ,
-                    },
-                    // Green lamp is lit when operation of the machine is permitted and will continue to be permitted
-                    green: match self.state.interlock {
-                        Interlock::OperationPermitted => LightPattern::ON,
-                        _ => LightPattern::OFF,
-                    },
-                },
-                // All lights off when AC bus is off
-                AcBusPower::Off => StatusLightSettings {
-                    red: LightPattern::OFF,
-                    amber: LightPattern::OFF,
-                    green: LightPattern::OFF,
-                },
-            };
+            let settings = self.state.settings();
             debug!("{}", settings);
 
             self.state
@@ -104,6 +165,75 @@ impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     }
 }
 
+impl State {
+    fn settings(&self) -> StatusLightSettings {
+        // A latched trip is shown even when the AC bus is off, as a trip is likely the reason for
+        // it being off
+        if self.interlock == Interlock::MachineProtected {
+            return StatusLightSettings {
+                red: LightPattern::BLINK_5HZ,
+                amber: LightPattern::OFF,
+                green: LightPattern::OFF,
+                buzzer: LightPattern::BLINK_1HZ,
+            };
+        }
+
+        match self.power {
+            AcBusPower::On => StatusLightSettings {
+                // Red lamp is lit when operation of the machine is denied
+                // and flashes while a job is running on borrowed time
+                red: if self.grace_period {
+                    LightPattern::BLINK_2HZ
+                } else if self.run == MachineRun::Running {
+                    match self.interlock {
+                        Interlock::OperationDenied => LightPattern::ON,
+                        _ => LightPattern::OFF,
+                    }
+                } else {
+                    match self.interlock {
+                        Interlock::OperationDenied | Interlock::OperationPermittedUntilIdle => {
+                            LightPattern::ON
+                        }
+                        _ => LightPattern::OFF,
+                    }
+                },
+                // Amber light flashes when machine is running or in maintenance mode
+                amber: match (self.run, self.maintenance) {
+                    (MachineRun::Running, _) => LightPattern::BLINK_1HZ,
+                    (MachineRun::Idle, true) => LightPattern::BLINK_1HZ,
+                    (MachineRun::Idle, false) => LightPattern::OFF,
+                },
+                // Green lamp blinks for a short time after a job finishes, alternates with amber in
+                // maintenance mode and is otherwise lit when operation of the machine is permitted
+                // and will continue to be permitted
+                green: if self.job_complete_until.is_some() {
+                    LightPattern::BLINK_2HZ
+                } else if self.maintenance && self.run == MachineRun::Idle {
+                    LightPattern::BLINK_1HZ_INVERTED
+                } else {
+                    match self.interlock {
+                        Interlock::OperationPermitted => LightPattern::ON,
+                        _ => LightPattern::OFF,
+                    }
+                },
+                // Buzzer chirps while a job is running on borrowed time
+                buzzer: if self.grace_period {
+                    LightPattern::PULSE
+                } else {
+                    LightPattern::OFF
+                },
+            },
+            // All lights off when AC bus is off
+            AcBusPower::Off => StatusLightSettings {
+                red: LightPattern::OFF,
+                amber: LightPattern::OFF,
+                green: LightPattern::OFF,
+                buzzer: LightPattern::OFF,
+            },
+        }
+    }
+}
+
 async fn startup_sequence<'a>(output_channel: OutputChannelSender<'a>) {
     let mut tick = Ticker::every(Duration::from_millis(200));
 
@@ -112,6 +242,7 @@ async fn startup_sequence<'a>(output_channel: OutputChannelSender<'a>) {
             red: LightPattern::ON,
             amber: LightPattern::OFF,
             green: LightPattern::OFF,
+            buzzer: LightPattern::OFF,
         }))
         .await;
 
@@ -122,6 +253,7 @@ async fn startup_sequence<'a>(output_channel: OutputChannelSender<'a>) {
             red: LightPattern::OFF,
             amber: LightPattern::ON,
             green: LightPattern::OFF,
+            buzzer: LightPattern::OFF,
         }))
         .await;
 
@@ -132,6 +264,7 @@ async fn startup_sequence<'a>(output_channel: OutputChannelSender<'a>) {
             red: LightPattern::OFF,
             amber: LightPattern::OFF,
             green: LightPattern::ON,
+            buzzer: LightPattern::OFF,
         }))
         .await;
 
@@ -142,6 +275,7 @@ async fn startup_sequence<'a>(output_channel: OutputChannelSender<'a>) {
             red: LightPattern::ON,
             amber: LightPattern::ON,
             green: LightPattern::ON,
+            buzzer: LightPattern::ON,
         }))
         .await;
 
@@ -0,0 +1,228 @@
+use defmt::{debug, warn};
+use heapless::{Deque, LinearMap};
+use hoshiguma_api::{TemperatureSensor, TemperatureSensorReading};
+use serde::{Deserialize, Serialize};
+
+crate::state_machine!(InputMessage, OutputMessage, State, 16);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InputMessage {
+    Temperature(TemperatureSensorReading),
+    UpdateConfig(Config),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OutputMessage {
+    /// A reading that passed the plausibility checks, smoothed by the median filter.
+    Temperature(TemperatureSensorReading),
+    /// Total number of readings from a sensor that have been rejected.
+    RejectedReadings(TemperatureSensor, u32),
+}
+
+#[derive(Default)]
+pub struct State {
+    config: Config,
+
+    sensors: LinearMap<TemperatureSensor, SensorFilter, MAX_SENSORS>,
+}
+
+/// Maximum number of sensors that can be filtered.
+const MAX_SENSORS: usize = 8;
+
+/// Number of accepted readings the median is taken over.
+const MEDIAN_WINDOW: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Largest change in temperature between consecutive readings that is considered plausible,
+    /// in degrees Celsius.
+    ///
+    /// A larger change is held back for one reading, it is rejected if the next reading does not
+    /// continue on from it.
+    pub maximum_step_change: f32,
+
+    /// Value reported by a DS18B20 that has reset and not yet completed a conversion.
+    pub power_on_reset_value: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            maximum_step_change: 10.0,
+            power_on_reset_value: 85.0,
+        }
+    }
+}
+
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SensorFilter {
+    /// Most recently accepted readings.
+    window: Deque<f32, MEDIAN_WINDOW>,
+    /// A reading that was an implausible step from the accepted readings, held back until the next
+    /// reading shows whether it was an outlier.
+    pending_step: Option<f32>,
+    rejected_readings: u32,
+}
+
+impl SensorFilter {
+    /// Returns the filtered temperature, or `None` if the reading was rejected.
+    fn filter(&mut self, config: &Config, temperature: f32) -> Option<f32> {
+        let last = self.window.back().copied();
+        let pending_step = self.pending_step.take();
+
+        // Where the temperature would be if it continued to change as it did between the last two
+        // accepted readings
+        let extrapolated = last
+            .zip(self.window.iter().rev().nth(1))
+            .map(|(last, previous)| 2.0 * last - previous);
+
+        let implausible_step = last
+            .is_some_and(|last| (temperature - last).abs() > config.maximum_step_change)
+            && extrapolated.is_none_or(|extrapolated| {
+                (temperature - extrapolated).abs() > config.maximum_step_change
+            });
+
+        // The reset value is only believable if it follows on from previous readings
+        if temperature == config.power_on_reset_value && (last.is_none() || implausible_step) {
+            return self.reject();
+        }
+
+        if let Some(last) = last
+            && implausible_step
+        {
+            // A step that the following reading continues on from is a genuine change, e.g. a fast
+            // rise in temperature, rather than a single bad reading
+            let sustained = pending_step.is_some_and(|pending| {
+                let direction = (pending - last).signum();
+                (temperature - last).signum() == direction
+                    && (temperature - pending) * direction >= -config.maximum_step_change
+            });
+
+            if !sustained {
+                self.pending_step = Some(temperature);
+                return self.reject();
+            }
+
+            // The temperature has really changed, start again from the new value, keeping the held
+            // back reading so that the direction of the change is known
+            self.window.clear();
+            if let Some(pending) = pending_step {
+                self.window.push_back(pending).unwrap();
+            }
+        }
+
+        if self.window.is_full() {
+            self.window.pop_front();
+        }
+        self.window.push_back(temperature).unwrap();
+
+        Some(self.median())
+    }
+
+    fn reject(&mut self) -> Option<f32> {
+        self.rejected_readings = self.rejected_readings.saturating_add(1);
+        None
+    }
+
+    fn median(&self) -> f32 {
+        let mut sorted = [0.0; MEDIAN_WINDOW];
+        let sorted = &mut sorted[..self.window.len()];
+        for (sorted, reading) in sorted.iter_mut().zip(self.window.iter()) {
+            *sorted = *reading;
+        }
+        sorted.sort_unstable_by(f32::total_cmp);
+        sorted[sorted.len() / 2]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub sensors: LinearMap<TemperatureSensor, SensorFilter, MAX_SENSORS>,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            sensors: self.sensors.clone(),
+        }
+    }
+}
+
+impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
+    async fn run(&mut self) -> ! {
+        loop {
+            match self.receive_input().await {
+                InputMessage::Temperature(reading) => {
+                    self.filter_reading(reading).await;
+                }
+                InputMessage::UpdateConfig(config) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> StateMachineRunner<'a> {
+    async fn filter_reading(&mut self, reading: TemperatureSensorReading) {
+        let sensors = &mut self.state.sensors;
+
+        // Failed readings are passed on as they are, so that the sensor is seen to have failed
+        let Ok(temperature) = reading.reading else {
+            self.output_channel
+                .send(OutputMessage::Temperature(reading))
+                .await;
+            return;
+        };
+
+        if !sensors.contains_key(&reading.sensor)
+            && sensors
+                .insert(reading.sensor, SensorFilter::default())
+                .is_err()
+        {
+            warn!(
+                "Too many temperature sensors, not filtering {}",
+                reading.sensor
+            );
+            self.output_channel
+                .send(OutputMessage::Temperature(reading))
+                .await;
+            return;
+        }
+
+        let filter = sensors.get_mut(&reading.sensor).unwrap();
+        match filter.filter(&self.state.config, temperature) {
+            Some(temperature) => {
+                debug!("{} filtered to {}", reading.sensor, temperature);
+                self.output_channel
+                    .send(OutputMessage::Temperature(TemperatureSensorReading {
+                        sensor: reading.sensor,
+                        reading: Ok(temperature),
+                    }))
+                    .await;
+            }
+            None => {
+                warn!(
+                    "Rejected reading of {} from {}",
+                    temperature, reading.sensor
+                );
+                self.output_channel
+                    .send(OutputMessage::RejectedReadings(
+                        reading.sensor,
+                        filter.rejected_readings,
+                    ))
+                    .await;
+            }
+        }
+    }
+}
@@ -1,26 +1,127 @@
 use defmt::{Format, debug, info, warn};
 use embassy_time::{Duration, Instant};
-use heapless::LinearMap;
-use hoshiguma_api::{Severity, TemperatureReading, TemperatureSensor, TemperatureSensorReading};
+use heapless::{Deque, LinearMap};
+use hoshiguma_api::{
+    MachineRun, Monitor, Severity, TemperatureReading, TemperatureSensor, TemperatureSensorReading,
+};
 use hoshiguma_common::changed::ObservedValue;
-use strum::{EnumCount, IntoEnumIterator};
+use serde::{Deserialize, Serialize};
 
 crate::state_machine!(InputMessage, OutputMessage, State, 16);
 
+// Configuration updates are rare, so the channel space taken by the thresholds they carry is
+// accepted in exchange for being able to replace them at runtime.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InputMessage {
     Temperature(TemperatureSensorReading),
+    MachineRun(MachineRun),
+    UpdateConfig(Config),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum OutputMessage {
     FunctionalSeverity(Severity),
-    ElectronicsTemperatureSeverity(Severity),
-    CoolantReservoirTemperatureSeverity(Severity),
+    TemperatureSeverity(Monitor, Severity),
+    RateOfRiseSeverity(Severity),
 }
 
-#[derive(Debug, Format, Clone, PartialEq)]
+/// Acceptable temperature range of a sensor, and the monitor that reports it.
+///
+/// Any threshold may be omitted if it does not apply to the sensor.
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemperatureThresholds {
+    pub monitor: Monitor,
+    pub low_critical: Option<f32>,
+    pub low_warning: Option<f32>,
+    pub high_warning: Option<f32>,
+    pub high_critical: Option<f32>,
+}
+
+impl TemperatureThresholds {
+    fn severity(&self, temperature: TemperatureReading) -> Severity {
+        let Ok(temperature) = temperature else {
+            warn!("Asked to check temperature of a sensor that failed to be read");
+            return Severity::Critical;
+        };
+
+        let below = |threshold: Option<f32>| threshold.is_some_and(|t| temperature <= t);
+        let above = |threshold: Option<f32>| threshold.is_some_and(|t| temperature >= t);
+
+        if below(self.low_critical) || above(self.high_critical) {
+            warn!(
+                "Temperature {} is outside critical thresholds for {}",
+                temperature, self.monitor
+            );
+            Severity::Critical
+        } else if below(self.low_warning) || above(self.high_warning) {
+            warn!(
+                "Temperature {} is outside warning thresholds for {}",
+                temperature, self.monitor
+            );
+            Severity::Warning
+        } else {
+            debug!("Temperature {} is normal", temperature);
+            Severity::Normal
+        }
+    }
+}
+
+/// Maximum number of sensors that can have temperature thresholds.
+pub const MAX_THRESHOLD_SENSORS: usize = 8;
+
+/// Thresholds used when none are configured.
+pub const DEFAULT_THRESHOLDS: &[(TemperatureSensor, TemperatureThresholds)] = &[
+    (
+        TemperatureSensor::OrchastratorPcb,
+        ELECTRONICS_TEMPERATURE_THRESHOLDS,
+    ),
+    (
+        TemperatureSensor::CoolerPcb,
+        ELECTRONICS_TEMPERATURE_THRESHOLDS,
+    ),
+    (
+        TemperatureSensor::CoolantReservoir,
+        TemperatureThresholds {
+            monitor: Monitor::CoolantReservoirTemperature,
+            // Risk of condensation on the tube and the coolant freezing
+            low_critical: Some(5.0),
+            low_warning: Some(10.0),
+            high_warning: Some(20.0),
+            high_critical: Some(25.0),
+        },
+    ),
+];
+
+const ELECTRONICS_TEMPERATURE_THRESHOLDS: TemperatureThresholds = TemperatureThresholds {
+    monitor: Monitor::ElectronicsTemperature,
+    low_critical: Some(0.0),
+    low_warning: Some(5.0),
+    high_warning: Some(35.0),
+    high_critical: Some(40.0),
+};
+
+/// Creates the state machine with a specific set of temperature thresholds, rather than
+/// `DEFAULT_THRESHOLDS`.
+pub fn new_with_thresholds<'a>(
+    input_channel: &'a InputChannel,
+    output_channel: &'a OutputChannel,
+    thresholds: &[(TemperatureSensor, TemperatureThresholds)],
+) -> (StateMachineRunner<'a>, StateMachineCommunicator<'a>) {
+    new_with_config(
+        input_channel,
+        output_channel,
+        Config {
+            thresholds: Config::thresholds_from(thresholds),
+            ..Default::default()
+        },
+    )
+}
+
+#[derive(Debug, Format, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TemperatureSensorDetails {
     reading: TemperatureReading,
+    #[serde(with = "crate::serde_instant")]
     last_good_reading: Instant,
 }
 
@@ -35,178 +136,357 @@ impl Default for TemperatureSensorDetails {
     }
 }
 
-pub type StateMap =
-    LinearMap<TemperatureSensor, TemperatureSensorDetails, { TemperatureSensor::COUNT - 1 }>;
+pub type StateMap = LinearMap<TemperatureSensor, TemperatureSensorDetails, MAX_THRESHOLD_SENSORS>;
+
+pub type ThresholdMap = LinearMap<TemperatureSensor, TemperatureThresholds, MAX_THRESHOLD_SENSORS>;
+
+/// Recent readings of a sensor used for fire detection.
+#[derive(Default)]
+struct RiseHistory {
+    samples: Deque<(Instant, f32), 32>,
+    /// Number of consecutive readings for which the rise has exceeded the threshold.
+    rising_readings: u8,
+}
+
+impl RiseHistory {
+    fn add_reading(&mut self, config: &Config, now: Instant, reading: TemperatureReading) {
+        let Ok(temperature) = reading else {
+            // A failed reading says nothing about the trend, start again
+            *self = Self::default();
+            return;
+        };
+
+        while let Some((time, _)) = self.samples.front()
+            && (now - *time > config.rate_of_rise_window || self.samples.is_full())
+        {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, temperature)).unwrap();
+
+        let lowest = self
+            .samples
+            .iter()
+            .map(|(_, t)| *t)
+            .fold(temperature, f32::min);
+
+        if temperature - lowest >= config.rate_of_rise_threshold {
+            self.rising_readings = self.rising_readings.saturating_add(1);
+        } else {
+            self.rising_readings = 0;
+        }
+    }
+
+    fn is_rising(&self, config: &Config) -> bool {
+        self.rising_readings >= config.rate_of_rise_confirmation_readings
+    }
+}
 
 pub struct State {
+    config: Config,
+
     sensors: StateMap,
+    rise_histories: LinearMap<TemperatureSensor, RiseHistory, 8>,
+    machine_run: MachineRun,
 
     output_functional_severity: ObservedValue<Severity>,
-    output_electronics_temperature_severity: ObservedValue<Severity>,
-    output_coolant_reservoir_temperature_severity: ObservedValue<Severity>,
+    output_temperature_severities:
+        LinearMap<Monitor, ObservedValue<Severity>, MAX_THRESHOLD_SENSORS>,
+    output_rate_of_rise_severity: ObservedValue<Severity>,
 }
 
 impl Default for State {
     fn default() -> Self {
-        let mut sensors = StateMap::new();
-        for sensor in TemperatureSensor::iter() {
-            if !matches!(sensor, TemperatureSensor::UnknownOnewire(_)) {
-                sensors
-                    .insert(sensor, TemperatureSensorDetails::default())
+        let mut state = Self {
+            config: Config::default(),
+
+            sensors: StateMap::new(),
+            rise_histories: LinearMap::new(),
+            machine_run: MachineRun::Idle,
+
+            output_functional_severity: ObservedValue::default(),
+            output_temperature_severities: LinearMap::new(),
+            output_rate_of_rise_severity: ObservedValue::default(),
+        };
+        state.track_threshold_sensors();
+        state
+    }
+}
+
+impl State {
+    /// Tracks the sensors and monitors that have thresholds in the current configuration, and
+    /// stops tracking any that no longer do.
+    fn track_threshold_sensors(&mut self) {
+        let thresholds = &self.config.thresholds;
+
+        self.sensors
+            .retain(|sensor, _| thresholds.contains_key(sensor));
+        self.output_temperature_severities
+            .retain(|monitor, _| thresholds.values().any(|t| t.monitor == *monitor));
+
+        for (sensor, sensor_thresholds) in thresholds.iter() {
+            // Every sensor with thresholds is expected to be reporting
+            if !self.sensors.contains_key(sensor) {
+                self.sensors
+                    .insert(*sensor, TemperatureSensorDetails::default())
                     .unwrap();
             }
+
+            let severities = &mut self.output_temperature_severities;
+            if !severities.contains_key(&sensor_thresholds.monitor) {
+                // Cannot be full, there are never more monitors than sensors
+                let _ = severities.insert(sensor_thresholds.monitor, ObservedValue::default());
+            }
+        }
+    }
+
+    fn rate_of_rise_severity(&self) -> Severity {
+        if self
+            .rise_histories
+            .values()
+            .any(|history| history.is_rising(&self.config))
+        {
+            match self.machine_run {
+                MachineRun::Running => Severity::Fatal,
+                MachineRun::Idle => Severity::Warning,
+            }
+        } else {
+            Severity::Normal
         }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Temperature thresholds of each sensor that is expected to be reporting.
+    pub thresholds: ThresholdMap,
+
+    #[serde(with = "crate::serde_duration")]
+    pub reading_age_warning_threshold: Duration,
+    #[serde(with = "crate::serde_duration")]
+    pub reading_age_critical_threshold: Duration,
+
+    /// Period over which the rise in temperature is measured for fire detection.
+    #[serde(with = "crate::serde_duration")]
+    pub rate_of_rise_window: Duration,
 
+    /// Rise in temperature within `rate_of_rise_window` that indicates a fire, in degrees Celsius.
+    pub rate_of_rise_threshold: f32,
+
+    /// Number of consecutive readings that must exceed the rate of rise threshold, so that a single
+    /// bad reading is not mistaken for a fire.
+    pub rate_of_rise_confirmation_readings: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
         Self {
-            sensors,
+            thresholds: Self::thresholds_from(DEFAULT_THRESHOLDS),
+            reading_age_warning_threshold: Duration::from_secs(10),
+            reading_age_critical_threshold: Duration::from_secs(30),
+            rate_of_rise_window: Duration::from_secs(30),
+            rate_of_rise_threshold: 6.0,
+            rate_of_rise_confirmation_readings: 2,
+        }
+    }
+}
 
-            output_functional_severity: ObservedValue::default(),
-            output_electronics_temperature_severity: ObservedValue::default(),
-            output_coolant_reservoir_temperature_severity: ObservedValue::default(),
+impl Config {
+    /// Builds a threshold map from a list of sensors and their thresholds.
+    pub fn thresholds_from(
+        thresholds: &[(TemperatureSensor, TemperatureThresholds)],
+    ) -> ThresholdMap {
+        let mut map = ThresholdMap::new();
+        for (sensor, sensor_thresholds) in thresholds {
+            if map.insert(*sensor, sensor_thresholds.clone()).is_err() {
+                warn!("Too many temperature thresholds, ignoring {}", sensor);
+            }
         }
+        map
     }
 }
 
-const READING_AGE_WARNING_THRESHOLD: Duration = Duration::from_secs(10);
-const READING_AGE_CRITICAL_THRESHOLD: Duration = Duration::from_secs(30);
+impl crate::ConfigurableState for State {
+    type Config = Config;
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+        self.track_threshold_sensors();
+    }
+}
+
+/// Sensors in the cutting area or extraction duct, which are monitored for a rapid rise in
+/// temperature.
+fn is_fire_detection_sensor(sensor: &TemperatureSensor) -> bool {
+    matches!(
+        sensor,
+        TemperatureSensor::ExtractionAirflow | TemperatureSensor::RearSensorBoardOnewire(_)
+    )
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub sensors: StateMap,
+    pub thresholds: ThresholdMap,
+    /// Number of consecutive readings for which each sensor has been rising quickly.
+    pub rising_readings: LinearMap<TemperatureSensor, u8, MAX_THRESHOLD_SENSORS>,
+    pub machine_run: MachineRun,
+}
+
+impl crate::StateSnapshot for State {
+    type Snapshot = Snapshot;
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            sensors: self.sensors.clone(),
+            thresholds: self.config.thresholds.clone(),
+            rising_readings: self
+                .rise_histories
+                .iter()
+                .map(|(sensor, history)| (*sensor, history.rising_readings))
+                .collect(),
+            machine_run: self.machine_run,
+        }
+    }
+}
 
 impl<'a> crate::StateMachineRun for StateMachineRunner<'a> {
     async fn run(&mut self) -> ! {
         loop {
-            // Receive a temperature reading and update the state for that sensor.
-            let InputMessage::Temperature(sensor_reading) = self.input_channel.receive().await;
+            match self.receive_input().await {
+                InputMessage::Temperature(sensor_reading) => {
+                    if is_fire_detection_sensor(&sensor_reading.sensor) {
+                        self.update_rise_history(sensor_reading);
+                    }
 
-            // Ignore unknown sensors
-            if matches!(sensor_reading.sensor, TemperatureSensor::UnknownOnewire(_)) {
-                continue;
+                    // Check absolute temperatures of the sensors that have thresholds
+                    if self.state.sensors.contains_key(&sensor_reading.sensor) {
+                        self.update_sensor(sensor_reading).await;
+                    }
+                }
+                InputMessage::MachineRun(state) => {
+                    self.state.machine_run = state;
+                }
+                InputMessage::UpdateConfig(config) => {
+                    crate::ConfigurableState::configure(&mut self.state, config);
+                }
             }
 
+            let severity = self.state.rate_of_rise_severity();
             self.state
-                .sensors
-                .insert(
-                    sensor_reading.sensor,
-                    TemperatureSensorDetails {
-                        reading: sensor_reading.reading,
-                        last_good_reading: Instant::now(),
-                    },
-                )
-                .unwrap();
-
-            // Check for any failed sensors.
-            let oldest_reading_time = self
-                .state
-                .sensors
-                .values()
-                .map(|details| details.last_good_reading)
-                .min()
-                .unwrap();
-            let oldest_reading_age = Instant::now() - oldest_reading_time;
-            let severity = if oldest_reading_age > READING_AGE_CRITICAL_THRESHOLD {
-                Severity::Critical
-            } else if oldest_reading_age > READING_AGE_WARNING_THRESHOLD {
-                Severity::Warning
-            } else {
-                Severity::Normal
-            };
-            info!(
-                "oldest reading age {}s = severity {}",
-                oldest_reading_age.as_secs(),
-                severity
-            );
-            self.state
-                .output_functional_severity
+                .output_rate_of_rise_severity
                 .update_and_async(severity, async |v| {
                     self.output_channel
-                        .send(OutputMessage::FunctionalSeverity(v))
+                        .send(OutputMessage::RateOfRiseSeverity(v))
                         .await;
                 })
                 .await;
-
-            // Check specific sensors are within acceptable ranges.
-            self.state
-                .output_electronics_temperature_severity
-                .update_and_async(
-                    check_temperatures(
-                        &self.state.sensors,
-                        &[
-                            TemperatureSensor::OrchastratorPcb,
-                            TemperatureSensor::CoolerPcb,
-                        ],
-                        35.0,
-                        40.0,
-                    ),
-                    async |v| {
-                        self.output_channel
-                            .send(OutputMessage::ElectronicsTemperatureSeverity(v))
-                            .await;
-                    },
-                )
-                .await;
-
-            self.state
-                .output_coolant_reservoir_temperature_severity
-                .update_and_async(
-                    check_temperatures(
-                        &self.state.sensors,
-                        &[TemperatureSensor::CoolantReservoir],
-                        20.0,
-                        25.0,
-                    ),
-                    async |v| {
-                        self.output_channel
-                            .send(OutputMessage::CoolantReservoirTemperatureSeverity(v))
-                            .await;
-                    },
-                )
-                .await;
         }
     }
 }
 
-fn check_temperatures(
-    readings: &StateMap,
-    sensors: &[TemperatureSensor],
-    warn: f32,
-    critical: f32,
-) -> Severity {
-    sensors
-        .iter()
-        .map(|sensor| {
-            temperature_to_severity(warn, critical, readings.get(sensor).unwrap().reading)
-        })
-        .fold(Severity::Normal, |acc, severity| {
-            if let Ok(severity) = severity {
-                acc.max(severity)
-            } else {
-                Severity::Critical
-            }
-        })
-}
+impl<'a> StateMachineRunner<'a> {
+    fn update_rise_history(&mut self, sensor_reading: TemperatureSensorReading) {
+        let State {
+            config,
+            rise_histories: histories,
+            ..
+        } = &mut self.state;
 
-fn temperature_to_severity(
-    warn: f32,
-    critical: f32,
-    temperature: TemperatureReading,
-) -> Result<Severity, ()> {
-    if let Ok(temperature) = temperature {
-        Ok(if temperature >= critical {
+        if !histories.contains_key(&sensor_reading.sensor)
+            && histories
+                .insert(sensor_reading.sensor, RiseHistory::default())
+                .is_err()
+        {
             warn!(
-                "Temperature {} is above critical threshold of {}",
-                temperature, critical
+                "Too many fire detection sensors, ignoring {}",
+                sensor_reading.sensor
             );
+            return;
+        }
+
+        if let Some(history) = histories.get_mut(&sensor_reading.sensor) {
+            history.add_reading(config, Instant::now(), sensor_reading.reading);
+            if history.is_rising(config) {
+                warn!("Rapid temperature rise on {}", sensor_reading.sensor);
+            }
+        }
+    }
+
+    async fn update_sensor(&mut self, sensor_reading: TemperatureSensorReading) {
+        self.state
+            .sensors
+            .insert(
+                sensor_reading.sensor,
+                TemperatureSensorDetails {
+                    reading: sensor_reading.reading,
+                    last_good_reading: Instant::now(),
+                },
+            )
+            .unwrap();
+
+        // Check for any failed sensors.
+        let oldest_reading_time = self
+            .state
+            .sensors
+            .values()
+            .map(|details| details.last_good_reading)
+            .min()
+            .unwrap();
+        let oldest_reading_age = Instant::now() - oldest_reading_time;
+        let severity = if oldest_reading_age > self.state.config.reading_age_critical_threshold {
             Severity::Critical
-        } else if temperature >= warn {
-            warn!(
-                "Temperature {} is above warning threshold of {}",
-                temperature, warn
-            );
+        } else if oldest_reading_age > self.state.config.reading_age_warning_threshold {
             Severity::Warning
         } else {
-            debug!("Temperature {} is normal", temperature);
             Severity::Normal
-        })
-    } else {
-        warn!("Asked to check temperature of a sensor that failed to be read");
-        Err(())
+        };
+        info!(
+            "oldest reading age {}s = severity {}",
+            oldest_reading_age.as_secs(),
+            severity
+        );
+        self.state
+            .output_functional_severity
+            .update_and_async(severity, async |v| {
+                self.output_channel
+                    .send(OutputMessage::FunctionalSeverity(v))
+                    .await;
+            })
+            .await;
+
+        // Check sensors are within acceptable ranges.
+        let State {
+            config,
+            sensors,
+            output_temperature_severities,
+            ..
+        } = &mut self.state;
+
+        for (monitor, output) in output_temperature_severities.iter_mut() {
+            let monitor = *monitor;
+            let severity = temperature_severity(&config.thresholds, sensors, monitor);
+
+            output
+                .update_and_async(severity, async |v| {
+                    self.output_channel
+                        .send(OutputMessage::TemperatureSeverity(monitor, v))
+                        .await;
+                })
+                .await;
+        }
     }
 }
+
+/// The highest severity of all sensors reported by a given monitor.
+fn temperature_severity(
+    thresholds: &ThresholdMap,
+    sensors: &StateMap,
+    monitor: Monitor,
+) -> Severity {
+    thresholds
+        .iter()
+        .filter(|(_, thresholds)| thresholds.monitor == monitor)
+        .map(|(sensor, thresholds)| thresholds.severity(sensors[sensor].reading))
+        .max()
+        .unwrap_or(Severity::Normal)
+}
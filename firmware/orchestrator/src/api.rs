@@ -1,30 +1,66 @@
-use crate::telemetry::queue_telemetry_data_point;
+use crate::{
+    devices::{
+        local::{laser_enable, machine_enable},
+        remote::observations::{
+            coolant_flow_rate_rx, coolant_return_rate_rx, extraction_airflow_rx,
+        },
+    },
+    logic::{
+        air_assist::air_assist_pump_rx,
+        extraction_airflow::reset_extraction_airflow_trend,
+        fume_extraction::{
+            fume_extraction_fan_rx, fume_extraction_mode_rx, request_fume_extraction_mode,
+        },
+        interlock::{interlock_action_rx, interlock_rx, monitor_states_rx},
+        machine_power::machine_power_rx,
+        runtime_counters::{reset_service_counter, runtime_counters_rx},
+    },
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
+};
 use defmt::warn;
 use embassy_net::Stack;
 use hoshiguma_api::{
-    Message,
+    FumeExtractionMode, Message,
     hmi::{AccessControlRawInput, AccessControlState, from_hmi},
+    orchestrator,
 };
 use hoshiguma_common::{network::message_handler_loop, telemetry::format_influx_line};
 
-crate::variable_watch!(access_control_raw_input, AccessControlRawInput, 2);
-crate::variable_watch!(access_control_state, AccessControlState, 1);
+crate::variable_watch!(access_control_raw_input, AccessControlRawInput, 3);
+crate::variable_watch!(access_control_state, AccessControlState, 2);
 
 #[embassy_executor::task]
 pub(super) async fn task(stack: Stack<'static>) {
     #[cfg(feature = "trace")]
     crate::trace::name_task("api").await;
 
+    let mut fume_extraction_mode_rx = fume_extraction_mode_rx();
+    let mut runtime_counters_rx = runtime_counters_rx();
+    let mut monitor_states_rx = monitor_states_rx();
+    let mut interlock_rx = interlock_rx();
+    let mut interlock_action_rx = interlock_action_rx();
+    let mut machine_power_rx = machine_power_rx();
+    let mut air_assist_pump_rx = air_assist_pump_rx();
+    let mut fume_extraction_fan_rx = fume_extraction_fan_rx();
+    let mut coolant_flow_rate_rx = coolant_flow_rate_rx();
+    let mut coolant_return_rate_rx = coolant_return_rate_rx();
+    let mut extraction_airflow_rx = extraction_airflow_rx();
+    let mut access_control_raw_input_rx = access_control_raw_input_rx();
+    let mut access_control_state_rx = access_control_state_rx();
+
     message_handler_loop(stack, 0, async |mut message| {
         let response = if let Ok(state) =
             message.payload::<from_hmi::request::NotifyAccessControlInputChanged>()
         {
             ACCESS_CONTROL_RAW_INPUT.sender().send(state.0);
 
-            queue_telemetry_data_point(format_influx_line(
-                format_args!("access_control_raw_input value=\"{}\"", state.0),
-                crate::wall_time::now(),
-            ));
+            queue_telemetry_data_point(
+                TelemetryPriority::State,
+                format_influx_line(
+                    format_args!("access_control_raw_input value=\"{}\"", state.0),
+                    crate::wall_time::now(),
+                ),
+            );
 
             Message::new(&from_hmi::response::AckAccessControlInputChanged(state.0)).ok()
         } else if let Ok(state) =
@@ -32,10 +68,13 @@ pub(super) async fn task(stack: Stack<'static>) {
         {
             ACCESS_CONTROL_STATE.sender().send(state.0);
 
-            queue_telemetry_data_point(format_influx_line(
-                format_args!("access_control_state value=\"{}\"", state.0),
-                crate::wall_time::now(),
-            ));
+            queue_telemetry_data_point(
+                TelemetryPriority::State,
+                format_influx_line(
+                    format_args!("access_control_state value=\"{}\"", state.0),
+                    crate::wall_time::now(),
+                ),
+            );
 
             Message::new(&from_hmi::response::AckAccessControlStateChanged(state.0)).ok()
         } else if message
@@ -43,13 +82,174 @@ pub(super) async fn task(stack: Stack<'static>) {
             .is_ok()
         {
             if let Some(time) = crate::wall_time::now() {
-                queue_telemetry_data_point(format_influx_line(
-                    format_args!("last_panel_interaction_time value={}", time.timestamp()),
-                    crate::wall_time::now(),
-                ));
+                queue_telemetry_data_point(
+                    TelemetryPriority::State,
+                    format_influx_line(
+                        format_args!("last_panel_interaction_time value={}", time.timestamp()),
+                        crate::wall_time::now(),
+                    ),
+                );
             }
 
             Message::new(&from_hmi::response::AckPanelInteraction).ok()
+        } else if message
+            .payload::<from_hmi::request::ToggleFumeExtractionMode>()
+            .is_ok()
+        {
+            let mode = match fume_extraction_mode_rx.try_get() {
+                Some(FumeExtractionMode::OverrideRun) => FumeExtractionMode::Automatic,
+                _ => FumeExtractionMode::OverrideRun,
+            };
+            request_fume_extraction_mode(mode).await;
+
+            Message::new(&from_hmi::response::FumeExtractionModeChanged(mode)).ok()
+        } else if let Ok(request) = message.payload::<orchestrator::request::GetInterlockEventLog>()
+        {
+            Message::new(&orchestrator::response::InterlockEventLog(
+                crate::interlock_log::page(request.start_index),
+            ))
+            .ok()
+        } else if let Ok(request) =
+            message.payload::<orchestrator::request::SetFumeExtractionMode>()
+        {
+            request_fume_extraction_mode(request.0).await;
+
+            Message::new(&orchestrator::response::FumeExtractionMode(request.0)).ok()
+        } else if message
+            .payload::<orchestrator::request::GetRecentJobs>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::RecentJobs(crate::job_log::recent())).ok()
+        } else if message
+            .payload::<orchestrator::request::GetRuntimeCounters>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::RuntimeCounters(
+                runtime_counters_rx.try_get().unwrap_or_default(),
+            ))
+            .ok()
+        } else if let Ok(request) = message.payload::<orchestrator::request::ResetServiceCounter>()
+        {
+            reset_service_counter(request.0).await;
+
+            Message::new(&orchestrator::response::ServiceCounterReset(request.0)).ok()
+        } else if message
+            .payload::<orchestrator::request::ResetExtractionAirflowTrend>()
+            .is_ok()
+        {
+            reset_extraction_airflow_trend();
+
+            Message::new(&orchestrator::response::ExtractionAirflowTrendReset).ok()
+        } else if let Ok(request) =
+            message.payload::<orchestrator::request::GetStateMachineSnapshot>()
+        {
+            Message::new(&orchestrator::response::StateMachineSnapshot(
+                crate::state_snapshot::get(request.0).await,
+            ))
+            .ok()
+        } else if let Ok(request) =
+            message.payload::<orchestrator::request::GetInputRecordingCheckpoint>()
+        {
+            Message::new(&orchestrator::response::InputRecordingCheckpoint(
+                crate::input_recording::checkpoint(request.0),
+            ))
+            .ok()
+        } else if let Ok(request) = message.payload::<orchestrator::request::GetInputRecording>() {
+            Message::new(&orchestrator::response::InputRecording(
+                crate::input_recording::page(request.machine, request.start_index),
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetMonitorStates>()
+            .is_ok()
+        {
+            let states = monitor_states_rx
+                .try_get()
+                .map(|states| states.iter().map(|(m, s)| (*m, *s)).collect())
+                .unwrap_or_default();
+
+            Message::new(&orchestrator::response::MonitorStates(states)).ok()
+        } else if message
+            .payload::<orchestrator::request::GetInterlockState>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::InterlockState(
+                orchestrator::InterlockState {
+                    interlock: interlock_rx.try_get(),
+                    action: interlock_action_rx.try_get(),
+                },
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetMachinePower>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::MachinePower(
+                machine_power_rx.try_get(),
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetRelayOutputs>()
+            .is_ok()
+        {
+            let action = interlock_action_rx.try_get();
+
+            Message::new(&orchestrator::response::RelayOutputs(
+                orchestrator::RelayOutputs {
+                    machine_power: machine_power_rx.try_get(),
+                    machine_enable: action.map(machine_enable::setting),
+                    laser_enable: action.map(laser_enable::setting),
+                    air_assist_pump: air_assist_pump_rx.try_get(),
+                    fume_extraction_fan: fume_extraction_fan_rx.try_get(),
+                },
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetLatestTemperatures>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::LatestTemperatures(
+                crate::logic::temperature_filter::latest(),
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetCoolantRates>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::CoolantRates(
+                orchestrator::CoolantRates {
+                    flow: coolant_flow_rate_rx.try_get(),
+                    ret: coolant_return_rate_rx.try_get(),
+                },
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetExtractionAirflow>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::ExtractionAirflow(
+                extraction_airflow_rx.try_get(),
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetAccessControl>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::AccessControl(
+                orchestrator::AccessControl {
+                    raw_input: access_control_raw_input_rx.try_get(),
+                    state: access_control_state_rx.try_get(),
+                },
+            ))
+            .ok()
+        } else if message
+            .payload::<orchestrator::request::GetWallTimeSyncStatus>()
+            .is_ok()
+        {
+            Message::new(&orchestrator::response::WallTimeSyncStatus(
+                crate::wall_time::sync_status(),
+            ))
+            .ok()
         } else {
             None
         };
@@ -1,6 +1,8 @@
 use crate::{
-    AcBusPowerDetectResources, input_change_detector::InputChangeDetector,
-    logic::interlock::update_monitor_severity, telemetry::queue_telemetry_data_point,
+    AcBusPowerDetectResources,
+    input_change_detector::InputChangeDetector,
+    logic::interlock::update_monitor_severity,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Input, Level, Pull};
 use embassy_time::{Duration, Timer};
@@ -37,10 +39,13 @@ pub(crate) async fn task(r: AcBusPowerDetectResources) {
         )
         .await;
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("ac_bus_power value=\"{state}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::State,
+            format_influx_line(
+                format_args!("ac_bus_power value=\"{state}\""),
+                crate::wall_time::now(),
+            ),
+        );
 
         if state == AcBusPower::On {
             // Wait a while before sending state, allows 24V bus to stabalise and
@@ -1,6 +1,7 @@
 use crate::{
-    AirAssistDemandDetectResources, input_change_detector::InputChangeDetector,
-    telemetry::queue_telemetry_data_point,
+    AirAssistDemandDetectResources,
+    input_change_detector::InputChangeDetector,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Input, Level, Pull};
 use embassy_time::Duration;
@@ -28,10 +29,13 @@ pub(crate) async fn task(r: AirAssistDemandDetectResources) {
             Level::High => AirAssistDemand::Demand,
         };
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("air_assist_demand value=\"{state}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::State,
+            format_influx_line(
+                format_args!("air_assist_demand value=\"{state}\""),
+                crate::wall_time::now(),
+            ),
+        );
 
         tx.send(state);
     }
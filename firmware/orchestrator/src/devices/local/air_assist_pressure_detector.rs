@@ -0,0 +1,42 @@
+use crate::{
+    AirAssistPressureDetectResources,
+    input_change_detector::InputChangeDetector,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
+};
+use embassy_rp::gpio::{Input, Level, Pull};
+use embassy_time::Duration;
+use hoshiguma_api::AirAssistPressure;
+use hoshiguma_common::telemetry::format_influx_line;
+
+crate::variable_watch!(air_assist_pressure, AirAssistPressure, 1);
+
+#[embassy_executor::task]
+pub(crate) async fn task(r: AirAssistPressureDetectResources) {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("air assist pressure detect").await;
+
+    let pin = Input::new(r.detect, Pull::Down);
+    let mut input =
+        InputChangeDetector::new(pin, Duration::from_millis(100), Duration::from_millis(100));
+
+    let tx = AIR_ASSIST_PRESSURE.sender();
+
+    loop {
+        let state = input.wait_for_change().await;
+
+        let state = match state {
+            Level::Low => AirAssistPressure::Low,
+            Level::High => AirAssistPressure::Normal,
+        };
+
+        queue_telemetry_data_point(
+            TelemetryPriority::State,
+            format_influx_line(
+                format_args!("air_assist_pressure value=\"{state}\""),
+                crate::wall_time::now(),
+            ),
+        );
+
+        tx.send(state);
+    }
+}
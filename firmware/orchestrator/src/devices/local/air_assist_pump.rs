@@ -1,6 +1,7 @@
 use crate::{
-    AirAssistPumpResources, logic::air_assist::air_assist_pump_rx,
-    telemetry::queue_telemetry_data_point,
+    AirAssistPumpResources,
+    logic::air_assist::air_assist_pump_rx,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Level, Output};
 use hoshiguma_api::AirAssistPump;
@@ -17,10 +18,13 @@ pub(crate) async fn task(r: AirAssistPumpResources) {
     loop {
         let setting = rx.changed().await;
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("air_assist_pump value=\"{setting}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::State,
+            format_influx_line(
+                format_args!("air_assist_pump value=\"{setting}\""),
+                crate::wall_time::now(),
+            ),
+        );
 
         let level = match setting {
             AirAssistPump::Idle => Level::Low,
@@ -1,12 +1,16 @@
 use crate::{
-    DoorsDetectResources, input_change_detector::InputChangeDetector,
-    logic::interlock::update_monitor_severity, telemetry::queue_telemetry_data_point,
+    DoorsDetectResources,
+    input_change_detector::InputChangeDetector,
+    logic::interlock::update_monitor_severity,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Input, Level, Pull};
 use embassy_time::Duration;
 use hoshiguma_api::{Doors, Monitor, Severity};
 use hoshiguma_common::telemetry::format_influx_line;
 
+crate::variable_watch!(doors, Doors, 1);
+
 #[embassy_executor::task]
 pub(crate) async fn task(r: DoorsDetectResources) {
     #[cfg(feature = "trace")]
@@ -16,6 +20,8 @@ pub(crate) async fn task(r: DoorsDetectResources) {
     let mut input =
         InputChangeDetector::new(pin, Duration::from_millis(0), Duration::from_millis(50));
 
+    let tx = DOORS.sender();
+
     loop {
         let state = input.wait_for_change().await;
 
@@ -33,9 +39,14 @@ pub(crate) async fn task(r: DoorsDetectResources) {
         )
         .await;
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("doors value=\"{state}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::State,
+            format_influx_line(
+                format_args!("doors value=\"{state}\""),
+                crate::wall_time::now(),
+            ),
+        );
+
+        tx.send(state);
     }
 }
@@ -1,6 +1,7 @@
 use crate::{
-    FumeExtractionFanResources, logic::fume_extraction::fume_extraction_fan_rx,
-    telemetry::queue_telemetry_data_point,
+    FumeExtractionFanResources,
+    logic::fume_extraction::fume_extraction_fan_rx,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Level, Output};
 use hoshiguma_api::FumeExtractionFan;
@@ -17,10 +18,13 @@ pub(crate) async fn task(r: FumeExtractionFanResources) {
     loop {
         let setting = rx.changed().await;
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("fume_extraction_fan value=\"{setting}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::State,
+            format_influx_line(
+                format_args!("fume_extraction_fan value=\"{setting}\""),
+                crate::wall_time::now(),
+            ),
+        );
 
         let level = match setting {
             FumeExtractionFan::Idle => Level::Low,
@@ -1,6 +1,7 @@
 use crate::{
-    LaserEnableResources, logic::interlock::interlock_action_rx,
-    telemetry::queue_telemetry_data_point,
+    LaserEnableResources,
+    logic::interlock::interlock_action_rx,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Level, Output};
 use hoshiguma_api::{InterlockAction, LaserEnable};
@@ -32,6 +33,14 @@ impl LaserEnableOutput {
     }
 }
 
+/// The setting of the laser enable relay for a given interlock action.
+pub(crate) fn setting(action: InterlockAction) -> LaserEnable {
+    match action {
+        InterlockAction::Normal => LaserEnable::Enable,
+        _ => LaserEnable::Inhibit,
+    }
+}
+
 #[embassy_executor::task]
 pub(crate) async fn task(r: LaserEnableResources) {
     #[cfg(feature = "trace")]
@@ -41,17 +50,15 @@ pub(crate) async fn task(r: LaserEnableResources) {
     let mut rx = interlock_action_rx();
 
     loop {
-        let interlock = rx.changed().await;
-
-        let setting = match interlock {
-            InterlockAction::Normal => LaserEnable::Enable,
-            _ => LaserEnable::Inhibit,
-        };
+        let setting = setting(rx.changed().await);
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("laser_enable value=\"{setting}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::Event,
+            format_influx_line(
+                format_args!("laser_enable value=\"{setting}\""),
+                crate::wall_time::now(),
+            ),
+        );
 
         output.set(setting);
     }
@@ -1,6 +1,7 @@
 use crate::{
-    MachinePowerResources, logic::machine_power::machine_power_rx,
-    telemetry::queue_telemetry_data_point,
+    MachinePowerResources,
+    logic::machine_power::machine_power_rx,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Level, Output};
 use hoshiguma_api::DesiredMachinePower;
@@ -17,10 +18,13 @@ pub(crate) async fn task(r: MachinePowerResources) {
     loop {
         let setting = rx.changed().await;
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("machine_power value=\"{setting}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::Event,
+            format_influx_line(
+                format_args!("machine_power value=\"{setting}\""),
+                crate::wall_time::now(),
+            ),
+        );
 
         let level = match setting {
             DesiredMachinePower::Off => Level::Low,
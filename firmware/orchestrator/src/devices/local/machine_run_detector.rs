@@ -1,13 +1,14 @@
 use crate::{
-    MachineRunDetectResources, input_change_detector::InputChangeDetector,
-    telemetry::queue_telemetry_data_point,
+    MachineRunDetectResources,
+    input_change_detector::InputChangeDetector,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_rp::gpio::{Input, Level, Pull};
 use embassy_time::Duration;
 use hoshiguma_api::MachineRun;
 use hoshiguma_common::telemetry::format_influx_line;
 
-crate::variable_watch!(machine_run, MachineRun, 4);
+crate::variable_watch!(machine_run, MachineRun, 9);
 
 #[embassy_executor::task]
 pub(crate) async fn task(r: MachineRunDetectResources) {
@@ -28,10 +29,13 @@ pub(crate) async fn task(r: MachineRunDetectResources) {
             Level::High => MachineRun::Running,
         };
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("machine_run value=\"{state}\""),
-            crate::wall_time::now(),
-        ));
+        queue_telemetry_data_point(
+            TelemetryPriority::State,
+            format_influx_line(
+                format_args!("machine_run value=\"{state}\""),
+                crate::wall_time::now(),
+            ),
+        );
 
         tx.send(state);
     }
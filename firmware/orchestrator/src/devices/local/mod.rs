@@ -1,5 +1,6 @@
 pub(crate) mod ac_bus_power_detector;
 pub(crate) mod air_assist_demand_detector;
+pub(crate) mod air_assist_pressure_detector;
 pub(crate) mod air_assist_pump;
 pub(crate) mod doors_detector;
 pub(crate) mod fume_extraction_fan;
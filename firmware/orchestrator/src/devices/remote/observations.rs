@@ -2,7 +2,7 @@ use crate::{
     devices::temperature::{
         TEMPERATURE_SENSOR_READING, onewire_sensor_to_named_temperature_sensor,
     },
-    telemetry::queue_telemetry_data_point,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use defmt::{info, warn};
 use embassy_futures::select::{Either, select};
@@ -10,13 +10,16 @@ use embassy_net::Stack;
 use embassy_time::{Duration, Ticker};
 use hoshiguma_api::{
     API_PORT, AirflowSensorMeasurement, COOLER_IP_ADDRESS, REAR_SENSOR_BOARD_IP_ADDRESS,
-    cooler::CoolantRate,
+    TemperatureSensor, TemperatureSensorReading,
+    cooler::{CoolantRate, CoolantVolume},
 };
 use hoshiguma_common::{network::send_request, telemetry::format_influx_line};
 
-crate::variable_watch!(coolant_flow_rate, CoolantRate, 1);
-crate::variable_watch!(coolant_return_rate, CoolantRate, 1);
-crate::variable_watch!(extraction_airflow, AirflowSensorMeasurement, 1);
+crate::variable_watch!(coolant_flow_rate, CoolantRate, 3);
+crate::variable_watch!(coolant_return_rate, CoolantRate, 2);
+crate::variable_watch!(coolant_flow_volume, CoolantVolume, 1);
+crate::variable_watch!(coolant_return_volume, CoolantVolume, 1);
+crate::variable_watch!(extraction_airflow, AirflowSensorMeasurement, 4);
 
 const COOLANT_FLOW_PULSES_PER_LITRE: f64 = 400.0;
 const COOLANT_RETURN_PULSES_PER_LITRE: f64 = 230.0;
@@ -50,14 +53,17 @@ pub(crate) async fn task(stack: Stack<'static>) {
                     let rate = state.into_rate(COOLANT_FLOW_PULSES_PER_LITRE);
                     COOLANT_FLOW_RATE.sender().send(rate);
 
-                    queue_telemetry_data_point(format_influx_line(
-                        format_args!(
-                            "coolant_flow_rate value={},raw_pulses={}",
-                            rate.into_inner(),
-                            state.pulses()
+                    queue_telemetry_data_point(
+                        TelemetryPriority::Sample,
+                        format_influx_line(
+                            format_args!(
+                                "coolant_flow_rate value={},raw_pulses={}",
+                                rate.into_inner(),
+                                state.pulses()
+                            ),
+                            crate::wall_time::now(),
                         ),
-                        crate::wall_time::now(),
-                    ));
+                    );
                 }
 
                 // Coolant return rate
@@ -74,14 +80,48 @@ pub(crate) async fn task(stack: Stack<'static>) {
                     let rate = state.into_rate(COOLANT_RETURN_PULSES_PER_LITRE);
                     COOLANT_RETURN_RATE.sender().send(rate);
 
-                    queue_telemetry_data_point(format_influx_line(
-                        format_args!(
-                            "coolant_return_rate value={},raw_pulses={}",
-                            rate.into_inner(),
-                            state.pulses()
+                    queue_telemetry_data_point(
+                        TelemetryPriority::Sample,
+                        format_influx_line(
+                            format_args!(
+                                "coolant_return_rate value={},raw_pulses={}",
+                                rate.into_inner(),
+                                state.pulses()
+                            ),
+                            crate::wall_time::now(),
                         ),
-                        crate::wall_time::now(),
-                    ));
+                    );
+                }
+
+                // Coolant flow volume
+                if let Ok(response) = send_request(
+                    stack,
+                    COOLER_IP_ADDRESS,
+                    API_PORT,
+                    5,
+                    &hoshiguma_api::cooler::request::GetCoolantFlowPulses,
+                )
+                .await
+                    && let Ok(pulses) = response.0
+                {
+                    let volume = CoolantVolume::from_pulses(pulses, COOLANT_FLOW_PULSES_PER_LITRE);
+                    COOLANT_FLOW_VOLUME.sender().send(volume);
+                }
+
+                // Coolant return volume
+                if let Ok(response) = send_request(
+                    stack,
+                    COOLER_IP_ADDRESS,
+                    API_PORT,
+                    5,
+                    &hoshiguma_api::cooler::request::GetCoolantReturnPulses,
+                )
+                .await
+                    && let Ok(pulses) = response.0
+                {
+                    let volume =
+                        CoolantVolume::from_pulses(pulses, COOLANT_RETURN_PULSES_PER_LITRE);
+                    COOLANT_RETURN_VOLUME.sender().send(volume);
                 }
 
                 // Fume extraction suction
@@ -97,14 +137,24 @@ pub(crate) async fn task(stack: Stack<'static>) {
                 {
                     EXTRACTION_AIRFLOW.sender().send(state);
 
+                    temperature_pub
+                        .publish(TemperatureSensorReading {
+                            sensor: TemperatureSensor::ExtractionAirflow,
+                            reading: state.map(|state| state.temperature),
+                        })
+                        .await;
+
                     if let Ok(state) = state {
-                        queue_telemetry_data_point(format_influx_line(
-                            format_args!(
-                                "extraction_airflow_suction value={},temperature={}",
-                                state.differential_pressure, state.temperature,
+                        queue_telemetry_data_point(
+                            TelemetryPriority::Sample,
+                            format_influx_line(
+                                format_args!(
+                                    "extraction_airflow_suction value={},temperature={}",
+                                    state.differential_pressure, state.temperature,
+                                ),
+                                crate::wall_time::now(),
                             ),
-                            crate::wall_time::now(),
-                        ));
+                        );
                     }
                 }
             }
@@ -149,7 +199,15 @@ pub(crate) async fn task(stack: Stack<'static>) {
                     Ok(response) => {
                         if let Ok(temperatures) = response.0 {
                             for reading in temperatures.into_iter() {
-                                let reading = onewire_sensor_to_named_temperature_sensor(reading);
+                                let mut reading =
+                                    onewire_sensor_to_named_temperature_sensor(reading);
+
+                                // Unnamed sensors on the rear sensor board are in the cutting area
+                                if let TemperatureSensor::UnknownOnewire(address) = reading.sensor {
+                                    reading.sensor =
+                                        TemperatureSensor::RearSensorBoardOnewire(address);
+                                }
+
                                 temperature_pub.publish(reading).await;
                             }
                         } else {
@@ -3,7 +3,7 @@ use crate::{
         cooling::{compressor_rx, coolant_pump_rx, radiator_fan_rx},
         status_light::status_light_rx,
     },
-    telemetry::queue_telemetry_data_point,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use defmt::{debug, info};
 use embassy_futures::select::{Either5, select5};
@@ -63,28 +63,37 @@ pub(crate) async fn task(stack: Stack<'static>) {
                 if let Ok(Some(response)) = cooler_pump.reconcile().await
                     && let Ok(state) = response.0
                 {
-                    queue_telemetry_data_point(format_influx_line(
-                        format_args!("coolant_pump value=\"{state}\""),
-                        crate::wall_time::now(),
-                    ));
+                    queue_telemetry_data_point(
+                        TelemetryPriority::State,
+                        format_influx_line(
+                            format_args!("coolant_pump value=\"{state}\""),
+                            crate::wall_time::now(),
+                        ),
+                    );
                 }
 
                 if let Ok(Some(response)) = cooler_radiator_fan.reconcile().await
                     && let Ok(state) = response.0
                 {
-                    queue_telemetry_data_point(format_influx_line(
-                        format_args!("radiator_fan value=\"{state}\""),
-                        crate::wall_time::now(),
-                    ));
+                    queue_telemetry_data_point(
+                        TelemetryPriority::State,
+                        format_influx_line(
+                            format_args!("radiator_fan value=\"{state}\""),
+                            crate::wall_time::now(),
+                        ),
+                    );
                 }
 
                 if let Ok(Some(response)) = cooler_compressor.reconcile().await
                     && let Ok(state) = response.0
                 {
-                    queue_telemetry_data_point(format_influx_line(
-                        format_args!("compressor value=\"{state}\""),
-                        crate::wall_time::now(),
-                    ));
+                    queue_telemetry_data_point(
+                        TelemetryPriority::State,
+                        format_influx_line(
+                            format_args!("compressor value=\"{state}\""),
+                            crate::wall_time::now(),
+                        ),
+                    );
                 }
 
                 let _ = status_light.reconcile().await;
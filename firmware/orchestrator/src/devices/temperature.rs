@@ -6,7 +6,7 @@ pub(crate) static TEMPERATURE_SENSOR_READING: PubSubChannel<
     CriticalSectionRawMutex,
     TemperatureSensorReading,
     16,
-    2,
+    1,
     2,
 > = PubSubChannel::new();
 
@@ -0,0 +1,121 @@
+//! Recordings of the inputs to selected state machines.
+//!
+//! Retrievable via the API so that the decisions made by a state machine can be reproduced after
+//! the fact by replaying its inputs on a host, starting from the checkpoint at the oldest held input.
+//!
+//! Only the cooling and interlock state machines are recorded, these are the ones that make safety
+//! decisions (stopping the machine and protecting the compressor) that may need to be explained
+//! after the fact. The other state machines either derive their outputs directly from their latest
+//! inputs or only affect telemetry and the HMI, and recording them all would not fit in RAM.
+
+use defmt::warn;
+use embassy_time::Instant;
+use heapless::Vec;
+use hoshiguma_api::orchestrator::{
+    INPUT_RECORDING_PAGE_SIZE, InputRecordingPage, RECORDED_INPUT_CAPACITY, RecordedInput,
+    RecordingCheckpoint, STATE_MACHINE_SNAPSHOT_CAPACITY, StateMachine,
+};
+use hoshiguma_state_machines::{cooling, interlock, recording::InputRecording};
+use serde::Serialize;
+
+/// Number of inputs retained for each state machine, once full the oldest inputs are discarded.
+const CAPACITY: usize = 64;
+
+pub(crate) static COOLING: InputRecording<cooling::InputMessage, cooling::Snapshot, CAPACITY> =
+    InputRecording::new();
+pub(crate) static INTERLOCK: InputRecording<
+    interlock::InputMessage,
+    interlock::Snapshot,
+    CAPACITY,
+> = InputRecording::new();
+
+/// Gets up to a page of the recorded inputs of a state machine, starting at the input with the
+/// given index.
+///
+/// Returns `None` if the inputs to the state machine are not recorded.
+pub(crate) fn page(machine: StateMachine, start_index: u32) -> Option<InputRecordingPage> {
+    match machine {
+        StateMachine::Cooling => Some(page_of(machine, &COOLING, start_index)),
+        StateMachine::Interlock => Some(page_of(machine, &INTERLOCK, start_index)),
+        _ => None,
+    }
+}
+
+/// Gets the checkpoint at the oldest held input of a state machine.
+///
+/// Returns `None` if the inputs to the state machine are not recorded or no inputs have been
+/// recorded yet.
+pub(crate) fn checkpoint(machine: StateMachine) -> Option<RecordingCheckpoint> {
+    match machine {
+        StateMachine::Cooling => checkpoint_of(machine, &COOLING),
+        StateMachine::Interlock => checkpoint_of(machine, &INTERLOCK),
+        _ => None,
+    }
+}
+
+fn checkpoint_of<T, S: Serialize, const N: usize>(
+    machine: StateMachine,
+    recording: &InputRecording<T, S, N>,
+) -> Option<RecordingCheckpoint> {
+    recording.with_oldest_checkpoint(|checkpoint| {
+        checkpoint.map(|checkpoint| {
+            let mut buffer = [0u8; STATE_MACHINE_SNAPSHOT_CAPACITY];
+            let snapshot = match postcard::to_slice(&checkpoint.snapshot, &mut buffer) {
+                Ok(data) => {
+                    Some(Vec::from_slice(data).expect("buffer should match the snapshot capacity"))
+                }
+                Err(_) => {
+                    warn!(
+                        "Checkpoint {} of {} state machine is too large",
+                        checkpoint.index, machine
+                    );
+                    None
+                }
+            };
+
+            RecordingCheckpoint {
+                index: checkpoint.index,
+                uptime: checkpoint.time.duration_since(Instant::MIN).into(),
+                snapshot,
+            }
+        })
+    })
+}
+
+fn page_of<T: Serialize, S, const N: usize>(
+    machine: StateMachine,
+    recording: &InputRecording<T, S, N>,
+    start_index: u32,
+) -> InputRecordingPage {
+    let mut inputs = Vec::<_, INPUT_RECORDING_PAGE_SIZE>::new();
+
+    recording.for_each_from(start_index, |recorded| {
+        let mut buffer = [0u8; RECORDED_INPUT_CAPACITY];
+        let input = match postcard::to_slice(&recorded.input, &mut buffer) {
+            Ok(data) => {
+                Some(Vec::from_slice(data).expect("buffer should match the input capacity"))
+            }
+            Err(_) => {
+                warn!(
+                    "Recorded input {} to {} state machine is too large",
+                    recorded.index, machine
+                );
+                None
+            }
+        };
+
+        inputs
+            .push(RecordedInput {
+                index: recorded.index,
+                uptime: recorded.time.duration_since(Instant::MIN).into(),
+                input,
+            })
+            .is_ok()
+    });
+
+    InputRecordingPage {
+        inputs,
+        oldest_index: recording.oldest_index(),
+        next_index: recording.next_index(),
+    }
+}
@@ -0,0 +1,75 @@
+//! Fixed size, in-memory log of interlock related events.
+//!
+//! Retained independently of telemetry so that the sequence of events leading up to an interlock
+//! trip can be retrieved via the API, even if the telemetry bridge was unavailable at the time.
+
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Instant;
+use heapless::Deque;
+use hoshiguma_api::orchestrator::{
+    INTERLOCK_EVENT_LOG_PAGE_SIZE, InterlockEvent, InterlockEventKind, InterlockEventLogPage,
+};
+
+/// Number of events retained, once full the oldest events are discarded.
+const CAPACITY: usize = 128;
+
+struct InterlockLog {
+    events: Deque<InterlockEvent, CAPACITY>,
+    next_index: u32,
+}
+
+static LOG: CriticalSectionMutex<RefCell<InterlockLog>> =
+    CriticalSectionMutex::new(RefCell::new(InterlockLog {
+        events: Deque::new(),
+        next_index: 0,
+    }));
+
+pub(crate) fn record(kind: InterlockEventKind) {
+    let event = InterlockEvent {
+        index: 0,
+        uptime: Instant::now().duration_since(Instant::MIN).into(),
+        wall_time: crate::wall_time::now(),
+        kind,
+    };
+
+    LOG.lock(|log| {
+        let mut log = log.borrow_mut();
+
+        let event = InterlockEvent {
+            index: log.next_index,
+            ..event
+        };
+        log.next_index = log.next_index.wrapping_add(1);
+
+        if log.events.is_full() {
+            log.events.pop_front();
+        }
+        log.events.push_back(event).unwrap();
+    });
+}
+
+/// Gets up to a page of events, starting at the event with the given index.
+pub(crate) fn page(start_index: u32) -> InterlockEventLogPage {
+    LOG.lock(|log| {
+        let log = log.borrow();
+
+        let oldest_index = log
+            .events
+            .front()
+            .map(|e| e.index)
+            .unwrap_or(log.next_index);
+
+        InterlockEventLogPage {
+            events: log
+                .events
+                .iter()
+                .filter(|e| e.index >= start_index)
+                .take(INTERLOCK_EVENT_LOG_PAGE_SIZE)
+                .cloned()
+                .collect(),
+            oldest_index,
+            next_index: log.next_index,
+        }
+    })
+}
@@ -0,0 +1,56 @@
+//! Fixed size, in-memory log of the most recently completed jobs.
+
+use chrono::TimeDelta;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use heapless::{Deque, Vec};
+use hoshiguma_api::orchestrator::{JobRecord, RECENT_JOBS_COUNT};
+use hoshiguma_state_machines::job_tracking::JobSummary;
+
+struct JobLog {
+    jobs: Deque<JobRecord, RECENT_JOBS_COUNT>,
+    next_index: u32,
+}
+
+static LOG: CriticalSectionMutex<RefCell<JobLog>> =
+    CriticalSectionMutex::new(RefCell::new(JobLog {
+        jobs: Deque::new(),
+        next_index: 0,
+    }));
+
+/// Records a job that has just ended, returning the record that was stored.
+pub(crate) fn record(summary: JobSummary) -> JobRecord {
+    let duration: core::time::Duration = summary.duration.into();
+
+    let end = crate::wall_time::now();
+    let start = end.and_then(|end| Some(end - TimeDelta::from_std(duration).ok()?));
+
+    LOG.lock(|log| {
+        let mut log = log.borrow_mut();
+
+        let job = JobRecord {
+            index: log.next_index,
+            start,
+            end,
+            duration,
+            peak_coolant_reservoir_temperature: summary.peak_coolant_reservoir_temperature,
+            minimum_extraction_airflow: summary.minimum_extraction_airflow,
+            interlock_events: summary.interlock_events,
+            door_openings: summary.door_openings,
+            end_reason: summary.end_reason,
+        };
+        log.next_index = log.next_index.wrapping_add(1);
+
+        if log.jobs.is_full() {
+            log.jobs.pop_front();
+        }
+        log.jobs.push_back(job.clone()).unwrap();
+
+        job
+    })
+}
+
+/// Gets the most recently completed jobs, oldest first.
+pub(crate) fn recent() -> Vec<JobRecord, RECENT_JOBS_COUNT> {
+    LOG.lock(|log| log.borrow().jobs.iter().cloned().collect())
+}
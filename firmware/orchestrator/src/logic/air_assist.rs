@@ -1,9 +1,13 @@
-use crate::devices::local::{
-    ac_bus_power_detector::ac_bus_power_rx, air_assist_demand_detector::air_assist_demand_rx,
+use crate::{
+    devices::local::{
+        ac_bus_power_detector::ac_bus_power_rx, air_assist_demand_detector::air_assist_demand_rx,
+        air_assist_pressure_detector::air_assist_pressure_rx,
+    },
+    logic::interlock::update_monitor_severity,
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either3, select3};
-use hoshiguma_api::AirAssistPump;
+use embassy_futures::select::{Either4, select4};
+use hoshiguma_api::{AirAssistPump, Monitor};
 use hoshiguma_state_machines::{
     StateMachineRun,
     air_assist::{
@@ -37,32 +41,42 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
 
     let mut ac_bus_power_rx = ac_bus_power_rx();
     let mut air_assist_demand_rx = air_assist_demand_rx();
+    let mut air_assist_pressure_rx = air_assist_pressure_rx();
 
     let pump_tx = AIR_ASSIST_PUMP.sender();
 
     loop {
-        match select3(
+        match select4(
             communicator.receive_output(),
             ac_bus_power_rx.changed(),
             air_assist_demand_rx.changed(),
+            air_assist_pressure_rx.changed(),
         )
         .await
         {
-            Either3::First(OutputMessage::AirAssistPump(state)) => {
+            Either4::First(OutputMessage::AirAssistPump(state)) => {
                 pump_tx.send(state);
             }
-            Either3::Second(state) => {
+            Either4::First(OutputMessage::PressureSeverity(severity)) => {
+                update_monitor_severity(Monitor::AirAssistPressure, severity).await;
+            }
+            Either4::Second(state) => {
                 communicator
                     .send_input(InputMessage::AcBusPower(state))
                     .await;
             }
-            Either3::Third(state) => {
+            Either4::Third(state) => {
                 communicator
                     .send_input(InputMessage::AirAssistDemand(state))
                     .await;
             }
+            Either4::Fourth(state) => {
+                communicator
+                    .send_input(InputMessage::AirAssistPressure(state))
+                    .await;
+            }
         }
     }
 }
 
-crate::variable_watch!(air_assist_pump, AirAssistPump, 1);
+crate::variable_watch!(air_assist_pump, AirAssistPump, 2);
@@ -1,10 +1,15 @@
 use crate::{
-    devices::remote::observations::{coolant_flow_rate_rx, coolant_return_rate_rx},
-    logic::interlock::update_monitor_severity,
+    devices::remote::observations::{
+        coolant_flow_rate_rx, coolant_flow_volume_rx, coolant_return_rate_rx,
+        coolant_return_volume_rx,
+    },
+    logic::{cooling::coolant_pump_rx, interlock::update_monitor_severity},
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either3, select3};
+use embassy_futures::select::{Either6, select6};
 use hoshiguma_api::Monitor;
+use hoshiguma_common::telemetry::format_influx_line;
 use hoshiguma_state_machines::{
     StateMachineRun,
     coolant_rate::{
@@ -38,31 +43,64 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
 
     let mut rate_flow_rx = coolant_flow_rate_rx();
     let mut rate_return_rx = coolant_return_rate_rx();
+    let mut volume_flow_rx = coolant_flow_volume_rx();
+    let mut volume_return_rx = coolant_return_volume_rx();
+    let mut coolant_pump_rx = coolant_pump_rx();
 
     loop {
-        match select3(
+        match select6(
             communicator.receive_output(),
             rate_flow_rx.changed(),
             rate_return_rx.changed(),
+            volume_flow_rx.changed(),
+            volume_return_rx.changed(),
+            coolant_pump_rx.changed(),
         )
         .await
         {
-            Either3::First(OutputMessage::RateSeverity(severity)) => {
+            Either6::First(OutputMessage::RateSeverity(severity)) => {
                 update_monitor_severity(Monitor::CoolantRate, severity).await;
             }
-            Either3::First(OutputMessage::SymmetrySeverity(severity)) => {
+            Either6::First(OutputMessage::SymmetrySeverity(severity)) => {
                 update_monitor_severity(Monitor::CoolantRateSymmetry, severity).await;
             }
-            Either3::Second(reading) => {
+            Either6::First(OutputMessage::LeakSeverity(severity)) => {
+                update_monitor_severity(Monitor::CoolantLeak, severity).await;
+            }
+            Either6::First(OutputMessage::LeakedVolume(volume)) => {
+                queue_telemetry_data_point(
+                    TelemetryPriority::Sample,
+                    format_influx_line(
+                        format_args!("coolant_leaked_volume value={}", volume.into_inner()),
+                        crate::wall_time::now(),
+                    ),
+                );
+            }
+            Either6::Second(reading) => {
                 communicator
                     .send_input(InputMessage::RateFlow(reading))
                     .await;
             }
-            Either3::Third(reading) => {
+            Either6::Third(reading) => {
                 communicator
                     .send_input(InputMessage::RateReturn(reading))
                     .await;
             }
+            Either6::Fourth(volume) => {
+                communicator
+                    .send_input(InputMessage::VolumeFlow(volume))
+                    .await;
+            }
+            Either6::Fifth(volume) => {
+                communicator
+                    .send_input(InputMessage::VolumeReturn(volume))
+                    .await;
+            }
+            Either6::Sixth(state) => {
+                communicator
+                    .send_input(InputMessage::CoolantPumpState(state))
+                    .await;
+            }
         }
     }
 }
@@ -1,10 +1,22 @@
-use crate::devices::{
-    local::ac_bus_power_detector::ac_bus_power_rx, temperature::TEMPERATURE_SENSOR_READING,
+use crate::{
+    devices::{
+        local::{ac_bus_power_detector::ac_bus_power_rx, machine_run_detector::machine_run_rx},
+        remote::observations::coolant_flow_rate_rx,
+    },
+    logic::{
+        interlock::update_monitor_severity, machine_power::machine_power_rx,
+        temperature_filter::FILTERED_TEMPERATURE_SENSOR_READING,
+    },
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either3, select3};
+use embassy_futures::select::{Either6, select6};
 use embassy_sync::pubsub::WaitResult;
-use hoshiguma_api::cooler::{CompressorState, CoolantPumpState, RadiatorFanState};
+use hoshiguma_api::{
+    AcBusPower, DesiredMachinePower, EmergencyStop, Monitor,
+    cooler::{CompressorState, CoolantPumpState, RadiatorFanState},
+};
+use hoshiguma_common::{changed::ObservedValue, telemetry::format_influx_line};
 use hoshiguma_state_machines::{
     StateMachineRun,
     cooling::{
@@ -17,7 +29,8 @@ static SM_INPUT: InputChannel = InputChannel::new();
 static SM_OUTPUT: OutputChannel = OutputChannel::new();
 
 pub(crate) fn init(spawner: Spawner) {
-    let (runner, communicator) = hoshiguma_state_machines::cooling::new(&SM_INPUT, &SM_OUTPUT);
+    let (mut runner, communicator) = hoshiguma_state_machines::cooling::new(&SM_INPUT, &SM_OUTPUT);
+    runner.record_inputs(&crate::input_recording::COOLING);
 
     spawner.spawn(runner_task(runner).unwrap());
     spawner.spawn(communication_task(communicator).unwrap());
@@ -37,46 +50,113 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
     crate::trace::name_task("cooling sm comm").await;
 
     let mut ac_bus_power_rx = ac_bus_power_rx();
-    let mut temperature_rx = TEMPERATURE_SENSOR_READING.subscriber().unwrap();
+    let mut temperature_rx = FILTERED_TEMPERATURE_SENSOR_READING.subscriber().unwrap();
+    let mut machine_power_rx = machine_power_rx();
+    let mut coolant_flow_rate_rx = coolant_flow_rate_rx();
+    let mut machine_run_rx = machine_run_rx();
+
+    let mut desired_machine_power = DesiredMachinePower::Off;
+    let mut emergency_stop = ObservedValue::new(EmergencyStop::Released);
 
     let coolant_pump_tx = COOLANT_PUMP.sender();
     let radiator_fan_tx = RADIATOR_FAN.sender();
     let compressor_tx = COMPRESSOR.sender();
 
     loop {
-        match select3(
+        match select6(
             communicator.receive_output(),
             ac_bus_power_rx.changed(),
             temperature_rx.next_message(),
+            machine_power_rx.changed(),
+            coolant_flow_rate_rx.changed(),
+            machine_run_rx.changed(),
         )
         .await
         {
-            Either3::First(OutputMessage::CoolantPump(state)) => {
+            Either6::First(OutputMessage::CoolantPump(state)) => {
                 coolant_pump_tx.send(state);
             }
-            Either3::First(OutputMessage::RadiatorFan(state)) => {
+            Either6::First(OutputMessage::RadiatorFan(state)) => {
                 radiator_fan_tx.send(state);
             }
-            Either3::First(OutputMessage::Compressor(state)) => {
+            Either6::First(OutputMessage::Compressor(state)) => {
                 compressor_tx.send(state);
             }
-            Either3::Second(state) => {
+            Either6::First(OutputMessage::CompressorLockoutSeverity(severity)) => {
+                update_monitor_severity(Monitor::CompressorLockout, severity).await;
+            }
+            Either6::Second(state) => {
+                // There is no dedicated E-stop input, however the E-stop is the only thing that
+                // removes AC bus power while the machine power contactor is being held on.
+                let e_stop = if state == AcBusPower::Off
+                    && desired_machine_power == DesiredMachinePower::On
+                {
+                    EmergencyStop::Pressed
+                } else {
+                    EmergencyStop::Released
+                };
+                update_emergency_stop(&mut communicator, &mut emergency_stop, e_stop).await;
+
                 communicator
                     .send_input(InputMessage::AcBusPower(state))
                     .await;
             }
-            Either3::Third(WaitResult::Message(reading)) => {
+            Either6::Third(WaitResult::Message(reading)) => {
                 communicator
                     .send_input(InputMessage::Temperature(reading))
                     .await;
             }
-            Either3::Third(WaitResult::Lagged(n)) => {
+            Either6::Third(WaitResult::Lagged(n)) => {
                 panic!("subscriber lagged, lost {} messages", n);
             }
+            Either6::Fourth(state) => {
+                desired_machine_power = state;
+
+                if state == DesiredMachinePower::Off {
+                    update_emergency_stop(
+                        &mut communicator,
+                        &mut emergency_stop,
+                        EmergencyStop::Released,
+                    )
+                    .await;
+                }
+            }
+            Either6::Fifth(rate) => {
+                communicator
+                    .send_input(InputMessage::CoolantFlowRate(rate))
+                    .await;
+            }
+            Either6::Sixth(state) => {
+                communicator
+                    .send_input(InputMessage::MachineRun(state))
+                    .await;
+            }
         }
     }
 }
 
-crate::variable_watch!(coolant_pump, CoolantPumpState, 1);
+async fn update_emergency_stop(
+    communicator: &mut StateMachineCommunicator<'static>,
+    emergency_stop: &mut ObservedValue<EmergencyStop>,
+    state: EmergencyStop,
+) {
+    emergency_stop
+        .update_and_async(state, async |v| {
+            queue_telemetry_data_point(
+                TelemetryPriority::Event,
+                format_influx_line(
+                    format_args!("emergency_stop value=\"{v}\""),
+                    crate::wall_time::now(),
+                ),
+            );
+
+            communicator
+                .send_input(InputMessage::EmergencyStop(v))
+                .await;
+        })
+        .await;
+}
+
+crate::variable_watch!(coolant_pump, CoolantPumpState, 3);
 crate::variable_watch!(radiator_fan, RadiatorFanState, 1);
-crate::variable_watch!(compressor, CompressorState, 1);
+crate::variable_watch!(compressor, CompressorState, 2);
@@ -1,10 +1,15 @@
 use crate::{
     devices::remote::observations::extraction_airflow_rx,
     logic::{fume_extraction::fume_extraction_fan_rx, interlock::update_monitor_severity},
+    storage::StorageKey,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
+use defmt::warn;
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either3, select3};
-use hoshiguma_api::Monitor;
+use embassy_futures::select::{Either4, select4};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use hoshiguma_api::{ExtractionAirflowTrend, Monitor};
+use hoshiguma_common::telemetry::format_influx_line;
 use hoshiguma_state_machines::{
     StateMachineRun,
     extraction_airflow::{
@@ -40,30 +45,83 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
     let mut fume_extraction_fan_rx = fume_extraction_fan_rx();
     let mut fume_extraction_airflow_rx = extraction_airflow_rx();
 
+    // If a stored trend exists but could not be loaded it is left untouched, rather than being
+    // overwritten by a trend starting from scratch.
+    let mut persist =
+        match crate::storage::load::<ExtractionAirflowTrend>(StorageKey::ExtractionAirflowTrend)
+            .await
+        {
+            Ok(trend) => {
+                communicator
+                    .send_input(InputMessage::RestoreTrend(trend.unwrap_or_default()))
+                    .await;
+                true
+            }
+            Err(e) => {
+                warn!(
+                    "Extraction airflow trend will not be saved, failed to load it: {}",
+                    e
+                );
+                false
+            }
+        };
+
     loop {
-        match select3(
+        match select4(
             communicator.receive_output(),
             fume_extraction_fan_rx.changed(),
             fume_extraction_airflow_rx.changed(),
+            RESET_TREND_REQUEST.wait(),
         )
         .await
         {
-            Either3::First(OutputMessage::FunctionalSeverity(severity)) => {
+            Either4::First(OutputMessage::FunctionalSeverity(severity)) => {
                 update_monitor_severity(Monitor::ExtractionAirflowSensorFunctional, severity).await;
             }
-            Either3::First(OutputMessage::AirflowSeverity(severity)) => {
+            Either4::First(OutputMessage::AirflowSeverity(severity)) => {
                 update_monitor_severity(Monitor::ExtractionAirflow, severity).await;
             }
-            Either3::Second(state) => {
+            Either4::First(OutputMessage::Trend(trend)) => {
+                if persist {
+                    crate::storage::save(StorageKey::ExtractionAirflowTrend, &trend).await;
+                }
+
+                queue_telemetry_data_point(
+                    TelemetryPriority::Sample,
+                    format_influx_line(
+                        format_args!(
+                            "extraction_airflow_trend average={},reference={},runs={}",
+                            trend.average, trend.reference, trend.runs
+                        ),
+                        crate::wall_time::now(),
+                    ),
+                );
+            }
+            Either4::First(OutputMessage::FilterSeverity(severity)) => {
+                update_monitor_severity(Monitor::ExtractionFilter, severity).await;
+            }
+            Either4::Second(state) => {
                 communicator
                     .send_input(InputMessage::FumeExtractionFan(state))
                     .await;
             }
-            Either3::Third(reading) => {
+            Either4::Third(reading) => {
                 communicator
                     .send_input(InputMessage::ExtractionAirflowReading(reading))
                     .await;
             }
+            Either4::Fourth(()) => {
+                // Replacing the stored trend is intended, even if it could not be loaded
+                persist = true;
+                communicator.send_input(InputMessage::ResetTrend).await;
+            }
         }
     }
 }
+
+static RESET_TREND_REQUEST: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Record that the extraction filter has been replaced, e.g. from the API.
+pub(crate) fn reset_extraction_airflow_trend() {
+    RESET_TREND_REQUEST.signal(());
+}
@@ -1,9 +1,15 @@
-use crate::devices::local::{
-    ac_bus_power_detector::ac_bus_power_rx, machine_run_detector::machine_run_rx,
+use crate::{
+    devices::{
+        local::{ac_bus_power_detector::ac_bus_power_rx, machine_run_detector::machine_run_rx},
+        remote::observations::extraction_airflow_rx,
+    },
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either3, select3};
-use hoshiguma_api::FumeExtractionFan;
+use embassy_futures::select::{Either5, select5};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use hoshiguma_api::{FumeExtractionFan, FumeExtractionMode};
+use hoshiguma_common::telemetry::format_influx_line;
 use hoshiguma_state_machines::{
     StateMachineRun,
     fume_extraction::{
@@ -38,32 +44,63 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
 
     let mut ac_bus_power_rx = ac_bus_power_rx();
     let mut machine_run_rx = machine_run_rx();
+    let mut extraction_airflow_rx = extraction_airflow_rx();
 
     let fume_extraction_fan_tx = FUME_EXTRACTION_FAN.sender();
+    let fume_extraction_mode_tx = FUME_EXTRACTION_MODE.sender();
 
     loop {
-        match select3(
+        match select5(
             communicator.receive_output(),
             ac_bus_power_rx.changed(),
             machine_run_rx.changed(),
+            MODE_REQUEST_CH.receive(),
+            extraction_airflow_rx.changed(),
         )
         .await
         {
-            Either3::First(OutputMessage::ExtractionFan(state)) => {
+            Either5::First(OutputMessage::ExtractionFan(state)) => {
                 fume_extraction_fan_tx.send(state);
             }
-            Either3::Second(state) => {
+            Either5::First(OutputMessage::Mode(mode)) => {
+                fume_extraction_mode_tx.send(mode);
+
+                queue_telemetry_data_point(
+                    TelemetryPriority::State,
+                    format_influx_line(
+                        format_args!("fume_extraction_mode value=\"{}\"", mode),
+                        crate::wall_time::now(),
+                    ),
+                );
+            }
+            Either5::Second(state) => {
                 communicator
                     .send_input(InputMessage::AcBusPower(state))
                     .await;
             }
-            Either3::Third(state) => {
+            Either5::Third(state) => {
                 communicator
                     .send_input(InputMessage::MachineRun(state))
                     .await;
             }
+            Either5::Fourth(mode) => {
+                communicator.send_input(InputMessage::Mode(mode)).await;
+            }
+            Either5::Fifth(reading) => {
+                communicator
+                    .send_input(InputMessage::ExtractionAirflowReading(reading))
+                    .await;
+            }
         }
     }
 }
 
-crate::variable_watch!(fume_extraction_fan, FumeExtractionFan, 2);
+static MODE_REQUEST_CH: Channel<CriticalSectionRawMutex, FumeExtractionMode, 2> = Channel::new();
+
+/// Request a change of fume extraction mode, e.g. from the HMI or the API.
+pub(crate) async fn request_fume_extraction_mode(mode: FumeExtractionMode) {
+    MODE_REQUEST_CH.send(mode).await;
+}
+
+crate::variable_watch!(fume_extraction_fan, FumeExtractionFan, 4);
+crate::variable_watch!(fume_extraction_mode, FumeExtractionMode, 3);
@@ -2,12 +2,13 @@ use crate::{
     api::access_control_raw_input_rx,
     devices::local::machine_run_detector::machine_run_rx,
     logic::{
-        interlock::{interlock_rx, monitor_states_rx},
-        machine_power::machine_power_rx,
+        fume_extraction::fume_extraction_mode_rx,
+        interlock::{grace_period_end_rx, interlock_rx, monitor_states_rx},
+        machine_power::{machine_power_rx, session_hold_end_rx},
     },
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either6, select6};
+use embassy_futures::select::{Either4, Either6, select4, select6};
 use hoshiguma_api::hmi::StatusScreenInfo;
 use hoshiguma_state_machines::{
     StateMachineRun,
@@ -46,48 +47,71 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
     let mut interlock_rx = interlock_rx();
     let mut machine_run_rx = machine_run_rx();
     let mut monitor_states_rx = monitor_states_rx();
+    let mut grace_period_end_rx = grace_period_end_rx();
+    let mut fume_extraction_mode_rx = fume_extraction_mode_rx();
+    let mut session_hold_end_rx = session_hold_end_rx();
 
     let status_screen_tx = HMI_STATUS_SCREEN_INFO.sender();
 
     loop {
-        match select6(
-            communicator.receive_output(),
-            access_control_raw_input_rx.changed(),
-            desired_machine_power_rx.changed(),
-            interlock_rx.changed(),
-            machine_run_rx.changed(),
-            monitor_states_rx.changed(),
+        match select4(
+            select6(
+                communicator.receive_output(),
+                access_control_raw_input_rx.changed(),
+                desired_machine_power_rx.changed(),
+                interlock_rx.changed(),
+                machine_run_rx.changed(),
+                monitor_states_rx.changed(),
+            ),
+            grace_period_end_rx.changed(),
+            fume_extraction_mode_rx.changed(),
+            session_hold_end_rx.changed(),
         )
         .await
         {
-            Either6::First(OutputMessage::StatusScreen(info)) => {
+            Either4::First(Either6::First(OutputMessage::StatusScreen(info))) => {
                 status_screen_tx.send(info);
             }
-            Either6::Second(state) => {
+            Either4::First(Either6::Second(state)) => {
                 communicator
                     .send_input(InputMessage::AccessControlRawInput(state))
                     .await;
             }
-            Either6::Third(state) => {
+            Either4::First(Either6::Third(state)) => {
                 communicator
                     .send_input(InputMessage::DesiredMachinePower(state))
                     .await;
             }
-            Either6::Fourth(state) => {
+            Either4::First(Either6::Fourth(state)) => {
                 communicator
                     .send_input(InputMessage::Interlock(state))
                     .await;
             }
-            Either6::Fifth(state) => {
+            Either4::First(Either6::Fifth(state)) => {
                 communicator
                     .send_input(InputMessage::MachineRun(state))
                     .await;
             }
-            Either6::Sixth(states) => {
+            Either4::First(Either6::Sixth(states)) => {
                 communicator
                     .send_input(InputMessage::MonitorStates(states))
                     .await;
             }
+            Either4::Second(end) => {
+                communicator
+                    .send_input(InputMessage::GracePeriodEnd(end))
+                    .await;
+            }
+            Either4::Third(mode) => {
+                communicator
+                    .send_input(InputMessage::FumeExtractionMode(mode))
+                    .await;
+            }
+            Either4::Fourth(end) => {
+                communicator
+                    .send_input(InputMessage::SessionHoldEnd(end))
+                    .await;
+            }
         }
     }
 }
@@ -1,10 +1,14 @@
 use crate::{
-    devices::local::machine_run_detector::machine_run_rx, telemetry::queue_telemetry_data_point,
+    devices::local::machine_run_detector::machine_run_rx,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_executor::Spawner;
 use embassy_futures::select::{Either3, select3};
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
-use hoshiguma_api::{Interlock, InterlockAction, Monitor, Severity};
+use embassy_time::Instant;
+use hoshiguma_api::{
+    Interlock, InterlockAction, Monitor, Severity, orchestrator::InterlockEventKind,
+};
 use hoshiguma_common::telemetry::format_influx_line;
 use hoshiguma_state_machines::{
     StateMachineRun,
@@ -18,7 +22,11 @@ static SM_INPUT: InputChannel = InputChannel::new();
 static SM_OUTPUT: OutputChannel = OutputChannel::new();
 
 pub(crate) fn init(spawner: Spawner) {
-    let (runner, communicator) = hoshiguma_state_machines::interlock::new(&SM_INPUT, &SM_OUTPUT);
+    crate::interlock_log::record(InterlockEventKind::Boot(crate::boot_reason()));
+
+    let (mut runner, communicator) =
+        hoshiguma_state_machines::interlock::new(&SM_INPUT, &SM_OUTPUT);
+    runner.record_inputs(&crate::input_recording::INTERLOCK);
 
     spawner.spawn(runner_task(runner).unwrap());
     spawner.spawn(communication_task(communicator).unwrap());
@@ -43,6 +51,9 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
     let monitor_states_tx = MONITOR_STATES.sender();
     let interlock_tx = INTERLOCK.sender();
     let interlock_action_tx = INTERLOCK_ACTION.sender();
+    let grace_period_end_tx = GRACE_PERIOD_END.sender();
+
+    let mut last_states = MonitorStateMap::new();
 
     loop {
         match select3(
@@ -54,29 +65,71 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
         {
             Either3::First(OutputMessage::States(states)) => {
                 for (monitor, severity) in &states {
-                    queue_telemetry_data_point(format_influx_line(
-                        format_args!("monitor,monitor={monitor} severity=\"{severity}\""),
-                        crate::wall_time::now(),
-                    ));
+                    let changed = last_states.get(monitor) != Some(severity);
+
+                    // Only changes of severity are events, the rest are a restatement of the
+                    // current severity and can be coalesced
+                    queue_telemetry_data_point(
+                        if changed {
+                            TelemetryPriority::Event
+                        } else {
+                            TelemetryPriority::State
+                        },
+                        format_influx_line(
+                            format_args!("monitor,monitor={monitor} severity=\"{severity}\""),
+                            crate::wall_time::now(),
+                        ),
+                    );
+
+                    if changed {
+                        crate::interlock_log::record(
+                            if *monitor == Monitor::InterlockTripped && *severity == Severity::Fatal
+                            {
+                                InterlockEventKind::Tripped
+                            } else {
+                                InterlockEventKind::MonitorSeverity(*monitor, *severity)
+                            },
+                        );
+                    }
                 }
+                last_states = states.clone();
 
                 monitor_states_tx.send(states);
             }
             Either3::First(OutputMessage::Interlock(state)) => {
                 interlock_tx.send(state);
+                crate::interlock_log::record(InterlockEventKind::Interlock(state));
 
-                queue_telemetry_data_point(format_influx_line(
-                    format_args!("interlock value=\"{state}\""),
-                    crate::wall_time::now(),
-                ));
+                queue_telemetry_data_point(
+                    TelemetryPriority::Event,
+                    format_influx_line(
+                        format_args!("interlock value=\"{state}\""),
+                        crate::wall_time::now(),
+                    ),
+                );
             }
             Either3::First(OutputMessage::Action(action)) => {
                 interlock_action_tx.send(action);
+                crate::interlock_log::record(InterlockEventKind::Action(action));
 
-                queue_telemetry_data_point(format_influx_line(
-                    format_args!("interlock_action value=\"{action}\""),
-                    crate::wall_time::now(),
-                ));
+                queue_telemetry_data_point(
+                    TelemetryPriority::Event,
+                    format_influx_line(
+                        format_args!("interlock_action value=\"{action}\""),
+                        crate::wall_time::now(),
+                    ),
+                );
+            }
+            Either3::First(OutputMessage::GracePeriodEnd(end)) => {
+                grace_period_end_tx.send(end);
+
+                queue_telemetry_data_point(
+                    TelemetryPriority::Event,
+                    format_influx_line(
+                        format_args!("interlock_grace_period active={}", end.is_some()),
+                        crate::wall_time::now(),
+                    ),
+                );
             }
             Either3::Second((monitor, severity)) => {
                 communicator
@@ -99,6 +152,7 @@ pub(crate) async fn update_monitor_severity(monitor: Monitor, severity: Severity
     MONITOR_SEVERITY_CH.send((monitor, severity)).await;
 }
 
-crate::variable_watch!(monitor_states, MonitorStateMap, 1);
-crate::variable_watch!(interlock, Interlock, 2);
-crate::variable_watch!(interlock_action, InterlockAction, 3);
+crate::variable_watch!(monitor_states, MonitorStateMap, 2);
+crate::variable_watch!(interlock, Interlock, 3);
+crate::variable_watch!(interlock_action, InterlockAction, 5);
+crate::variable_watch!(grace_period_end, Option<Instant>, 2);
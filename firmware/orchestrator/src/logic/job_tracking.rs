@@ -0,0 +1,139 @@
+use crate::{
+    devices::{
+        local::{doors_detector::doors_rx, machine_run_detector::machine_run_rx},
+        remote::observations::extraction_airflow_rx,
+    },
+    logic::{
+        interlock::interlock_action_rx, machine_power::machine_power_rx,
+        temperature_filter::FILTERED_TEMPERATURE_SENSOR_READING,
+    },
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
+};
+use core::fmt::{Display, Formatter};
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, Either6, select, select6};
+use embassy_sync::pubsub::WaitResult;
+use hoshiguma_common::telemetry::format_influx_line;
+use hoshiguma_state_machines::{
+    StateMachineRun,
+    job_tracking::{
+        InputChannel, InputMessage, OutputChannel, OutputMessage, StateMachineCommunicator,
+        StateMachineRunner,
+    },
+};
+
+static SM_INPUT: InputChannel = InputChannel::new();
+static SM_OUTPUT: OutputChannel = OutputChannel::new();
+
+pub(crate) fn init(spawner: Spawner) {
+    let (runner, communicator) = hoshiguma_state_machines::job_tracking::new(&SM_INPUT, &SM_OUTPUT);
+
+    spawner.spawn(runner_task(runner).unwrap());
+    spawner.spawn(communication_task(communicator).unwrap());
+}
+
+#[embassy_executor::task]
+async fn runner_task(mut runner: StateMachineRunner<'static>) -> ! {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("job tracking sm runner").await;
+
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn communication_task(mut communicator: StateMachineCommunicator<'static>) -> ! {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("job tracking sm comm").await;
+
+    let mut machine_run_rx = machine_run_rx();
+    let mut interlock_action_rx = interlock_action_rx();
+    let mut doors_rx = doors_rx();
+    let mut machine_power_rx = machine_power_rx();
+    let mut temperature_rx = FILTERED_TEMPERATURE_SENSOR_READING.subscriber().unwrap();
+    let mut extraction_airflow_rx = extraction_airflow_rx();
+
+    loop {
+        match select(
+            communicator.receive_output(),
+            select6(
+                machine_run_rx.changed(),
+                interlock_action_rx.changed(),
+                doors_rx.changed(),
+                machine_power_rx.changed(),
+                temperature_rx.next_message(),
+                extraction_airflow_rx.changed(),
+            ),
+        )
+        .await
+        {
+            Either::First(OutputMessage::JobEnded(summary)) => {
+                let job = crate::job_log::record(summary);
+
+                queue_telemetry_data_point(
+                    TelemetryPriority::Event,
+                    format_influx_line(
+                        format_args!(
+                            "job,end_reason={} duration={},interlock_events={},door_openings={}{}{}",
+                            job.end_reason,
+                            job.duration.as_secs_f32(),
+                            job.interlock_events,
+                            job.door_openings,
+                            OptionalField(
+                                "peak_coolant_reservoir_temperature",
+                                job.peak_coolant_reservoir_temperature
+                            ),
+                            OptionalField(
+                                "minimum_extraction_airflow",
+                                job.minimum_extraction_airflow
+                            ),
+                        ),
+                        job.end,
+                    ),
+                );
+            }
+            Either::Second(Either6::First(state)) => {
+                communicator
+                    .send_input(InputMessage::MachineRun(state))
+                    .await;
+            }
+            Either::Second(Either6::Second(action)) => {
+                communicator
+                    .send_input(InputMessage::InterlockAction(action))
+                    .await;
+            }
+            Either::Second(Either6::Third(state)) => {
+                communicator.send_input(InputMessage::Doors(state)).await;
+            }
+            Either::Second(Either6::Fourth(state)) => {
+                communicator
+                    .send_input(InputMessage::DesiredMachinePower(state))
+                    .await;
+            }
+            Either::Second(Either6::Fifth(WaitResult::Message(reading))) => {
+                communicator
+                    .send_input(InputMessage::Temperature(reading))
+                    .await;
+            }
+            Either::Second(Either6::Fifth(WaitResult::Lagged(n))) => {
+                panic!("subscriber lagged, lost {} messages", n);
+            }
+            Either::Second(Either6::Sixth(reading)) => {
+                communicator
+                    .send_input(InputMessage::ExtractionAirflowReading(reading))
+                    .await;
+            }
+        }
+    }
+}
+
+/// An InfluxDB field that is only written, including its separator, if it has a value.
+struct OptionalField(&'static str, Option<f32>);
+
+impl Display for OptionalField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.1 {
+            Some(value) => write!(f, ",{}={}", self.0, value),
+            None => Ok(()),
+        }
+    }
+}
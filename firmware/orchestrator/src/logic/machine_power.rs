@@ -1,7 +1,14 @@
-use crate::{api::access_control_state_rx, logic::interlock::interlock_action_rx};
+use crate::{
+    api::access_control_state_rx,
+    devices::local::machine_run_detector::machine_run_rx,
+    logic::interlock::interlock_action_rx,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
+};
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either3, select3};
+use embassy_futures::select::{Either4, select4};
+use embassy_time::Instant;
 use hoshiguma_api::DesiredMachinePower;
+use hoshiguma_common::telemetry::format_influx_line;
 use hoshiguma_state_machines::{
     StateMachineRun,
     machine_power::{
@@ -36,32 +43,52 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
 
     let mut access_control_state_rx = access_control_state_rx();
     let mut interlock_action_rx = interlock_action_rx();
+    let mut machine_run_rx = machine_run_rx();
 
     let machine_power_tx = MACHINE_POWER.sender();
+    let session_hold_end_tx = SESSION_HOLD_END.sender();
 
     loop {
-        match select3(
+        match select4(
             communicator.receive_output(),
             access_control_state_rx.changed(),
             interlock_action_rx.changed(),
+            machine_run_rx.changed(),
         )
         .await
         {
-            Either3::First(OutputMessage::Power(state)) => {
+            Either4::First(OutputMessage::Power(state)) => {
                 machine_power_tx.send(state);
             }
-            Either3::Second(state) => {
+            Either4::First(OutputMessage::SessionHoldEnd(end)) => {
+                session_hold_end_tx.send(end);
+
+                queue_telemetry_data_point(
+                    TelemetryPriority::State,
+                    format_influx_line(
+                        format_args!("machine_power_session_hold active={}", end.is_some()),
+                        crate::wall_time::now(),
+                    ),
+                );
+            }
+            Either4::Second(state) => {
                 communicator
                     .send_input(InputMessage::AccessControlState(state))
                     .await;
             }
-            Either3::Third(state) => {
+            Either4::Third(state) => {
                 communicator
                     .send_input(InputMessage::InterlockAction(state))
                     .await;
             }
+            Either4::Fourth(state) => {
+                communicator
+                    .send_input(InputMessage::MachineRun(state))
+                    .await;
+            }
         }
     }
 }
 
-crate::variable_watch!(machine_power, DesiredMachinePower, 3);
+crate::variable_watch!(machine_power, DesiredMachinePower, 6);
+crate::variable_watch!(session_hold_end, Option<Instant>, 1);
@@ -7,6 +7,9 @@ pub(crate) mod extraction_airflow;
 pub(crate) mod fume_extraction;
 pub(crate) mod hmi_status_screen;
 pub(crate) mod interlock;
+pub(crate) mod job_tracking;
 pub(crate) mod machine_power;
+pub(crate) mod runtime_counters;
 pub(crate) mod status_light;
+pub(crate) mod temperature_filter;
 pub(crate) mod temperatures;
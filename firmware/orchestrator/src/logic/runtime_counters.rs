@@ -0,0 +1,151 @@
+use crate::{
+    devices::local::machine_run_detector::machine_run_rx,
+    logic::{
+        cooling::{compressor_rx, coolant_pump_rx},
+        fume_extraction::fume_extraction_fan_rx,
+        interlock::update_monitor_severity,
+    },
+    storage::StorageKey,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
+};
+use defmt::warn;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either6, select6};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use hoshiguma_api::{Component, Monitor, RuntimeCounters};
+use hoshiguma_common::telemetry::format_influx_line;
+use hoshiguma_state_machines::{
+    StateMachineRun,
+    runtime_counters::{
+        InputChannel, InputMessage, OutputChannel, OutputMessage, StateMachineCommunicator,
+        StateMachineRunner,
+    },
+};
+
+static SM_INPUT: InputChannel = InputChannel::new();
+static SM_OUTPUT: OutputChannel = OutputChannel::new();
+
+pub(crate) fn init(spawner: Spawner) {
+    let (runner, communicator) =
+        hoshiguma_state_machines::runtime_counters::new(&SM_INPUT, &SM_OUTPUT);
+
+    spawner.spawn(runner_task(runner).unwrap());
+    spawner.spawn(communication_task(communicator).unwrap());
+}
+
+#[embassy_executor::task]
+async fn runner_task(mut runner: StateMachineRunner<'static>) -> ! {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("runtime counters sm runner").await;
+
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn communication_task(mut communicator: StateMachineCommunicator<'static>) -> ! {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("runtime counters sm comm").await;
+
+    let mut machine_run_rx = machine_run_rx();
+    let mut coolant_pump_rx = coolant_pump_rx();
+    let mut compressor_rx = compressor_rx();
+    let mut fume_extraction_fan_rx = fume_extraction_fan_rx();
+
+    let runtime_counters_tx = RUNTIME_COUNTERS.sender();
+
+    // If stored counters exist but could not be loaded they are left untouched, rather than being
+    // overwritten by counters starting from zero.
+    let persist = match crate::storage::load::<RuntimeCounters>(StorageKey::RuntimeCounters).await {
+        Ok(counters) => {
+            communicator
+                .send_input(InputMessage::RestoreCounters(counters.unwrap_or_default()))
+                .await;
+            true
+        }
+        Err(e) => {
+            warn!(
+                "Runtime counters will not be saved, failed to load them: {}",
+                e
+            );
+            false
+        }
+    };
+
+    loop {
+        match select6(
+            communicator.receive_output(),
+            machine_run_rx.changed(),
+            coolant_pump_rx.changed(),
+            compressor_rx.changed(),
+            fume_extraction_fan_rx.changed(),
+            RESET_REQUEST_CH.receive(),
+        )
+        .await
+        {
+            Either6::First(OutputMessage::Counters(counters)) => {
+                if persist {
+                    crate::storage::save(StorageKey::RuntimeCounters, &counters).await;
+                }
+                runtime_counters_tx.send(counters);
+
+                for component in [
+                    Component::LaserTube,
+                    Component::CoolantPump,
+                    Component::Compressor,
+                    Component::ExtractionFan,
+                ] {
+                    let counter = counters.get(component);
+                    queue_telemetry_data_point(
+                        TelemetryPriority::Sample,
+                        format_influx_line(
+                            format_args!(
+                                "runtime,component={} total={},since_service={}",
+                                component,
+                                counter.total.as_secs(),
+                                counter.since_service.as_secs()
+                            ),
+                            crate::wall_time::now(),
+                        ),
+                    );
+                }
+            }
+            Either6::First(OutputMessage::ServiceSeverity(severity)) => {
+                update_monitor_severity(Monitor::ServiceDue, severity).await;
+            }
+            Either6::Second(state) => {
+                communicator
+                    .send_input(InputMessage::MachineRun(state))
+                    .await;
+            }
+            Either6::Third(state) => {
+                communicator
+                    .send_input(InputMessage::CoolantPumpState(state))
+                    .await;
+            }
+            Either6::Fourth(state) => {
+                communicator
+                    .send_input(InputMessage::CompressorState(state))
+                    .await;
+            }
+            Either6::Fifth(state) => {
+                communicator
+                    .send_input(InputMessage::FumeExtractionFan(state))
+                    .await;
+            }
+            Either6::Sixth(component) => {
+                communicator
+                    .send_input(InputMessage::ResetServiceCounter(component))
+                    .await;
+            }
+        }
+    }
+}
+
+static RESET_REQUEST_CH: Channel<CriticalSectionRawMutex, Component, 2> = Channel::new();
+
+/// Record that a component has been serviced, e.g. from the API.
+pub(crate) async fn reset_service_counter(component: Component) {
+    RESET_REQUEST_CH.send(component).await;
+}
+
+crate::variable_watch!(runtime_counters, RuntimeCounters, 1);
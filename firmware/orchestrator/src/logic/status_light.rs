@@ -2,11 +2,14 @@ use crate::{
     devices::local::{
         ac_bus_power_detector::ac_bus_power_rx, machine_run_detector::machine_run_rx,
     },
-    logic::interlock::interlock_rx,
+    logic::{
+        fume_extraction::fume_extraction_mode_rx,
+        interlock::{grace_period_end_rx, interlock_rx},
+    },
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either4, select4};
-use hoshiguma_api::rear_sensor_board::StatusLightSettings;
+use embassy_futures::select::{Either6, select6};
+use hoshiguma_api::{FumeExtractionMode, rear_sensor_board::StatusLightSettings};
 use hoshiguma_state_machines::{
     StateMachineRun,
     status_light::{
@@ -41,36 +44,53 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
     let mut ac_bus_power_rx = ac_bus_power_rx();
     let mut machine_run_rx = machine_run_rx();
     let mut interlock_rx = interlock_rx();
+    let mut grace_period_end_rx = grace_period_end_rx();
+    let mut fume_extraction_mode_rx = fume_extraction_mode_rx();
 
     let setting_tx = STATUS_LIGHT.sender();
 
     loop {
-        match select4(
+        match select6(
             communicator.receive_output(),
             ac_bus_power_rx.changed(),
             machine_run_rx.changed(),
             interlock_rx.changed(),
+            grace_period_end_rx.changed(),
+            fume_extraction_mode_rx.changed(),
         )
         .await
         {
-            Either4::First(OutputMessage::Settings(settings)) => {
+            Either6::First(OutputMessage::Settings(settings)) => {
                 setting_tx.send(settings);
             }
-            Either4::Second(state) => {
+            Either6::Second(state) => {
                 communicator
                     .send_input(InputMessage::AcBusPower(state))
                     .await;
             }
-            Either4::Third(state) => {
+            Either6::Third(state) => {
                 communicator
                     .send_input(InputMessage::MachineRun(state))
                     .await;
             }
-            Either4::Fourth(state) => {
+            Either6::Fourth(state) => {
                 communicator
                     .send_input(InputMessage::Interlock(state))
                     .await;
             }
+            Either6::Fifth(end) => {
+                communicator
+                    .send_input(InputMessage::GracePeriodEnd(end))
+                    .await;
+            }
+            Either6::Sixth(mode) => {
+                // Overriding the fume extraction is currently the only maintenance mode
+                communicator
+                    .send_input(InputMessage::Maintenance(
+                        mode == FumeExtractionMode::OverrideRun,
+                    ))
+                    .await;
+            }
         }
     }
 }
@@ -0,0 +1,112 @@
+use crate::{
+    devices::temperature::TEMPERATURE_SENSOR_READING,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
+};
+use core::cell::RefCell;
+use defmt::warn;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    pubsub::{PubSubChannel, WaitResult},
+};
+use heapless::{LinearMap, Vec};
+use hoshiguma_api::{
+    TemperatureReading, TemperatureSensor, TemperatureSensorReading,
+    orchestrator::LATEST_TEMPERATURES_CAPACITY,
+};
+use hoshiguma_common::telemetry::format_influx_line;
+use hoshiguma_state_machines::{
+    StateMachineRun,
+    temperature_filter::{
+        InputChannel, InputMessage, OutputChannel, OutputMessage, StateMachineCommunicator,
+        StateMachineRunner,
+    },
+};
+
+/// Temperature readings that have passed the plausibility checks.
+pub(crate) static FILTERED_TEMPERATURE_SENSOR_READING: PubSubChannel<
+    CriticalSectionRawMutex,
+    TemperatureSensorReading,
+    16,
+    3,
+    1,
+> = PubSubChannel::new();
+
+/// The most recent filtered reading of each sensor.
+static LATEST: CriticalSectionMutex<
+    RefCell<LinearMap<TemperatureSensor, TemperatureReading, LATEST_TEMPERATURES_CAPACITY>>,
+> = CriticalSectionMutex::new(RefCell::new(LinearMap::new()));
+
+/// Gets the most recent filtered reading of each sensor that has reported since boot.
+pub(crate) fn latest() -> Vec<TemperatureSensorReading, LATEST_TEMPERATURES_CAPACITY> {
+    LATEST.lock(|latest| {
+        latest
+            .borrow()
+            .iter()
+            .map(|(sensor, reading)| TemperatureSensorReading {
+                sensor: *sensor,
+                reading: *reading,
+            })
+            .collect()
+    })
+}
+
+static SM_INPUT: InputChannel = InputChannel::new();
+static SM_OUTPUT: OutputChannel = OutputChannel::new();
+
+pub(crate) fn init(spawner: Spawner) {
+    let (runner, communicator) =
+        hoshiguma_state_machines::temperature_filter::new(&SM_INPUT, &SM_OUTPUT);
+
+    spawner.spawn(runner_task(runner).unwrap());
+    spawner.spawn(communication_task(communicator).unwrap());
+}
+
+#[embassy_executor::task]
+async fn runner_task(mut runner: StateMachineRunner<'static>) -> ! {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("temperature filter sm runner").await;
+
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn communication_task(mut communicator: StateMachineCommunicator<'static>) -> ! {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("temperature filter sm comm").await;
+
+    let mut temperature_rx = TEMPERATURE_SENSOR_READING.subscriber().unwrap();
+    let filtered_temperature_pub = FILTERED_TEMPERATURE_SENSOR_READING.publisher().unwrap();
+
+    loop {
+        match select(communicator.receive_output(), temperature_rx.next_message()).await {
+            Either::First(OutputMessage::Temperature(reading)) => {
+                if LATEST
+                    .lock(|latest| latest.borrow_mut().insert(reading.sensor, reading.reading))
+                    .is_err()
+                {
+                    warn!("No room to keep latest reading from {}", reading.sensor);
+                }
+                filtered_temperature_pub.publish(reading).await;
+            }
+            Either::First(OutputMessage::RejectedReadings(sensor, count)) => {
+                queue_telemetry_data_point(
+                    TelemetryPriority::Sample,
+                    format_influx_line(
+                        format_args!("temperature_rejected_readings,sensor={sensor} value={count}"),
+                        crate::wall_time::now(),
+                    ),
+                );
+            }
+            Either::Second(WaitResult::Message(reading)) => {
+                communicator
+                    .send_input(InputMessage::Temperature(reading))
+                    .await;
+            }
+            Either::Second(WaitResult::Lagged(n)) => {
+                panic!("subscriber lagged, lost {} messages", n);
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
-    devices::temperature::TEMPERATURE_SENSOR_READING, logic::interlock::update_monitor_severity,
-    telemetry::queue_telemetry_data_point,
+    devices::local::machine_run_detector::machine_run_rx,
+    logic::{
+        interlock::update_monitor_severity, temperature_filter::FILTERED_TEMPERATURE_SENSOR_READING,
+    },
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
 };
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either, select};
+use embassy_futures::select::{Either3, select3};
 use embassy_sync::pubsub::WaitResult;
 use hoshiguma_api::Monitor;
 use hoshiguma_common::telemetry::format_influx_line;
@@ -38,20 +41,27 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
     #[cfg(feature = "trace")]
     crate::trace::name_task("temperatures sm comm").await;
 
-    let mut temperature_rx = TEMPERATURE_SENSOR_READING.subscriber().unwrap();
+    let mut temperature_rx = FILTERED_TEMPERATURE_SENSOR_READING.subscriber().unwrap();
+    let mut machine_run_rx = machine_run_rx();
 
     loop {
-        match select(communicator.receive_output(), temperature_rx.next_message()).await {
-            Either::First(OutputMessage::FunctionalSeverity(severity)) => {
+        match select3(
+            communicator.receive_output(),
+            temperature_rx.next_message(),
+            machine_run_rx.changed(),
+        )
+        .await
+        {
+            Either3::First(OutputMessage::FunctionalSeverity(severity)) => {
                 update_monitor_severity(Monitor::TemperatureSensorsFunctional, severity).await;
             }
-            Either::First(OutputMessage::ElectronicsTemperatureSeverity(severity)) => {
-                update_monitor_severity(Monitor::ElectronicsTemperature, severity).await;
+            Either3::First(OutputMessage::TemperatureSeverity(monitor, severity)) => {
+                update_monitor_severity(monitor, severity).await;
             }
-            Either::First(OutputMessage::CoolantReservoirTemperatureSeverity(severity)) => {
-                update_monitor_severity(Monitor::CoolantReservoirTemperature, severity).await;
+            Either3::First(OutputMessage::RateOfRiseSeverity(severity)) => {
+                update_monitor_severity(Monitor::TemperatureRateOfRise, severity).await;
             }
-            Either::Second(WaitResult::Message(reading)) => {
+            Either3::Second(WaitResult::Message(reading)) => {
                 communicator
                     .send_input(InputMessage::Temperature(reading))
                     .await;
@@ -59,15 +69,23 @@ async fn communication_task(mut communicator: StateMachineCommunicator<'static>)
                 // Submit sensor telemetry
                 let sensor = reading.sensor;
                 if let Ok(reading) = reading.reading {
-                    queue_telemetry_data_point(format_influx_line(
-                        format_args!("temperature,sensor={sensor} value={reading}"),
-                        crate::wall_time::now(),
-                    ));
+                    queue_telemetry_data_point(
+                        TelemetryPriority::Sample,
+                        format_influx_line(
+                            format_args!("temperature,sensor={sensor} value={reading}"),
+                            crate::wall_time::now(),
+                        ),
+                    );
                 }
             }
-            Either::Second(WaitResult::Lagged(n)) => {
+            Either3::Second(WaitResult::Lagged(n)) => {
                 panic!("subscriber lagged, lost {} messages", n);
             }
+            Either3::Third(state) => {
+                communicator
+                    .send_input(InputMessage::MachineRun(state))
+                    .await;
+            }
         }
     }
 }
@@ -5,12 +5,18 @@ mod api;
 mod devices;
 mod hmi;
 mod input_change_detector;
+mod input_recording;
+mod interlock_log;
+mod job_log;
 mod logic;
 mod network;
 mod remote_device_monitor;
 mod self_telemetry;
+mod state_snapshot;
+mod storage;
 mod telemetry;
 mod telemetry_bridge_comm;
+mod telemetry_spool;
 #[cfg(feature = "trace")]
 mod trace;
 mod wall_time;
@@ -51,6 +57,9 @@ assign_resources! {
         int: PIN_24,
         reset: PIN_25,
     },
+    storage: StorageResources {
+        flash: FLASH,
+    },
     onewire: OnewireResources {
         pio: PIO1,
         pin: PIN_28,
@@ -65,6 +74,9 @@ assign_resources! {
     air_assist_demand_detect: AirAssistDemandDetectResources {
         detect: PIN_4, // Input 6
     },
+    air_assist_pressure_detect: AirAssistPressureDetectResources {
+        detect: PIN_5, // Input 1
+    },
     machine_run_detect: MachineRunDetectResources {
         detect: PIN_3, // Input 5
     },
@@ -151,6 +163,8 @@ fn main() -> ! {
     let _relay_7 = Output::new(r.unused_relays.relay_7, Level::Low);
     let _relay_8 = Output::new(r.unused_relays.relay_8, Level::Low);
 
+    storage::init(r.storage);
+
     // Core 1 deals with everything...
     spawn_core1(
         p.CORE1,
@@ -173,6 +187,10 @@ fn main() -> ! {
                 devices::local::air_assist_demand_detector::task(r.air_assist_demand_detect)
                     .unwrap(),
             );
+            spawner.spawn(
+                devices::local::air_assist_pressure_detector::task(r.air_assist_pressure_detect)
+                    .unwrap(),
+            );
             spawner.spawn(devices::local::air_assist_pump::task(r.air_assist_pump).unwrap());
             spawner.spawn(devices::local::doors_detector::task(r.doors_detect).unwrap());
             spawner
@@ -191,14 +209,20 @@ fn main() -> ! {
             logic::extraction_airflow::init(spawner);
             logic::fume_extraction::init(spawner);
             logic::interlock::init(spawner);
+            logic::job_tracking::init(spawner);
             logic::hmi_status_screen::init(spawner);
             logic::machine_power::init(spawner);
+            logic::runtime_counters::init(spawner);
             logic::status_light::init(spawner);
+            logic::temperature_filter::init(spawner);
             logic::temperatures::init(spawner);
 
             #[cfg(feature = "test-panic-on-core-1")]
             spawner.spawn(dummy_panic().unwrap());
 
+            #[cfg(feature = "test-storage-round-trip")]
+            spawner.spawn(storage::round_trip_test_task().unwrap());
+
             loop {
                 cortex_m::asm::wfe();
                 if !PANIC_HALT.load(Ordering::Relaxed) {
@@ -208,12 +232,13 @@ fn main() -> ! {
         },
     );
 
-    // ...except networking, which is on core 0
+    // ...except networking and storage, which are on core 0
     let executor_0 = EXECUTOR_0.init(Executor::new(usize::MAX as *mut ()));
     #[cfg(feature = "trace")]
     trace::identify_core_0_executor(executor_0.id() as u32);
     let spawner = executor_0.spawner();
 
+    spawner.spawn(storage::task().unwrap());
     spawner.spawn(network_tasks(spawner, r.ethernet).unwrap());
 
     #[cfg(feature = "trace")]
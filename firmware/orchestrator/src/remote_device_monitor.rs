@@ -1,4 +1,7 @@
-use crate::{logic::interlock::update_monitor_severity, telemetry::queue_telemetry_data_point};
+use crate::{
+    logic::interlock::update_monitor_severity,
+    telemetry::{TelemetryPriority, queue_telemetry_data_point},
+};
 use defmt::info;
 use embassy_net::Stack;
 use embassy_time::{Instant, Timer};
@@ -23,7 +26,7 @@ pub(crate) async fn task(stack: Stack<'static>) -> ! {
             update_monitor_severity(Monitor::CoolerCommunication, severity).await;
         },
         |data_point| {
-            queue_telemetry_data_point(data_point);
+            queue_telemetry_data_point(TelemetryPriority::Sample, data_point);
         },
     );
 
@@ -40,7 +43,7 @@ pub(crate) async fn task(stack: Stack<'static>) -> ! {
             update_monitor_severity(Monitor::RearSensorBoardCommunication, severity).await;
         },
         |data_point| {
-            queue_telemetry_data_point(data_point);
+            queue_telemetry_data_point(TelemetryPriority::Sample, data_point);
         },
     );
 
@@ -57,7 +60,7 @@ pub(crate) async fn task(stack: Stack<'static>) -> ! {
             update_monitor_severity(Monitor::HmiCommunication, severity).await;
         },
         |data_point| {
-            queue_telemetry_data_point(data_point);
+            queue_telemetry_data_point(TelemetryPriority::Sample, data_point);
         },
     );
 
@@ -9,8 +9,14 @@
 //! - wall time
 //! - number of data points discarded due to formatting failures
 //! - number of data points discarded due to buffer capacity
+//! - number of data points replaced by a newer value of the same series
+//! - number of data points moved to the spool because the queue was full
+//! - number of data points spooled during telemetry bridge outages
+//! - number of spooled data points replayed to the telemetry bridge
+//! - number of spooled data points discarded due to spool capacity
+//! - number of spooled data points discarded because they could not be replayed
 
-use crate::telemetry::queue_telemetry_data_point;
+use crate::telemetry::{TelemetryPriority, queue_telemetry_data_point};
 use core::sync::atomic::Ordering;
 use embassy_time::{Instant, Timer};
 use hoshiguma_common::telemetry::format_influx_line;
@@ -19,6 +25,12 @@ use portable_atomic::AtomicUsize;
 pub(crate) static DATA_POINTS_DISCARDED_FORMAT_FAIL: AtomicUsize = AtomicUsize::new(0);
 pub(crate) static DATA_POINTS_DISCARDED_QUEUE_FULL: AtomicUsize = AtomicUsize::new(0);
 pub(crate) static DATA_POINTS_DISCARDED_TX_FAIL: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static DATA_POINTS_COALESCED: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static DATA_POINTS_SPILLED: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static DATA_POINTS_SPOOLED: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static DATA_POINTS_REPLAYED: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static DATA_POINTS_DISCARDED_SPOOL_FULL: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static DATA_POINTS_DISCARDED_SPOOL_UNREPLAYABLE: AtomicUsize = AtomicUsize::new(0);
 
 #[embassy_executor::task]
 pub(crate) async fn task() {
@@ -27,60 +39,147 @@ pub(crate) async fn task() {
 
     // Send data points that only change on boot
     {
-        queue_telemetry_data_point(format_influx_line(
-            format_args!(
-                "orchestrator_git_revision value=\"{}\"",
-                git_version::git_version!()
+        queue_telemetry_data_point(
+            TelemetryPriority::Event,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_git_revision value=\"{}\"",
+                    git_version::git_version!()
+                ),
+                None,
             ),
-            None,
-        ));
+        );
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!(
-                "orchestrator_boot_reason value=\"{}\"",
-                crate::boot_reason()
+        queue_telemetry_data_point(
+            TelemetryPriority::Event,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_boot_reason value=\"{}\"",
+                    crate::boot_reason()
+                ),
+                None,
             ),
-            None,
-        ));
+        );
     }
 
     loop {
-        queue_telemetry_data_point(format_influx_line(
-            format_args!("orchestrator_uptime value={}", Instant::now().as_millis()),
-            None,
-        ));
-
-        queue_telemetry_data_point(format_influx_line(
-            format_args!(
-                "orchestrator_wall_time value={}",
-                crate::wall_time::now().unwrap_or_default().timestamp()
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!("orchestrator_uptime value={}", Instant::now().as_millis()),
+                None,
             ),
-            None,
-        ));
+        );
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!(
-                "orchestrator_data_points_discarded,reason=format_error value={}",
-                DATA_POINTS_DISCARDED_FORMAT_FAIL.load(Ordering::Relaxed)
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_wall_time value={}",
+                    crate::wall_time::now().unwrap_or_default().timestamp()
+                ),
+                None,
             ),
-            None,
-        ));
+        );
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!(
-                "orchestrator_data_points_discarded,reason=queue_full value={}",
-                DATA_POINTS_DISCARDED_QUEUE_FULL.load(Ordering::Relaxed)
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_discarded,reason=format_error value={}",
+                    DATA_POINTS_DISCARDED_FORMAT_FAIL.load(Ordering::Relaxed)
+                ),
+                None,
             ),
-            None,
-        ));
+        );
 
-        queue_telemetry_data_point(format_influx_line(
-            format_args!(
-                "orchestrator_data_points_discarded,reason=tx_fail value={}",
-                DATA_POINTS_DISCARDED_TX_FAIL.load(Ordering::Relaxed)
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_discarded,reason=queue_full value={}",
+                    DATA_POINTS_DISCARDED_QUEUE_FULL.load(Ordering::Relaxed)
+                ),
+                None,
             ),
-            None,
-        ));
+        );
+
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_discarded,reason=tx_fail value={}",
+                    DATA_POINTS_DISCARDED_TX_FAIL.load(Ordering::Relaxed)
+                ),
+                None,
+            ),
+        );
+
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_coalesced value={}",
+                    DATA_POINTS_COALESCED.load(Ordering::Relaxed)
+                ),
+                None,
+            ),
+        );
+
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_spilled value={}",
+                    DATA_POINTS_SPILLED.load(Ordering::Relaxed)
+                ),
+                None,
+            ),
+        );
+
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_spooled value={}",
+                    DATA_POINTS_SPOOLED.load(Ordering::Relaxed)
+                ),
+                None,
+            ),
+        );
+
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_replayed value={}",
+                    DATA_POINTS_REPLAYED.load(Ordering::Relaxed)
+                ),
+                None,
+            ),
+        );
+
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_discarded,reason=spool_full value={}",
+                    DATA_POINTS_DISCARDED_SPOOL_FULL.load(Ordering::Relaxed)
+                ),
+                None,
+            ),
+        );
+
+        queue_telemetry_data_point(
+            TelemetryPriority::Sample,
+            format_influx_line(
+                format_args!(
+                    "orchestrator_data_points_discarded,reason=spool_unreplayable value={}",
+                    DATA_POINTS_DISCARDED_SPOOL_UNREPLAYABLE.load(Ordering::Relaxed)
+                ),
+                None,
+            ),
+        );
 
         // Send data points (approximately) every minute
         Timer::after_secs(60).await;
@@ -0,0 +1,56 @@
+//! Snapshots of the internal state of each state machine, for introspection via the API.
+
+use defmt::warn;
+use embassy_time::Instant;
+use heapless::Vec;
+use hoshiguma_api::orchestrator::{
+    STATE_MACHINE_SNAPSHOT_CAPACITY, StateMachine, StateMachineSnapshot,
+};
+use hoshiguma_state_machines as sm;
+use serde::Serialize;
+
+pub(crate) async fn get(machine: StateMachine) -> StateMachineSnapshot {
+    let snapshot = match machine {
+        StateMachine::AirAssist => serialise(machine, sm::air_assist::snapshot().await),
+        StateMachine::CoolantRate => serialise(machine, sm::coolant_rate::snapshot().await),
+        StateMachine::Cooling => serialise(machine, sm::cooling::snapshot().await),
+        StateMachine::ExtractionAirflow => {
+            serialise(machine, sm::extraction_airflow::snapshot().await)
+        }
+        StateMachine::FumeExtraction => serialise(machine, sm::fume_extraction::snapshot().await),
+        StateMachine::HmiStatusScreen => {
+            serialise(machine, sm::hmi_status_screen::snapshot().await)
+        }
+        StateMachine::Interlock => serialise(machine, sm::interlock::snapshot().await),
+        StateMachine::JobTracking => serialise(machine, sm::job_tracking::snapshot().await),
+        StateMachine::MachinePower => serialise(machine, sm::machine_power::snapshot().await),
+        StateMachine::RuntimeCounters => serialise(machine, sm::runtime_counters::snapshot().await),
+        StateMachine::StatusLight => serialise(machine, sm::status_light::snapshot().await),
+        StateMachine::TemperatureFilter => {
+            serialise(machine, sm::temperature_filter::snapshot().await)
+        }
+        StateMachine::Temperatures => serialise(machine, sm::temperatures::snapshot().await),
+    };
+
+    StateMachineSnapshot {
+        machine,
+        uptime: Instant::now().duration_since(Instant::MIN).into(),
+        snapshot,
+    }
+}
+
+fn serialise<T: Serialize>(
+    machine: StateMachine,
+    snapshot: Option<T>,
+) -> Option<Vec<u8, STATE_MACHINE_SNAPSHOT_CAPACITY>> {
+    let snapshot = snapshot?;
+
+    let mut buffer = [0u8; STATE_MACHINE_SNAPSHOT_CAPACITY];
+    match postcard::to_slice(&snapshot, &mut buffer) {
+        Ok(data) => Some(Vec::from_slice(data).expect("buffer should match the snapshot capacity")),
+        Err(_) => {
+            warn!("Snapshot of {} state machine is too large", machine);
+            None
+        }
+    }
+}
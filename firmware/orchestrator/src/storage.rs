@@ -0,0 +1,274 @@
+//! Persistent key/value storage in the end of the on-board flash.
+//!
+//! Values are serialised with postcard, so any type from the API crate can be stored.
+//!
+//! Flash can only be written from core 0, so all access to storage is performed by a task on core 0
+//! which other tasks, on either core, send load and save requests to.
+//!
+//! The flash below the key/value storage is reserved for the telemetry spool, which is only used
+//! when the `telemetry-spool-flash` feature is enabled.
+
+use crate::StorageResources;
+use defmt::{Format, warn};
+use embassy_embedded_hal::{adapter::BlockingAsync, flash::partition::Partition};
+use embassy_rp::{
+    flash::{Blocking, ERASE_SIZE, Flash},
+    peripherals::FLASH,
+};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, mutex::Mutex, signal::Signal,
+};
+use heapless::Vec;
+use sequential_storage::{
+    cache::{Cache, Uncached},
+    map::{MapConfig, MapStorage},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use static_cell::StaticCell;
+
+/// Total size of the flash chip.
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// Size of the region at the end of flash used for storage.
+const STORAGE_SIZE: usize = 16 * ERASE_SIZE;
+
+/// Size of the region immediately before storage used for the telemetry spool.
+///
+/// Together with the storage region this must match the space reserved in memory.x, the region is
+/// reserved regardless of the spool being enabled.
+#[cfg(feature = "telemetry-spool-flash")]
+const TELEMETRY_SPOOL_SIZE: usize = 16 * ERASE_SIZE;
+
+/// Large enough for the key and the largest stored value, rounded up to flash word alignment.
+const BUFFER_SIZE: usize = 128;
+
+type SharedFlash =
+    Mutex<CriticalSectionRawMutex, BlockingAsync<Flash<'static, FLASH, Blocking, FLASH_SIZE>>>;
+
+/// A region of the on-board flash.
+pub(crate) type FlashPartition = Partition<
+    'static,
+    CriticalSectionRawMutex,
+    BlockingAsync<Flash<'static, FLASH, Blocking, FLASH_SIZE>>,
+>;
+
+type Storage = MapStorage<u8, FlashPartition, StorageCache>;
+type StorageCache = Cache<Uncached, Uncached, Uncached, u8>;
+
+static FLASH: StaticCell<SharedFlash> = StaticCell::new();
+static STORAGE: Mutex<CriticalSectionRawMutex, Option<Storage>> = Mutex::new(None);
+
+enum Request {
+    Load(StorageKey),
+    Save(StorageKey, Vec<u8, BUFFER_SIZE>),
+}
+
+static REQUESTS: Channel<CriticalSectionRawMutex, Request, 4> = Channel::new();
+
+/// Only one load is requested at a time, so that the response can be matched to its request.
+static LOAD_LOCK: Mutex<CriticalSectionRawMutex, ()> = Mutex::new(());
+static LOAD_RESPONSE: Signal<
+    CriticalSectionRawMutex,
+    Result<Option<Vec<u8, BUFFER_SIZE>>, LoadError>,
+> = Signal::new();
+
+#[cfg(feature = "telemetry-spool-flash")]
+static TELEMETRY_SPOOL_PARTITION: Mutex<CriticalSectionRawMutex, Option<FlashPartition>> =
+    Mutex::new(None);
+
+/// Identifies each value held in storage.
+///
+/// Discriminants are stored in flash, so must not be changed or reused.
+#[derive(Debug, Format, Clone, Copy)]
+#[repr(u8)]
+pub(crate) enum StorageKey {
+    ExtractionAirflowTrend = 0,
+    RuntimeCounters = 1,
+}
+
+/// A stored value could not be loaded.
+///
+/// This is distinct from a value not being stored, in which case a default is usually appropriate.
+#[derive(Debug, Format, Clone, Copy)]
+pub(crate) enum LoadError {
+    /// Reading from flash failed.
+    Flash,
+    /// The stored value could not be deserialised.
+    Deserialise,
+}
+
+pub(crate) fn init(r: StorageResources) {
+    let flash = FLASH.init(Mutex::new(BlockingAsync::new(Flash::new_blocking(r.flash))));
+
+    let storage = MapStorage::new(
+        Partition::new(
+            flash,
+            (FLASH_SIZE - STORAGE_SIZE) as u32,
+            STORAGE_SIZE as u32,
+        ),
+        MapConfig::new(0..STORAGE_SIZE as u32),
+        StorageCache::new_uncached(),
+    );
+
+    STORAGE
+        .try_lock()
+        .expect("storage should not be in use before init")
+        .replace(storage);
+
+    #[cfg(feature = "telemetry-spool-flash")]
+    TELEMETRY_SPOOL_PARTITION
+        .try_lock()
+        .expect("telemetry spool partition should not be in use before init")
+        .replace(Partition::new(
+            flash,
+            (FLASH_SIZE - STORAGE_SIZE - TELEMETRY_SPOOL_SIZE) as u32,
+            TELEMETRY_SPOOL_SIZE as u32,
+        ));
+}
+
+/// Takes the flash region reserved for the telemetry spool, this can only be done once.
+#[cfg(feature = "telemetry-spool-flash")]
+pub(crate) async fn take_telemetry_spool_partition() -> FlashPartition {
+    TELEMETRY_SPOOL_PARTITION
+        .lock()
+        .await
+        .take()
+        .expect("storage should be initialised and spool partition not already taken")
+}
+
+/// Loads the most recently saved value for a key, if there is one.
+pub(crate) async fn load<T: DeserializeOwned>(key: StorageKey) -> Result<Option<T>, LoadError> {
+    let data = {
+        let _lock = LOAD_LOCK.lock().await;
+        REQUESTS.send(Request::Load(key)).await;
+        LOAD_RESPONSE.wait().await?
+    };
+
+    let Some(data) = data else {
+        return Ok(None);
+    };
+
+    match postcard::from_bytes(&data) {
+        Ok(value) => Ok(Some(value)),
+        Err(_) => {
+            warn!("Failed to deserialise stored value for {}", key);
+            Err(LoadError::Deserialise)
+        }
+    }
+}
+
+/// Saves a value for a key, replacing any previously saved value.
+///
+/// The value is written to flash by the storage task some time after this returns.
+pub(crate) async fn save<T: Serialize>(key: StorageKey, value: &T) {
+    let mut data = Vec::new();
+    data.resize_default(BUFFER_SIZE).unwrap();
+
+    let Ok(len) = postcard::to_slice(value, &mut data).map(|data| data.len()) else {
+        warn!("Failed to serialise value for {}", key);
+        return;
+    };
+    data.truncate(len);
+
+    REQUESTS.send(Request::Save(key, data)).await;
+}
+
+/// Performs load and save requests, this must be run on core 0.
+#[embassy_executor::task]
+pub(crate) async fn task() -> ! {
+    #[cfg(feature = "trace")]
+    crate::trace::name_task("storage").await;
+
+    let mut storage = STORAGE
+        .lock()
+        .await
+        .take()
+        .expect("storage should be initialised and not already in use");
+
+    let mut buffer = [0u8; BUFFER_SIZE];
+
+    loop {
+        match REQUESTS.receive().await {
+            Request::Load(key) => {
+                let data = match storage.fetch_item::<&[u8]>(&mut buffer, &(key as u8)).await {
+                    Ok(Some(data)) => Vec::from_slice(data).map(Some).map_err(|_| {
+                        warn!("Stored value for {} is larger than the buffer", key);
+                        LoadError::Deserialise
+                    }),
+                    Ok(None) => Ok(None),
+                    Err(e) => {
+                        warn!("Failed to load {}: {}", key, e);
+                        Err(LoadError::Flash)
+                    }
+                };
+                LOAD_RESPONSE.signal(data);
+            }
+            Request::Save(key, data) => {
+                if let Err(e) = storage.store_item(&mut buffer, &(key as u8), &&*data).await {
+                    warn!("Failed to save {}: {}", key, e);
+                }
+            }
+        }
+    }
+}
+
+/// Saves and reloads values from core 1, where the tasks that use storage run, checking that they
+/// survive the round trip through flash.
+///
+/// Any previously stored values are restored afterwards.
+#[cfg(feature = "test-storage-round-trip")]
+#[embassy_executor::task]
+pub(crate) async fn round_trip_test_task() {
+    use core::time::Duration;
+    use hoshiguma_api::{ExtractionAirflowTrend, RuntimeCounter, RuntimeCounters};
+
+    round_trip(
+        StorageKey::ExtractionAirflowTrend,
+        ExtractionAirflowTrend {
+            average: 61.5,
+            reference: 80.25,
+            runs: 42,
+        },
+    )
+    .await;
+
+    let counter = RuntimeCounter {
+        total: Duration::from_secs(123_456),
+        since_service: Duration::from_secs(789),
+    };
+
+    round_trip(
+        StorageKey::RuntimeCounters,
+        RuntimeCounters {
+            laser_tube: counter,
+            coolant_pump: counter,
+            compressor: counter,
+            extraction_fan: counter,
+        },
+    )
+    .await;
+
+    defmt::info!("Storage round trip test passed");
+}
+
+#[cfg(feature = "test-storage-round-trip")]
+async fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Format>(
+    key: StorageKey,
+    value: T,
+) {
+    let original: Option<T> = load(key).await.ok().flatten();
+
+    save(key, &value).await;
+    let loaded: Option<T> = load(key).await.ok().flatten();
+
+    if let Some(original) = original {
+        save(key, &original).await;
+    }
+
+    defmt::assert_eq!(
+        loaded,
+        Some(value),
+        "{} did not survive the round trip",
+        key
+    );
+}
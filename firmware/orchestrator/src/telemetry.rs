@@ -1,83 +1,250 @@
 use crate::{
     logic::interlock::update_monitor_severity,
-    self_telemetry::{DATA_POINTS_DISCARDED_FORMAT_FAIL, DATA_POINTS_DISCARDED_QUEUE_FULL},
+    self_telemetry::{
+        DATA_POINTS_COALESCED, DATA_POINTS_DISCARDED_FORMAT_FAIL, DATA_POINTS_DISCARDED_QUEUE_FULL,
+        DATA_POINTS_SPILLED,
+    },
     telemetry_bridge_comm::wait_for_telemetry_bridge_ready,
+    telemetry_spool::TelemetrySpool,
 };
-use core::sync::atomic::Ordering;
+use core::{cell::RefCell, fmt::Write, pin::pin, sync::atomic::Ordering};
 use defmt::{debug, info, warn};
 use embassy_net::Stack;
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
-use embassy_time::Timer;
+use embassy_rp::clocks::RoscRng;
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    channel::Channel,
+    signal::Signal,
+};
+use embassy_time::{Duration, Instant, Timer, WithTimeout};
+use heapless::String;
 use hoshiguma_api::{
     API_PORT, Monitor, Severity, TELEMETRY_BRIDGE_IP_ADDRESS,
     telemetry_bridge::{
-        FormattedTelemetryDataPoint, TELEMETRY_DATA_POINT_MAX_LEN, request, response,
+        FormattedTelemetryDataPoint, TELEMETRY_DATA_POINT_MAX_LEN, TelemetryDataPointBatch,
+        request, response,
     },
 };
-use hoshiguma_common::{network::send_request, telemetry::FormatInfluxResult};
+use hoshiguma_common::{
+    network::send_request,
+    telemetry::{FormatInfluxResult, has_timestamp},
+    telemetry_queue::{QueueOutcome, QueuedDataPoint, TelemetryQueue},
+};
+
+pub(crate) use hoshiguma_common::telemetry_queue::TelemetryPriority;
+
+const QUEUE_CAPACITY: usize = 64;
+
+/// Maximum time to wait for the wall time to be known once the telemetry bridge is available.
+///
+/// Data points queued before the wall time is known are given a timestamp once it is, if it is not
+/// known by this point they are sent without one.
+const WALL_TIME_SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Adds a timestamp to an Influx line that does not have one, using the wall time at the uptime
+/// the data point was queued at.
+///
+/// Does nothing if the wall time is not yet known or the timestamp does not fit.
+pub(crate) fn backfill_timestamp(
+    line: &mut String<TELEMETRY_DATA_POINT_MAX_LEN>,
+    queued_at: Instant,
+) {
+    if has_timestamp(line) {
+        return;
+    }
+
+    if let Some(timestamp) =
+        crate::wall_time::at(queued_at).and_then(|timestamp| timestamp.timestamp_nanos_opt())
+    {
+        // Only add the timestamp if all of it fits
+        let mut suffix = String::<24>::new();
+        if suffix.write_fmt(format_args!(" {timestamp}")).is_ok() {
+            let _ = line.push_str(&suffix);
+        }
+    }
+}
+
+static QUEUE: CriticalSectionMutex<RefCell<TelemetryQueue<QUEUE_CAPACITY>>> =
+    CriticalSectionMutex::new(RefCell::new(TelemetryQueue::new()));
 
-static TELEMETRY_TX: Channel<CriticalSectionRawMutex, FormattedTelemetryDataPoint, 64> =
+static DATA_POINT_QUEUED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Data points moved out of the queue when it is full, waiting for the telemetry task to add them
+/// to the spool.
+///
+/// Adding to the spool is left to the telemetry task, as it is too slow to do while the queue is
+/// locked.
+pub(crate) static SPILLED: Channel<CriticalSectionRawMutex, QueuedDataPoint, SPILLED_CAPACITY> =
     Channel::new();
 
+const SPILLED_CAPACITY: usize = 4;
+
 pub(crate) fn queue_telemetry_data_point(
+    priority: TelemetryPriority,
     data_point: FormatInfluxResult<TELEMETRY_DATA_POINT_MAX_LEN>,
 ) {
     if let Ok(data_point) = data_point {
-        if let Err(e) = TELEMETRY_TX.try_send(FormattedTelemetryDataPoint(data_point)) {
-            warn!("Data point discarded: {}", e);
-            DATA_POINTS_DISCARDED_QUEUE_FULL.fetch_add(1, Ordering::Relaxed);
+        let point = QueuedDataPoint {
+            priority,
+            data_point: FormattedTelemetryDataPoint(data_point),
+            queued_at: Instant::now(),
+        };
+
+        let mut spilled = None;
+
+        match QUEUE.lock(|queue| queue.borrow_mut().push(point, |p| spilled = Some(p))) {
+            QueueOutcome::Queued => {}
+            QueueOutcome::Coalesced => {
+                DATA_POINTS_COALESCED.fetch_add(1, Ordering::Relaxed);
+            }
+            QueueOutcome::Displaced => {
+                warn!("Data point discarded: displaced by {}", priority);
+                DATA_POINTS_DISCARDED_QUEUE_FULL.fetch_add(1, Ordering::Relaxed);
+            }
+            QueueOutcome::Spilled => {
+                if let Some(spilled) = spilled
+                    && SPILLED.try_send(spilled).is_ok()
+                {
+                    debug!("Queue full, moved data point to spool");
+                    DATA_POINTS_SPILLED.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    warn!("Data point discarded: queue and spill buffer full");
+                    DATA_POINTS_DISCARDED_QUEUE_FULL.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            QueueOutcome::Discarded => {
+                warn!("Data point discarded: queue full");
+                DATA_POINTS_DISCARDED_QUEUE_FULL.fetch_add(1, Ordering::Relaxed);
+            }
         }
+
+        DATA_POINT_QUEUED.signal(());
     } else {
         warn!("Data point discarded: failed to format data point");
         DATA_POINTS_DISCARDED_FORMAT_FAIL.fetch_add(1, Ordering::Relaxed);
     }
 }
 
+/// Waits for a data point to be queued, then takes the one that was queued earliest.
+pub(crate) async fn receive_oldest_data_point() -> QueuedDataPoint {
+    loop {
+        if let Some(point) = QUEUE.lock(|queue| queue.borrow_mut().pop_oldest()) {
+            return point;
+        }
+
+        DATA_POINT_QUEUED.wait().await;
+    }
+}
+
+/// Waits for data points to be queued, then takes as many as will fit in a single batch.
+async fn receive_batch() -> TelemetryDataPointBatch {
+    let mut batch = TelemetryDataPointBatch::default();
+
+    loop {
+        while let Some(point) = QUEUE.lock(|queue| {
+            queue.borrow_mut().pop_if(|p| {
+                backfill_timestamp(&mut p.data_point.0, p.queued_at);
+                batch.fits(&p.data_point)
+            })
+        }) {
+            batch.push(point.data_point).unwrap();
+        }
+
+        if !batch.is_empty() {
+            return batch;
+        }
+
+        DATA_POINT_QUEUED.wait().await;
+    }
+}
+
 #[embassy_executor::task]
 pub(super) async fn task(stack: Stack<'static>) {
     #[cfg(feature = "trace")]
     crate::trace::name_task("telemetry").await;
 
-    let telem_rx = TELEMETRY_TX.receiver();
+    let mut spool = TelemetrySpool::new().await;
+    let mut batch_being_sent: Option<request::SendTelemetryDataPointBatch> = None;
 
-    let mut data_point_being_sent: Option<FormattedTelemetryDataPoint> = None;
+    // Start from a random sequence number so that the first batch after a reboot is not mistaken
+    // for a resend of the last batch before it
+    let mut sequence = RoscRng.next_u32();
 
     'connection: loop {
         // Report telemetry inoperative
         update_monitor_severity(Monitor::TelemetryBridgeCommunication, Severity::Information).await;
 
-        // Wait for telemetry bridge to come online
-        wait_for_telemetry_bridge_ready(stack).await;
+        // Wait for telemetry bridge to come online and for the wall time to be known, spooling
+        // data points in the meantime.
+        // This is scoped so that the connection attempt is not held for the rest of the loop.
+        {
+            let ready = pin!(async {
+                wait_for_telemetry_bridge_ready(stack).await;
+
+                if crate::wall_time::wait_for_sync()
+                    .with_timeout(WALL_TIME_SYNC_TIMEOUT)
+                    .await
+                    .is_err()
+                {
+                    warn!("Wall time is not known, data points will be sent without timestamps");
+                }
+            });
+            spool.spool_queued_data_points_until(ready).await;
+        }
 
         // Report telemetry ready
         update_monitor_severity(Monitor::TelemetryBridgeCommunication, Severity::Normal).await;
 
-        // Receive data points from queue
+        // Receive batches of data points from queue
         loop {
-            let data_point: FormattedTelemetryDataPoint = match data_point_being_sent {
-                Some(ref point) => {
+            let batch = match batch_being_sent {
+                Some(ref batch) => {
                     // Small delay because things don't fail for no reason
                     Timer::after_millis(100).await;
 
-                    debug!("Retrying failed data point");
-                    point.clone()
+                    debug!("Retrying failed batch");
+                    batch
+                }
+                None => {
+                    // Anything spooled is older than what is currently queued
+                    let batch = match spool.take_batch().await {
+                        Some(batch) => batch,
+                        None => receive_batch().await,
+                    };
+                    sequence = sequence.wrapping_add(1);
+                    batch_being_sent
+                        .insert(request::SendTelemetryDataPointBatch { sequence, batch })
                 }
-                None => telem_rx.receive().await,
             };
 
-            info!("Sending data point: {}", data_point.0);
-            match send_request::<_, response::TelemetryDataPointAck>(
+            info!(
+                "Sending batch {} of {} data points",
+                batch.sequence,
+                batch.batch.len()
+            );
+            for data_point in batch.batch.data_points() {
+                debug!("Sending data point: {}", data_point.0);
+            }
+
+            match send_request::<_, response::TelemetryDataPointBatchAck>(
                 stack,
                 TELEMETRY_BRIDGE_IP_ADDRESS,
                 API_PORT,
                 5,
-                &request::SendTelemetryDataPoint(data_point),
+                batch,
             )
             .await
             {
-                Ok(_) => {
-                    debug!("Data point ack");
-                    data_point_being_sent.take();
+                Ok(ack) if ack.0 == batch.sequence => {
+                    debug!("Batch ack");
+                    batch_being_sent.take();
+                    spool.commit_batch().await;
+                }
+                Ok(ack) => {
+                    warn!(
+                        "Received ack for batch {} while sending batch {}",
+                        ack.0, batch.sequence
+                    );
                 }
                 Err(e) => {
                     warn!("Failed to send request: {}", e);
@@ -0,0 +1,620 @@
+//! Retains data points while the telemetry bridge is unavailable, so that they can be replayed
+//! once it is available again.
+//!
+//! Data points are spooled in the order they were queued, except for those moved out of the
+//! telemetry queue when it is full, which are spooled as soon as they are moved.
+//! Spooled data points are replayed in the order they were spooled.
+//!
+//! Data points are held in a RAM ring, with the `telemetry-spool-flash` feature they are moved from
+//! there into a region of flash, allowing much longer outages to be covered and spooled data points
+//! to survive a reboot.
+//! To limit flash writes, data points are moved to flash several at a time, once the RAM ring is
+//! half full or has held data points for `flash::WRITE_INTERVAL`.
+//!
+//! When there is no room, the oldest of the lowest priority data points are discarded first.
+//! Data points taken to be sent remain in the spool until the batch they are sent in is
+//! acknowledged.
+//!
+//! Each data point is stored along with the uptime at which it was queued, so that data points
+//! queued without a timestamp are given one on replay and appear at the right place in the time
+//! series.
+
+use crate::{
+    self_telemetry::{
+        DATA_POINTS_DISCARDED_SPOOL_FULL, DATA_POINTS_DISCARDED_SPOOL_UNREPLAYABLE,
+        DATA_POINTS_REPLAYED, DATA_POINTS_SPOOLED,
+    },
+    telemetry::{SPILLED, backfill_timestamp, receive_oldest_data_point},
+};
+use core::{ops::Range, sync::atomic::Ordering};
+use defmt::{debug, warn};
+use embassy_rp::clocks::RoscRng;
+use embassy_sync::lazy_lock::LazyLock;
+use embassy_time::Instant;
+use heapless::String;
+use hoshiguma_api::telemetry_bridge::{
+    FormattedTelemetryDataPoint, TELEMETRY_DATA_POINT_MAX_LEN, TelemetryDataPointBatch,
+};
+use hoshiguma_common::{
+    telemetry::has_timestamp,
+    telemetry_queue::{QueuedDataPoint, TelemetryPriority},
+};
+use static_cell::ConstStaticCell;
+
+/// Size of the RAM ring.
+///
+/// When spooling to flash this only needs to hold data points until they are written to flash.
+#[cfg(not(feature = "telemetry-spool-flash"))]
+const RAM_SIZE: usize = 8 * 1024;
+#[cfg(feature = "telemetry-spool-flash")]
+const RAM_SIZE: usize = 2 * 1024;
+
+/// Size of the header stored before each data point, the priority, the boot ID and the uptime it
+/// was queued at.
+const HEADER_LEN: usize = 1 + 4 + 8;
+
+const ENTRY_MAX_LEN: usize = HEADER_LEN + TELEMETRY_DATA_POINT_MAX_LEN;
+
+const PRIORITY_COUNT: usize = TelemetryPriority::Event as usize + 1;
+
+/// Identifies the current boot, uptimes recorded in a previous boot cannot be used to give a data
+/// point a timestamp.
+static BOOT_ID: LazyLock<u32> = LazyLock::new(|| RoscRng.next_u32());
+
+/// Only accessed by the telemetry task, held in a static so that it is not part of the task.
+static RAM: ConstStaticCell<RamRing> = ConstStaticCell::new(RamRing::new());
+
+pub(crate) struct TelemetrySpool {
+    ram: &'static mut RamRing,
+    #[cfg(feature = "telemetry-spool-flash")]
+    flash: flash::Queue,
+}
+
+impl TelemetrySpool {
+    pub(crate) async fn new() -> Self {
+        Self {
+            ram: RAM.take(),
+            #[cfg(feature = "telemetry-spool-flash")]
+            flash: flash::new_queue().await,
+        }
+    }
+
+    /// Moves queued data points into the spool, until `until` completes.
+    ///
+    /// `until` is not polled while data points are being written to flash, so that a write is
+    /// never interrupted.
+    pub(crate) async fn spool_queued_data_points_until(&mut self, mut until: impl Future + Unpin) {
+        use embassy_futures::select::{Either3, select3};
+
+        #[cfg(not(feature = "telemetry-spool-flash"))]
+        loop {
+            // Spilled data points are older than anything still queued
+            match select3(&mut until, SPILLED.receive(), receive_oldest_data_point()).await {
+                Either3::First(_) => return,
+                Either3::Second(point) | Either3::Third(point) => self.spool(point),
+            }
+        }
+
+        #[cfg(feature = "telemetry-spool-flash")]
+        {
+            use embassy_futures::select::{Either, select};
+            use embassy_time::Timer;
+
+            let mut write_at = Instant::now() + flash::WRITE_INTERVAL;
+
+            loop {
+                match select(
+                    select3(&mut until, SPILLED.receive(), receive_oldest_data_point()),
+                    Timer::at(write_at),
+                )
+                .await
+                {
+                    Either::First(Either3::First(_)) => return,
+                    Either::First(Either3::Second(point) | Either3::Third(point)) => {
+                        self.spool(point);
+
+                        if !self.ram.is_half_full() {
+                            continue;
+                        }
+                    }
+                    Either::Second(()) => {}
+                }
+
+                self.flash.write_from_ram(self.ram).await;
+                write_at = Instant::now() + flash::WRITE_INTERVAL;
+            }
+        }
+    }
+
+    /// Takes as many spooled data points as will fit in a single batch, oldest first.
+    ///
+    /// The data points remain in the spool until `commit_batch` is called, once the batch has been
+    /// acknowledged.
+    /// Returns `None` if the spool is empty.
+    pub(crate) async fn take_batch(&mut self) -> Option<TelemetryDataPointBatch> {
+        while let Ok(point) = SPILLED.try_receive() {
+            self.spool(point);
+        }
+
+        let mut batch = TelemetryDataPointBatch::default();
+
+        #[cfg(feature = "telemetry-spool-flash")]
+        {
+            // Stop data points building up in RAM if the telemetry bridge is not keeping up
+            if self.ram.is_half_full() {
+                self.flash.write_from_ram(self.ram).await;
+            }
+
+            // Anything in flash is older than what is in RAM
+            while self
+                .flash
+                .take(|entry| {
+                    if !take_entry(&mut batch, entry) {
+                        // Items are written such that their entries always fit in a single batch
+                        warn!("Discarding spooled data point that does not fit in the batch");
+                        DATA_POINTS_DISCARDED_SPOOL_UNREPLAYABLE.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+                .await
+            {
+                if !batch.is_empty() {
+                    return Some(batch);
+                }
+
+                // Nothing taken from flash could be replayed
+                self.flash.commit().await;
+            }
+        }
+
+        self.ram.take(|entry| take_entry(&mut batch, entry));
+
+        if batch.is_empty() {
+            // Nothing taken could be replayed
+            self.ram.commit();
+            return None;
+        }
+
+        Some(batch)
+    }
+
+    /// Removes the data points taken by `take_batch` from the spool.
+    pub(crate) async fn commit_batch(&mut self) {
+        #[cfg(feature = "telemetry-spool-flash")]
+        self.flash.commit().await;
+
+        self.ram.commit();
+    }
+
+    /// Adds a data point to the spool, discarding lower priority data points if there is no room.
+    fn spool(&mut self, point: QueuedDataPoint) {
+        let mut entry = heapless::Vec::<u8, ENTRY_MAX_LEN>::new();
+        entry.push(point.priority as u8).unwrap();
+        entry
+            .extend_from_slice(&BOOT_ID.get().to_le_bytes())
+            .unwrap();
+        entry
+            .extend_from_slice(&point.queued_at.as_micros().to_le_bytes())
+            .unwrap();
+        entry
+            .extend_from_slice(point.data_point.0.as_bytes())
+            .unwrap();
+
+        match self.ram.push(&entry) {
+            Ok(discarded) => {
+                debug!("Spooled data point: {}", point.data_point.0);
+                DATA_POINTS_SPOOLED.fetch_add(1, Ordering::Relaxed);
+
+                if discarded > 0 {
+                    warn!("Discarded {} spooled data points", discarded);
+                    DATA_POINTS_DISCARDED_SPOOL_FULL.fetch_add(discarded, Ordering::Relaxed);
+                }
+            }
+            Err(SpoolFull) => {
+                warn!("Data point discarded: spool full");
+                DATA_POINTS_DISCARDED_SPOOL_FULL.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Adds the data point from a spooled entry to a batch.
+///
+/// Returns false if the batch is full, in which case the entry should not be taken.
+fn take_entry(batch: &mut TelemetryDataPointBatch, entry: &[u8]) -> bool {
+    match decode(entry) {
+        Some(data_point) => {
+            if batch.push(data_point).is_err() {
+                return false;
+            }
+            DATA_POINTS_REPLAYED.fetch_add(1, Ordering::Relaxed);
+        }
+        None => {
+            warn!("Discarding spooled data point that cannot be replayed");
+            DATA_POINTS_DISCARDED_SPOOL_UNREPLAYABLE.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    true
+}
+
+/// Recovers a data point from a spooled entry, giving it a timestamp if it does not have one.
+///
+/// Returns `None` if the entry is malformed, or has no timestamp and was spooled in a previous
+/// boot.
+fn decode(entry: &[u8]) -> Option<FormattedTelemetryDataPoint> {
+    let (_priority, entry) = entry.split_first_chunk::<1>()?;
+    let (boot_id, entry) = entry.split_first_chunk::<4>()?;
+    let (queued_at, line) = entry.split_first_chunk::<8>()?;
+
+    let mut line: String<TELEMETRY_DATA_POINT_MAX_LEN> =
+        core::str::from_utf8(line).ok()?.try_into().ok()?;
+
+    if !has_timestamp(&line) {
+        if u32::from_le_bytes(*boot_id) != *BOOT_ID.get() {
+            return None;
+        }
+
+        backfill_timestamp(
+            &mut line,
+            Instant::from_micros(u64::from_le_bytes(*queued_at)),
+        );
+    }
+
+    Some(FormattedTelemetryDataPoint(line))
+}
+
+/// Ranges of the frames in a sequence of entries, each prefixed with its length.
+fn frames(bytes: &[u8]) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut offset = 0;
+
+    core::iter::from_fn(move || {
+        let frame = frame_at(bytes, offset)?;
+        offset = frame.end;
+        Some(frame)
+    })
+}
+
+/// Range of the frame starting at `offset`.
+fn frame_at(bytes: &[u8], offset: usize) -> Option<Range<usize>> {
+    let len = bytes.get(offset..offset + 2)?;
+    let end = offset + 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    Some(offset..end.min(bytes.len()))
+}
+
+/// The entry held in a frame.
+fn entry(bytes: &[u8], frame: Range<usize>) -> &[u8] {
+    &bytes[frame.start + 2..frame.end]
+}
+
+/// There was no room for an entry, even after discarding all lower priority entries.
+struct SpoolFull;
+
+/// Fixed size buffer of variable length entries, each prefixed with its length, oldest first.
+struct RamRing {
+    bytes: heapless::Vec<u8, RAM_SIZE>,
+    /// Number of entries at the front that have been taken and not yet committed.
+    taken: usize,
+}
+
+impl RamRing {
+    const fn new() -> Self {
+        Self {
+            bytes: heapless::Vec::new(),
+            taken: 0,
+        }
+    }
+
+    /// Adds an entry, returning the number of entries that were discarded to make room for it.
+    ///
+    /// The oldest of the lowest priority entries are discarded first, if there would not be room
+    /// after discarding every entry of the same or a lower priority then nothing is discarded and
+    /// the new entry is rejected instead.
+    /// Taken entries are never discarded.
+    fn push(&mut self, new_entry: &[u8]) -> Result<usize, SpoolFull> {
+        let frame_len = new_entry.len() + 2;
+        let mut needed = frame_len.saturating_sub(RAM_SIZE - self.bytes.len());
+        let mut discarded = 0;
+
+        if needed > 0 {
+            let priority = new_entry[0] as usize;
+            let taken_end = self.taken_end();
+
+            // Space that could be freed at each priority
+            let mut freeable = [0; PRIORITY_COUNT];
+            for frame in frames(&self.bytes[taken_end..]) {
+                if let Some(freeable) =
+                    freeable.get_mut(self.bytes[taken_end + frame.start + 2] as usize)
+                {
+                    *freeable += frame.len();
+                }
+            }
+
+            // Every entry below the cutoff priority is discarded, along with the oldest entries of
+            // the cutoff priority until enough space has been freed
+            let mut cutoff = None;
+            for (p, &freeable) in freeable.iter().enumerate().take(priority + 1) {
+                if freeable >= needed {
+                    cutoff = Some(p);
+                    break;
+                }
+                needed -= freeable;
+            }
+
+            let Some(cutoff) = cutoff else {
+                return Err(SpoolFull);
+            };
+
+            // Remove the discarded entries in a single pass
+            let mut read = taken_end;
+            let mut write = taken_end;
+
+            while let Some(frame) = frame_at(&self.bytes, read) {
+                let p = self.bytes[frame.start + 2] as usize;
+                read = frame.end;
+
+                if p < cutoff || (p == cutoff && needed > 0) {
+                    if p == cutoff {
+                        needed = needed.saturating_sub(frame.len());
+                    }
+                    discarded += 1;
+                } else {
+                    let len = frame.len();
+                    self.bytes.copy_within(frame, write);
+                    write += len;
+                }
+            }
+
+            self.bytes.truncate(write);
+        }
+
+        // There is always room by this point
+        let _ = self
+            .bytes
+            .extend_from_slice(&(new_entry.len() as u16).to_le_bytes());
+        let _ = self.bytes.extend_from_slice(new_entry);
+
+        Ok(discarded)
+    }
+
+    /// Takes entries after those already taken, oldest first, for as long as `f` accepts them.
+    fn take(&mut self, mut f: impl FnMut(&[u8]) -> bool) {
+        for frame in frames(&self.bytes).skip(self.taken) {
+            if !f(entry(&self.bytes, frame)) {
+                break;
+            }
+            self.taken += 1;
+        }
+    }
+
+    /// Removes the taken entries.
+    fn commit(&mut self) {
+        let end = self.taken_end();
+        self.remove(0..end);
+        self.taken = 0;
+    }
+
+    /// Copies entries after those taken into `item`, for as long as `accept` accepts them,
+    /// returning the number of entries copied.
+    #[cfg(feature = "telemetry-spool-flash")]
+    fn copy_untaken_into<const N: usize>(
+        &self,
+        item: &mut heapless::Vec<u8, N>,
+        mut accept: impl FnMut(&[u8]) -> bool,
+    ) -> usize {
+        let start = self.taken_end();
+        let mut count = 0;
+
+        for frame in frames(&self.bytes[start..]) {
+            let frame = start + frame.start..start + frame.end;
+
+            if !accept(entry(&self.bytes, frame.clone()))
+                || item.extend_from_slice(&self.bytes[frame]).is_err()
+            {
+                break;
+            }
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Removes the first `count` entries after those taken.
+    #[cfg(feature = "telemetry-spool-flash")]
+    fn remove_untaken(&mut self, count: usize) {
+        let start = self.taken_end();
+        let end = frames(&self.bytes[start..])
+            .take(count)
+            .last()
+            .map_or(start, |frame| start + frame.end);
+        self.remove(start..end);
+    }
+
+    #[cfg(feature = "telemetry-spool-flash")]
+    fn is_half_full(&self) -> bool {
+        self.bytes.len() >= RAM_SIZE / 2
+    }
+
+    fn taken_end(&self) -> usize {
+        frames(&self.bytes)
+            .take(self.taken)
+            .last()
+            .map_or(0, |frame| frame.end)
+    }
+
+    fn remove(&mut self, range: Range<usize>) {
+        let len = self.bytes.len();
+        self.bytes.copy_within(range.end.., range.start);
+        self.bytes.truncate(len - range.len());
+    }
+}
+
+#[cfg(feature = "telemetry-spool-flash")]
+mod flash {
+    use super::{HEADER_LEN, RamRing, entry, frames};
+    use crate::storage::{FlashPartition, take_telemetry_spool_partition};
+    use defmt::warn;
+    use embassy_time::Duration;
+    use hoshiguma_api::{
+        MESSAGE_PAYLOAD_CAPACITY, telemetry_bridge::TELEMETRY_DATA_POINT_BATCH_CAPACITY,
+    };
+    use hoshiguma_common::telemetry::has_timestamp;
+    use sequential_storage::{
+        cache::{Cache, Uncached},
+        queue::{QueueConfig, QueueStorage},
+    };
+
+    /// Maximum time data points are held in RAM before being written to flash.
+    pub(super) const WRITE_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Maximum size of a flash item.
+    ///
+    /// Items are limited to what fits in a single batch, this is the most that can take.
+    const ITEM_MAX_LEN: usize =
+        TELEMETRY_DATA_POINT_BATCH_CAPACITY * (2 + HEADER_LEN) + MESSAGE_PAYLOAD_CAPACITY;
+
+    /// Most that a data point can grow by when given a timestamp on replay, a space and a signed
+    /// 64 bit integer.
+    const TIMESTAMP_MAX_LEN: usize = 1 + 20;
+
+    /// Each item holds as many entries as will fit in a single batch, so that an item can be
+    /// removed as a whole once the batch it was sent in is acknowledged.
+    ///
+    /// This avoids having to track, in flash, how much of an item has been delivered.
+    pub(super) struct Queue {
+        inner: QueueStorage<FlashPartition, Cache<Uncached, Uncached, Uncached>>,
+        /// Header of the first entry of the item that was taken, to check that it was not
+        /// overwritten before being removed.
+        taken: Option<[u8; HEADER_LEN]>,
+    }
+
+    pub(super) async fn new_queue() -> Queue {
+        let partition = take_telemetry_spool_partition().await;
+        let config = QueueConfig::new(0..partition.size());
+
+        Queue {
+            inner: QueueStorage::new(partition, config, Cache::new_uncached()),
+            taken: None,
+        }
+    }
+
+    impl Queue {
+        /// Moves every entry in the RAM ring that has not been taken into flash.
+        ///
+        /// Entries are only removed from RAM once they have been written.
+        /// Entries discarded from flash when it is full are erased a page at a time and cannot be
+        /// counted.
+        pub(super) async fn write_from_ram(&mut self, ram: &mut RamRing) {
+            loop {
+                let mut item = heapless::Vec::<u8, ITEM_MAX_LEN>::new();
+                let mut batch = BatchSize::default();
+                let count = ram.copy_untaken_into(&mut item, |entry| batch.add(entry));
+
+                if count == 0 {
+                    break;
+                }
+
+                if let Err(e) = self.inner.push(&item, true).await {
+                    warn!("Failed to write spooled data points to flash: {}", e);
+                    break;
+                }
+
+                ram.remove_untaken(count);
+            }
+        }
+
+        /// Passes every entry of the oldest item to `f`.
+        ///
+        /// Returns false if there is nothing in flash.
+        pub(super) async fn take(&mut self, mut f: impl FnMut(&[u8])) -> bool {
+            let mut buffer = [0u8; ITEM_MAX_LEN];
+
+            let Some(item) = self.peek(&mut buffer).await else {
+                return false;
+            };
+
+            self.taken = Some(first_header(item));
+
+            for frame in frames(item) {
+                f(entry(item, frame));
+            }
+
+            true
+        }
+
+        /// Removes the taken item.
+        pub(super) async fn commit(&mut self) {
+            let Some(taken) = self.taken.take() else {
+                return;
+            };
+
+            let mut buffer = [0u8; ITEM_MAX_LEN];
+            let Some(item) = self.peek(&mut buffer).await else {
+                return;
+            };
+
+            if first_header(item) != taken {
+                warn!("Spooled data points were overwritten while being sent");
+                return;
+            }
+
+            let mut buffer = [0u8; ITEM_MAX_LEN];
+            if let Err(e) = self.inner.pop(&mut buffer).await {
+                warn!("Failed to remove spooled data points from flash: {}", e);
+            }
+        }
+
+        /// Copies the oldest item into a buffer.
+        async fn peek<'a>(&mut self, buffer: &'a mut [u8]) -> Option<&'a [u8]> {
+            match self.inner.peek(buffer).await {
+                Ok(item) => item.map(|item| &*item),
+                Err(e) => {
+                    warn!("Failed to read spooled data points from flash: {}", e);
+                    None
+                }
+            }
+        }
+    }
+
+    fn first_header(item: &[u8]) -> [u8; HEADER_LEN] {
+        let mut header = [0u8; HEADER_LEN];
+        if let Some(first) = frames(item)
+            .next()
+            .and_then(|frame| entry(item, frame).get(..HEADER_LEN))
+        {
+            header.copy_from_slice(first);
+        }
+        header
+    }
+
+    /// Upper bound on the size of a batch holding the data points of the entries added so far,
+    /// allowing for each data point without a timestamp being given one on replay.
+    #[derive(Default)]
+    struct BatchSize {
+        count: usize,
+        /// The sequence number and batch length, followed by the length and data of each data
+        /// point.
+        len: usize,
+    }
+
+    impl BatchSize {
+        /// Adds an entry, if its data point would still fit in a batch.
+        fn add(&mut self, entry: &[u8]) -> bool {
+            let line = entry.get(HEADER_LEN..).unwrap_or_default();
+            let line_len = if core::str::from_utf8(line).is_ok_and(has_timestamp) {
+                line.len()
+            } else {
+                line.len() + TIMESTAMP_MAX_LEN
+            };
+
+            let len = if self.count == 0 { 5 + 1 } else { self.len } + 2 + line_len;
+
+            if self.count < TELEMETRY_DATA_POINT_BATCH_CAPACITY && len <= MESSAGE_PAYLOAD_CAPACITY {
+                self.count += 1;
+                self.len = len;
+                true
+            } else {
+                false
+            }
+        }
+    }
+}
@@ -4,20 +4,52 @@ use core::sync::atomic::Ordering;
 use defmt::{debug, info};
 use embassy_net::Stack;
 use embassy_time::{Duration, Instant, Timer};
-use portable_atomic::AtomicI64;
+use hoshiguma_api::orchestrator::WallTimeSyncStatus;
+use portable_atomic::{AtomicI64, AtomicU64};
 
 /// Offset in microseconds to add to uptime to get world time.
 static BOOT_CLOCK_WALL_OFFSET_US: AtomicI64 = AtomicI64::new(0);
 
+/// Uptime in microseconds at the last successful sync, zero if there has not been one.
+static LAST_SYNC_UPTIME_US: AtomicU64 = AtomicU64::new(0);
+
 /// Gets the current wall time.
 ///
 /// Time is only valid after a successful NTP sync.
 pub(crate) fn now() -> Option<DateTime<Utc>> {
-    let now = Instant::now().as_micros() as i64;
+    at(Instant::now())
+}
+
+/// Gets the wall time at a given uptime.
+///
+/// Time is only valid after a successful NTP sync.
+pub(crate) fn at(uptime: Instant) -> Option<DateTime<Utc>> {
+    let uptime = uptime.as_micros() as i64;
     let offset = BOOT_CLOCK_WALL_OFFSET_US.load(Ordering::Relaxed);
     match offset {
         0 => None,
-        _ => Some(DateTime::from_timestamp_micros(now + offset).unwrap()),
+        _ => Some(DateTime::from_timestamp_micros(uptime + offset).unwrap()),
+    }
+}
+
+/// Waits until the wall time is known.
+pub(crate) async fn wait_for_sync() {
+    while now().is_none() {
+        Timer::after_secs(1).await;
+    }
+}
+
+/// Gets the status of the synchronisation of the wall time.
+pub(crate) fn sync_status() -> WallTimeSyncStatus {
+    let last_sync = LAST_SYNC_UPTIME_US.load(Ordering::Relaxed);
+
+    WallTimeSyncStatus {
+        uptime: Instant::now().duration_since(Instant::MIN).into(),
+        wall_time: now(),
+        last_sync: match last_sync {
+            0 => None,
+            _ => Some(core::time::Duration::from_micros(last_sync)),
+        },
     }
 }
 
@@ -42,6 +74,7 @@ pub(super) async fn task(stack: Stack<'static>) -> ! {
             );
 
             BOOT_CLOCK_WALL_OFFSET_US.store(offset, Ordering::Relaxed);
+            LAST_SYNC_UPTIME_US.store(now.as_micros(), Ordering::Relaxed);
             info!("Time now: {}", self::now());
 
             last_sync = Some(now);
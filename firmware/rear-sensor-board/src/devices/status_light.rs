@@ -40,6 +40,7 @@ pub(crate) async fn task(r: StatusLightResources, comm: [MyChannelSide; NUM_LIST
     let mut red = Output::new(r.red, Level::Low);
     let mut amber = Output::new(r.amber, Level::Low);
     let mut green = Output::new(r.green, Level::Low);
+    let mut buzzer = Output::new(r.buzzer, Level::Low);
 
     let mut settings = StatusLightSettings::default();
     let mut time_zero = Instant::now();
@@ -67,6 +68,7 @@ pub(crate) async fn task(r: StatusLightResources, comm: [MyChannelSide; NUM_LIST
                     (&mut red, &settings.red),
                     (&mut amber, &settings.amber),
                     (&mut green, &settings.green),
+                    (&mut buzzer, &settings.buzzer),
                 ] {
                     let state = pattern.state_at_time(now);
                     output.set_level(match state {
@@ -48,6 +48,7 @@ assign_resources! {
         red: PIN_15,
         amber: PIN_14,
         green: PIN_13,
+        buzzer: PIN_12,
     },
     sdp810: Sdp810Resources {
         i2c: I2C0,
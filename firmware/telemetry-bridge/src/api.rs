@@ -1,6 +1,8 @@
 use crate::telemetry_tx::TELEMETRY_TX;
-use defmt::warn;
+use core::cell::Cell;
+use defmt::{debug, warn};
 use embassy_net::Stack;
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
 use hoshiguma_api::{
     Message,
     telemetry_bridge::{request, response},
@@ -9,6 +11,13 @@ use hoshiguma_common::network::message_handler_loop;
 
 pub(crate) const NUM_LISTENERS: usize = 2;
 
+/// Sequence number of the most recently received batch of data points.
+///
+/// A batch is resent if its ack is lost, this allows the resent batch to be dropped rather than
+/// its data points being published twice.
+static LAST_BATCH_SEQUENCE: CriticalSectionMutex<Cell<Option<u32>>> =
+    CriticalSectionMutex::new(Cell::new(None));
+
 #[embassy_executor::task(pool_size = NUM_LISTENERS)]
 pub(super) async fn task(stack: Stack<'static>, id: usize) {
     let telem_pub = TELEMETRY_TX.publisher().unwrap();
@@ -21,6 +30,23 @@ pub(super) async fn task(stack: Stack<'static>, id: usize) {
         } else if let Ok(data_point) = message.payload::<request::SendTelemetryDataPoint>() {
             telem_pub.publish(data_point.0).await;
             Message::new(&response::TelemetryDataPointAck).ok()
+        } else if let Ok(request) = message.payload::<request::SendTelemetryDataPointBatch>() {
+            let sequence = request.sequence;
+
+            // Checked and updated together, a resend may arrive on another listener while the
+            // original is still being published
+            let resent =
+                LAST_BATCH_SEQUENCE.lock(|last| last.replace(Some(sequence))) == Some(sequence);
+
+            if resent {
+                debug!("Dropping resent batch {}", sequence);
+            } else {
+                for data_point in request.batch {
+                    telem_pub.publish(data_point).await;
+                }
+            }
+
+            Message::new(&response::TelemetryDataPointBatchAck(sequence)).ok()
         } else {
             None
         };